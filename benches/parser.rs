@@ -10,7 +10,7 @@ fn bench_single_instance(b: Bencher) {
     let input = "This is some text. {{ variable }} and {{ toLower(trim(variable)) }}";
 
     let ctx = SrTemplate::default();
-    ctx.add_variable("variable", &"Variable");
+    ctx.add_variable("variable", "Variable");
 
     b.bench(|| {
         ctx.render(input).unwrap();
@@ -20,7 +20,7 @@ fn bench_single_instance(b: Bencher) {
 #[divan::bench(args = ["This is some text. {{ variable }} and {{ toLower(trim(variable)) }}"])]
 fn bench_iter_instance(input: &str) {
     let ctx = SrTemplate::default();
-    ctx.add_variable("variable", &"Variable");
+    ctx.add_variable("variable", "Variable");
     ctx.render(input).unwrap();
 }
 
@@ -29,8 +29,32 @@ fn bench_clone_instance(b: Bencher) {
     let input = "This is some text. {{ variable }} and {{ toLower(trim(variable)) }}";
 
     let ctx = SrTemplate::default();
-    ctx.add_variable("variable", &"Variable");
+    ctx.add_variable("variable", "Variable");
 
     b.with_inputs(|| ctx.clone())
         .bench_values(|ctx| ctx.render(input).unwrap())
 }
+
+mod compiled {
+    use divan::Bencher;
+    use srtemplate::SrTemplate;
+
+    const INPUT: &str = "This is some text. {{ variable }} and {{ toLower(trim(variable)) }}";
+
+    #[divan::bench]
+    fn uncompiled(b: Bencher) {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("variable", "Variable");
+
+        b.bench(|| ctx.render(INPUT).unwrap())
+    }
+
+    #[divan::bench]
+    fn compiled(b: Bencher) {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("variable", "Variable");
+        let template = ctx.compile(INPUT).unwrap();
+
+        b.bench(|| template.render(&ctx).unwrap())
+    }
+}
@@ -14,6 +14,7 @@ fn main() {
                 println!("Function not supported: {e}")
             }
             srtemplate::Error::Function(e) => println!("Error procesing function: {e}"),
+            e => println!("Error rendering template: {e}"),
         },
     }
 }
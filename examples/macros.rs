@@ -1,8 +1,8 @@
 use srtemplate::{function, SrTemplate, Variable};
 
 #[function]
-fn merge(name: String, age: u8) {
-    Ok(format!("{name}_{age}"))
+fn merge(name: String, age: u8, #[default('_')] separator: char) {
+    Ok(format!("{name}{separator}{age}"))
 }
 
 #[derive(Variable)]
@@ -26,14 +26,17 @@ impl Default for User {
 
 fn main() {
     let ctx = SrTemplate::default();
-    ctx.add_variable("var", &"mUnDo");
-    ctx.add_variable("other", &255u8);
+    ctx.add_variable("var", "mUnDo");
+    ctx.add_variable("other", 255u8);
 
     ctx.add_function("merge", merge);
+    ctx.add_signature("merge", merge_signature());
 
-    ctx.add(&User::default());
+    let user = User::default();
+    ctx.add(&user);
+    ctx.add_variable("age", user.age);
 
-    let template = "Hola {{ merge(var, other) }}, {{ user.Name }} {{ user.LastName}}";
+    let template = "Hola {{ merge(var, other) }} {{ merge(var, age=other, separator=\"-\") }}, {{ user.Name }} {{ user.LastName}} ({{ age }})";
 
     println!("Rendered: {}", ctx.render(template).unwrap());
 }
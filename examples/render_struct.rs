@@ -6,9 +6,9 @@ struct Point {
     y: f32,
 }
 
-impl ToString for Point {
-    fn to_string(&self) -> String {
-        format!("(x: {}, y: {})", self.x, self.y)
+impl std::fmt::Display for Point {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(x: {}, y: {})", self.x, self.y)
     }
 }
 
@@ -17,7 +17,7 @@ fn main() {
 
     let p = Point { x: 0.0, y: 5.025 };
 
-    ctx.add_variable("point", &p);
+    ctx.add_variable("point", p);
 
     let template = "Point {{ point }}";
     println!("Rendered: {}", ctx.render(template).unwrap());
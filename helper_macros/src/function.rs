@@ -1,11 +1,21 @@
 use std::ops::Not;
 
-use proc_macro2::{Ident, TokenStream};
-use quote::quote_spanned;
-use venial::{Error, Function as VenialFunc, TypeExpr};
+use proc_macro2::{Ident, TokenStream, TokenTree};
+use quote::{format_ident, quote, quote_spanned};
+use venial::{AttributeValue, Error, FnTypedParam, Function as VenialFunc, TypeExpr};
+
+/// How a parameter is taken from the arguments of the call.
+enum ParamKind {
+    Required,
+    /// `Option<T>`, holding the tokens of `T`
+    Optional(Vec<TokenTree>),
+    /// `#[default(expr)]`, holding the tokens of `expr`
+    Default(Vec<TokenTree>),
+}
 
 pub fn gen_function(func: VenialFunc) -> Result<TokenStream, Error> {
     let func_name = &func.name;
+    let signature_name = format_ident!("{}_signature", func_name);
 
     let vis = func.vis_marker.as_ref();
 
@@ -16,16 +26,31 @@ pub fn gen_function(func: VenialFunc) -> Result<TokenStream, Error> {
             venial::FnParam::Receiver(receiver) => {
                 Some(Err(Error::new_at_tokens(receiver, "self is not permitted")))
             }
-            venial::FnParam::Typed(venial::FnTypedParam { name, ty, .. }) => {
-                ty.tokens.is_empty().not().then_some(Ok((name, ty)))
-            }
+            venial::FnParam::Typed(param) => param
+                .ty
+                .tokens
+                .is_empty()
+                .not()
+                .then(|| param_kind(param).map(|kind| (&param.name, &param.ty, kind))),
         })
         .collect::<Result<Vec<_>, _>>()?;
     let total_params = params.len();
+    // the optional parameters can be omitted only after the last required one
+    let required_params = params
+        .iter()
+        .rposition(|(_, _, kind)| matches!(kind, ParamKind::Required))
+        .map_or(0, |last| last + 1);
+    let signature = params.iter().map(|(param, _, kind)| {
+        let name = param.to_string();
+        match kind {
+            ParamKind::Required => quote! { .required(#name) },
+            ParamKind::Optional(_) | ParamKind::Default(_) => quote! { .optional(#name) },
+        }
+    });
     let decl = params
-        .into_iter()
+        .iter()
         .enumerate()
-        .map(|(idx, (param, ty))| parse_param(idx, param, ty))
+        .map(|(idx, (param, ty, kind))| parse_param(idx, param, ty, kind))
         .collect::<Vec<TokenStream>>();
 
     let func_body = func
@@ -33,21 +58,181 @@ pub fn gen_function(func: VenialFunc) -> Result<TokenStream, Error> {
         .as_ref()
         .ok_or_else(|| Error::new_at_span(func.span(), "Function should have body"))?;
     let func_body = quote_spanned! {func_body.span() => #func_body};
+    let signature_doc = format!(
+        " The [`Signature`](srtemplate::prelude::Signature) of [`{func_name}`], to call it with named arguments."
+    );
 
     Ok(quote_spanned! { func.span() =>
         #vis fn #func_name(args: &[String]) -> srtemplate::prelude::FuncResult {
-            srtemplate::prelude::validations::args_min_len(args, #total_params)?;
+            srtemplate::prelude::validations::args_min_len(args, #required_params)?;
             srtemplate::prelude::validations::args_max_len(args, #total_params)?;
 
             #(#decl)*
 
             #func_body
         }
+
+        #[doc = #signature_doc]
+        #vis fn #signature_name() -> srtemplate::prelude::Signature {
+            srtemplate::prelude::Signature::new() #(#signature)*
+        }
     })
 }
 
-fn parse_param(idx: usize, param: &Ident, ty: &TypeExpr) -> TokenStream {
-    quote_spanned! {ty.span() =>
-        let #param = args[#idx].parse::<#ty>().map_err(|_| srtemplate::prelude::FromArgsError::ParseFailed(#idx))?;
+fn param_kind(param: &FnTypedParam) -> Result<ParamKind, Error> {
+    let default = param
+        .attributes
+        .iter()
+        .find(|attr| {
+            attr.get_single_path_segment()
+                .is_some_and(|name| name == "default")
+        })
+        .map(|attr| match &attr.value {
+            AttributeValue::Group(_, tokens) | AttributeValue::Equals(_, tokens) => {
+                Ok(tokens.clone())
+            }
+            AttributeValue::Empty => Err(Error::new_at_tokens(
+                attr,
+                "Expected a default value, like #[default(10)]",
+            )),
+        })
+        .transpose()?;
+
+    let tokens = &param.ty.tokens;
+    let option = match tokens.as_slice() {
+        [TokenTree::Ident(name), TokenTree::Punct(open), inner @ .., TokenTree::Punct(close)]
+            if name == "Option" && open.as_char() == '<' && close.as_char() == '>' =>
+        {
+            Some(inner.to_vec())
+        }
+        _ => None,
+    };
+
+    match (option, default) {
+        (Some(_), Some(_)) => Err(Error::new_at_tokens(
+            &param.ty,
+            "Option parameters are already optional, they can not have a default value",
+        )),
+        (Some(inner), None) => Ok(ParamKind::Optional(inner)),
+        (None, Some(default)) => Ok(ParamKind::Default(default)),
+        (None, None) => Ok(ParamKind::Required),
+    }
+}
+
+fn parse_param(idx: usize, param: &Ident, ty: &TypeExpr, kind: &ParamKind) -> TokenStream {
+    let parse_failed = quote! { srtemplate::prelude::FromArgsError::ParseFailed(#idx) };
+    // the omitted arguments are empty texts when an argument after them is given
+    let given = quote! { args.get(#idx).filter(|arg| !arg.is_empty()) };
+
+    match kind {
+        ParamKind::Required => quote_spanned! {ty.span() =>
+            let #param = args[#idx].parse::<#ty>().map_err(|_| #parse_failed)?;
+        },
+        ParamKind::Optional(inner) => {
+            let inner: TokenStream = inner.iter().cloned().collect();
+            quote_spanned! {ty.span() =>
+                let #param: #ty = match #given {
+                    Some(arg) => Some(arg.parse::<#inner>().map_err(|_| #parse_failed)?),
+                    None => None,
+                };
+            }
+        }
+        ParamKind::Default(default) => {
+            let default: TokenStream = default.iter().cloned().collect();
+            quote_spanned! {ty.span() =>
+                let #param: #ty = match #given {
+                    Some(arg) => arg.parse::<#ty>().map_err(|_| #parse_failed)?,
+                    None => #default,
+                };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quote::quote;
+    use venial::{parse_item, Item};
+
+    use super::*;
+
+    fn expand(item: TokenStream) -> Result<String, String> {
+        let Ok(Item::Function(func)) = parse_item(item) else {
+            panic!("Expected a function");
+        };
+        gen_function(func)
+            .map(|tokens| tokens.to_string())
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_optional_params() {
+        let expanded = expand(quote! {
+            fn pad(text: String, #[default(8)] width: usize, fill: Option<char>) {
+                Ok(text)
+            }
+        })
+        .unwrap();
+
+        // the empty arguments are taken as omitted
+        let width = quote! {
+            let width: usize = match args.get(1usize).filter(|arg| !arg.is_empty()) {
+                Some(arg) => arg.parse::<usize>().map_err(|_| srtemplate::prelude::FromArgsError::ParseFailed(1usize))?,
+                None => 8,
+            };
+        };
+        let fill = quote! {
+            let fill: Option<char> = match args.get(2usize).filter(|arg| !arg.is_empty()) {
+                Some(arg) => Some(arg.parse::<char>().map_err(|_| srtemplate::prelude::FromArgsError::ParseFailed(2usize))?),
+                None => None,
+            };
+        };
+        assert!(expanded.contains(&width.to_string()), "{expanded}");
+        assert!(expanded.contains(&fill.to_string()), "{expanded}");
+
+        let signature = quote! {
+            srtemplate::prelude::Signature::new().required("text").optional("width").optional("fill")
+        };
+        assert!(expanded.contains(&signature.to_string()), "{expanded}");
+    }
+
+    #[test]
+    fn test_required_params_count() {
+        // an optional parameter before a required one can not be omitted
+        let expanded = expand(quote! {
+            fn f(a: Option<i32>, b: i32, #[default = 1] c: i32) {
+                Ok(String::new())
+            }
+        })
+        .unwrap();
+
+        let min = quote! { srtemplate::prelude::validations::args_min_len(args, 2usize)?; };
+        let max = quote! { srtemplate::prelude::validations::args_max_len(args, 3usize)?; };
+        assert!(expanded.contains(&min.to_string()), "{expanded}");
+        assert!(expanded.contains(&max.to_string()), "{expanded}");
+        assert!(expanded.contains("None => 1"), "{expanded}");
+    }
+
+    #[test]
+    fn test_invalid_default() {
+        assert_eq!(
+            expand(quote! {
+                fn f(#[default(1)] a: Option<i32>) {
+                    Ok(String::new())
+                }
+            }),
+            Err(
+                "Option parameters are already optional, they can not have a default value"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            expand(quote! {
+                fn f(#[default] a: i32) {
+                    Ok(String::new())
+                }
+            }),
+            Err("Expected a default value, like #[default(10)]".to_string())
+        );
     }
 }
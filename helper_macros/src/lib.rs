@@ -3,7 +3,6 @@ use core::panic;
 use function::gen_function;
 use proc_macro::TokenStream;
 use venial::{parse_item, Error, Item};
-use quote::quote;
 
 mod function;
 mod variable;
@@ -28,7 +27,7 @@ pub fn derive_variable(input: TokenStream) -> TokenStream {
         .into()
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub(crate) enum TextCase {
     Lower,
     Upper,
@@ -65,11 +64,9 @@ impl TextCase {
                 }
             } else if c.is_uppercase() {
                 if let Some(prev) = prev_char {
-                    if prev.is_lowercase() || prev.is_ascii_digit() {
-                        if !current_word.is_empty() {
-                            words.push(current_word);
-                            current_word = String::new();
-                        }
+                    if (prev.is_lowercase() || prev.is_ascii_digit()) && !current_word.is_empty() {
+                        words.push(current_word);
+                        current_word = String::new();
                     }
                 }
                 current_word.push(c);
@@ -153,7 +150,13 @@ impl std::str::FromStr for TextCase {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
+        let normalized = s
+            .chars()
+            .filter(|c| !matches!(c, '_' | '-' | ' '))
+            .collect::<String>()
+            .to_lowercase();
+
+        match normalized.as_str() {
             "lower" | "lowercase" => Ok(TextCase::Lower),
             "upper" | "uppercase" => Ok(TextCase::Upper),
             "sentence" | "sentencecase" => Ok(TextCase::Sentence),
@@ -161,9 +164,7 @@ impl std::str::FromStr for TextCase {
             "camel" | "camelcase" => Ok(TextCase::Camel),
             "pascal" | "pascalcase" => Ok(TextCase::Pascal),
             "kebab" | "kebabcase" => Ok(TextCase::Kebab),
-            "screamingsnake" | "screaming_snake" | "screaming-snake" | "screaming snake" => {
-                Ok(TextCase::ScreamingSnake)
-            }
+            "screamingsnake" | "screamingsnakecase" => Ok(TextCase::ScreamingSnake),
             _ => Err(format!("Invalid text case: {}", s)),
         }
     }
@@ -213,7 +214,7 @@ mod tests {
 
         assert_eq!(
             TextCase::Lower.convert(input),
-            "hello_world-example 123httpprequest"
+            "hello_world-example 123httprequest"
         );
         assert_eq!(
             TextCase::Upper.convert(input),
@@ -48,12 +48,10 @@ fn parse_template_attribute(attr: &Attribute) -> Result<Vec<(String, String)>, E
                         current_key = ident_str;
                     }
                     TokenTree::Punct(p) if p.as_char() == '=' => {}
-                    TokenTree::Literal(lit) => {
-                        if !current_key.is_empty() {
-                            let current_value = lit.to_string().trim_matches('"').to_string();
-                            attributes.push((current_key.clone(), current_value));
-                            current_key.clear();
-                        }
+                    TokenTree::Literal(lit) if !current_key.is_empty() => {
+                        let current_value = lit.to_string().trim_matches('"').to_string();
+                        attributes.push((current_key.clone(), current_value));
+                        current_key.clear();
                     }
                     _ => {}
                 }
@@ -75,10 +73,10 @@ fn derive_struct(struct_decl: &Struct) -> Result<TokenStream, Error> {
         for (key, value) in attrs {
             match key.as_str() {
                 "rename" => {
-                    struct_case = TextCase::from_str(&value).map_err(|e| Error::new(e))?;
+                    struct_case = TextCase::from_str(&value).map_err(Error::new)?;
                 }
                 "rename_fields" => {
-                    field_case = Some(TextCase::from_str(&value).map_err(|e| Error::new(e))?);
+                    field_case = Some(TextCase::from_str(&value).map_err(Error::new)?);
                 }
                 _ => {}
             }
@@ -105,7 +103,7 @@ fn derive_struct(struct_decl: &Struct) -> Result<TokenStream, Error> {
                                 ignore = true;
                             }
                             "rename" => {
-                                let case = TextCase::from_str(&value).map_err(|e| Error::new(e))?;
+                                let case = TextCase::from_str(&value).map_err(Error::new)?;
                                 field_name = case.convert(&field_name);
                             }
                             _ => {}
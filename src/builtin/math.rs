@@ -1,19 +1,196 @@
 #![allow(unused)]
 
 use paste::paste;
-use std::ops::{Add, Div, Mul, Sub};
+use std::any::type_name;
+use std::str::FromStr;
 
 use crate::function::FuncResult;
-use crate::prelude::validations;
+use crate::prelude::{validations, FunctionError};
+
+/// The numbers the math functions work with.
+///
+/// The operations return `None` when the result is out of the range of the type, instead of
+/// overflowing, and floats do it when the result is infinite or not a number.
+trait Number: Copy + Default + PartialOrd + FromStr + ToString {
+    fn add(self, rhs: Self) -> Option<Self>;
+    fn sub(self, rhs: Self) -> Option<Self>;
+    fn mul(self, rhs: Self) -> Option<Self>;
+    fn div(self, rhs: Self) -> Option<Self>;
+    fn rem(self, rhs: Self) -> Option<Self>;
+    fn pow(self, exp: Self) -> Option<Self>;
+    fn abs(self) -> Option<Self>;
+    fn round(self) -> Option<Self>;
+    fn floor(self) -> Option<Self>;
+    fn ceil(self) -> Option<Self>;
+
+    fn min(self, rhs: Self) -> Option<Self> {
+        Some(if rhs < self { rhs } else { self })
+    }
+
+    fn max(self, rhs: Self) -> Option<Self> {
+        Some(if rhs > self { rhs } else { self })
+    }
+}
+
+macro_rules! impl_number {
+    (@int $abs: expr, $( $t: ty ),*) => {
+        $(
+            impl Number for $t {
+                fn add(self, rhs: Self) -> Option<Self> {
+                    self.checked_add(rhs)
+                }
+
+                fn sub(self, rhs: Self) -> Option<Self> {
+                    self.checked_sub(rhs)
+                }
+
+                fn mul(self, rhs: Self) -> Option<Self> {
+                    self.checked_mul(rhs)
+                }
+
+                fn div(self, rhs: Self) -> Option<Self> {
+                    self.checked_div(rhs)
+                }
+
+                fn rem(self, rhs: Self) -> Option<Self> {
+                    self.checked_rem(rhs)
+                }
+
+                fn pow(self, exp: Self) -> Option<Self> {
+                    self.checked_pow(u32::try_from(exp).ok()?)
+                }
+
+                fn abs(self) -> Option<Self> {
+                    $abs(self)
+                }
+
+                fn round(self) -> Option<Self> {
+                    Some(self)
+                }
+
+                fn floor(self) -> Option<Self> {
+                    Some(self)
+                }
+
+                fn ceil(self) -> Option<Self> {
+                    Some(self)
+                }
+            }
+        )*
+    };
+    (@float $( $t: ty ),*) => {
+        $(
+            impl Number for $t {
+                fn add(self, rhs: Self) -> Option<Self> {
+                    Some(self + rhs).filter(|value| value.is_finite())
+                }
+
+                fn sub(self, rhs: Self) -> Option<Self> {
+                    Some(self - rhs).filter(|value| value.is_finite())
+                }
+
+                fn mul(self, rhs: Self) -> Option<Self> {
+                    Some(self * rhs).filter(|value| value.is_finite())
+                }
+
+                fn div(self, rhs: Self) -> Option<Self> {
+                    Some(self / rhs).filter(|value| value.is_finite())
+                }
+
+                fn rem(self, rhs: Self) -> Option<Self> {
+                    Some(self % rhs).filter(|value| value.is_finite())
+                }
+
+                fn pow(self, exp: Self) -> Option<Self> {
+                    Some(self.powf(exp)).filter(|value| value.is_finite())
+                }
+
+                fn abs(self) -> Option<Self> {
+                    Some(<$t>::abs(self))
+                }
+
+                fn round(self) -> Option<Self> {
+                    Some(<$t>::round(self))
+                }
+
+                fn floor(self) -> Option<Self> {
+                    Some(<$t>::floor(self))
+                }
+
+                fn ceil(self) -> Option<Self> {
+                    Some(<$t>::ceil(self))
+                }
+            }
+        )*
+    };
+}
+
+impl_number!(@int Some, u8, u16, u32, u64, u128);
+impl_number!(@int |value: Self| value.checked_abs(), i8, i16, i32, i64, i128);
+impl_number!(@float f32, f64);
+
+/// Parses every argument as a `T`.
+fn parse_args<T: Number>(args: &[String]) -> Result<Vec<T>, FunctionError> {
+    args.iter()
+        .map(|arg| {
+            validations::arg_type::<T>(arg.clone())?;
+            Ok(arg.parse::<T>().unwrap_or_default())
+        })
+        .collect()
+}
+
+fn out_of_range<T>(function: &str, args: &[String]) -> FunctionError {
+    FunctionError::RuntimeError(format!(
+        "{function}({}) is out of the range of {}",
+        args.join(", "),
+        type_name::<T>()
+    ))
+}
+
+/// Applies `operation` to the first argument and each of the next ones in order, so
+/// `sub(10, 3, 2)` is `(10 - 3) - 2`.
+fn fold<T: Number>(
+    function: &str,
+    args: &[String],
+    operation: fn(T, T) -> Option<T>,
+    divides: bool,
+) -> FuncResult {
+    validations::args_min_len(args, 1)?;
+    let values = parse_args::<T>(args)?;
+
+    let mut result = values[0];
+    for value in values.into_iter().skip(1) {
+        if divides && value == T::default() {
+            return Err(FunctionError::RuntimeError(format!(
+                "{function}({}) divides by zero",
+                args.join(", ")
+            )));
+        }
+        result = operation(result, value).ok_or_else(|| out_of_range::<T>(function, args))?;
+    }
+
+    Ok(result.to_string())
+}
+
+/// Applies `operation` to the only argument.
+fn unary<T: Number>(function: &str, args: &[String], operation: fn(T) -> Option<T>) -> FuncResult {
+    validations::args_min_len(args, 1)?;
+    validations::args_max_len(args, 1)?;
+    let values = parse_args::<T>(args)?;
+
+    operation(values[0])
+        .map(|value| value.to_string())
+        .ok_or_else(|| out_of_range::<T>(function, args))
+}
 
 macro_rules! gen_math_fn {
-    ($name: ident, $( $t: ty ),* ) => {
+    ($name: ident => $operation: ident, $divides: expr, $( $t: ty ),* ) => {
         $(
             paste! {
                 /// Perform arithmetic operations on a list of values and return the result as a string.
                 ///
                 /// This function takes a slice of strings, attempts to parse them as values of type `$t`,
-                /// and then applies the `$name` operation to those values.
+                /// and then applies the `$name` operation to the first value and each of the next ones.
                 ///
                 /// # Arguments
                 ///
@@ -26,78 +203,136 @@ macro_rules! gen_math_fn {
                 /// # Errors
                 ///
                 /// This function can return an error of [`crate::function::FunctionError`] variant:
+                /// - `crate::function::FunctionError::ArgumentsIncomplete` if there are no arguments.
                 /// - `crate::function::FunctionError::InvalidType` if any argument cannot be parsed as a value of type `$t`.
-                /// - `crate::function::FunctionError::ConvertArgsFailed` if parsing or conversion from arguments failed.
+                /// - `crate::function::FunctionError::RuntimeError` if the result is out of the range of `$t` or a value is divided by zero.
                 #[cfg_attr(docsrs, doc(cfg(feature = "math")))]
                 #[cfg(feature = "math")]
                 pub fn [<$name _ $t>](args: &[String]) -> FuncResult {
-                    for arg in args {
-                        validations::arg_type::<$t>(arg.clone())?;
-                    }
-                    Ok(args.iter()
-                        .map(|a| a.parse::<$t>().unwrap_or_default())
-                        .fold($t::default(), |acc, x| acc.$name(x))
-                        .to_string())
+                    fold::<$t>(stringify!([<$name _ $t>]), args, Number::$operation, $divides)
                 }
             }
         )*
     };
-    (@$name: ident, $t: ty) => {
-        /// Perform arithmetic operations on a list of values and return the result as a string.
-        ///
-        /// This function takes a slice of strings, attempts to parse them as values of type `$t`,
-        /// and then applies the `$name` operation to those values.
-        ///
-        /// # Arguments
-        ///
-        /// * `args`: A slice of strings representing values to perform the operation on.
-        ///
-        /// # Returns
-        ///
-        /// * A [`FuncResult`] containing the result of the operation as a string.
-        ///
-        /// # Errors
-        ///
-        /// This function can return an error of [`crate::function::FunctionError`] variant:
-        /// - `crate::function::FunctionError::InvalidType` if any argument cannot be parsed as a value of type `$t`.
-        /// - `crate::function::FunctionError::ConvertArgsFailed` if parsing or conversion from arguments failed.
-        #[cfg_attr(docsrs, doc(cfg(feature = "math")))]
-        #[cfg(feature = "math")]
-        pub fn $name(args: &[String]) -> FuncResult {
-            for arg in args {
-                validations::arg_type::<$t>(arg.clone())?;
+    (@$name: ident, $( $t: ty ),* ) => {
+        $(
+            paste! {
+                /// Perform an arithmetic operation on a value and return the result as a string.
+                ///
+                /// This function takes a slice with one string, attempts to parse it as a value of type `$t`,
+                /// and then applies the `$name` operation to that value.
+                ///
+                /// # Arguments
+                ///
+                /// * `args`: A slice with the string representing the value to perform the operation on.
+                ///
+                /// # Returns
+                ///
+                /// * A [`FuncResult`] containing the result of the operation as a string.
+                ///
+                /// # Errors
+                ///
+                /// This function can return an error of [`crate::function::FunctionError`] variant:
+                /// - `crate::function::FunctionError::ArgumentsIncomplete` if there is not exactly one argument.
+                /// - `crate::function::FunctionError::InvalidType` if the argument cannot be parsed as a value of type `$t`.
+                /// - `crate::function::FunctionError::RuntimeError` if the result is out of the range of `$t`.
+                #[cfg_attr(docsrs, doc(cfg(feature = "math")))]
+                #[cfg(feature = "math")]
+                pub fn [<$name _ $t>](args: &[String]) -> FuncResult {
+                    unary::<$t>(stringify!([<$name _ $t>]), args, Number::$name)
+                }
             }
-            Ok(args.iter()
-                .map(|a| a.parse::<$t>().unwrap_or_default())
-                .fold(<$t>::default(), |acc, x| acc.$name(x))
-                .to_string())
-        }
+        )*
     };
 }
 
-gen_math_fn!(add, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
-gen_math_fn!(sub, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
-gen_math_fn!(mul, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
-gen_math_fn!(div, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+gen_math_fn!(add => add, false, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+gen_math_fn!(sub => sub, false, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+gen_math_fn!(mul => mul, false, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+gen_math_fn!(div => div, true, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+gen_math_fn!(mod => rem, true, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+gen_math_fn!(pow => pow, false, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+gen_math_fn!(min => min, false, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+gen_math_fn!(max => max, false, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
 
-gen_math_fn!(@add, i32);
-gen_math_fn!(@sub, i32);
-gen_math_fn!(@mul, i32);
-gen_math_fn!(@div, i32);
+gen_math_fn!(@abs, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+gen_math_fn!(@round, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+gen_math_fn!(@floor, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+gen_math_fn!(@ceil, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
 
+/// Generates the functions registered without the type suffix, their errors are reported
+/// with that name instead of the name of the typed function.
+macro_rules! gen_math_alias {
+    ($t: ty, $( $name: literal => $function: ident ($operation: ident, $divides: expr) ),* ) => {
+        $(
+            #[doc = concat!("The same as [`", $name, "_", stringify!($t), "`], registered as `", $name, "`.")]
+            #[cfg_attr(docsrs, doc(cfg(feature = "math")))]
+            #[cfg(feature = "math")]
+            pub fn $function(args: &[String]) -> FuncResult {
+                fold::<$t>($name, args, Number::$operation, $divides)
+            }
+        )*
+    };
+    (@$t: ty, $( $name: literal => $function: ident ),* ) => {
+        $(
+            #[doc = concat!("The same as [`", $name, "_", stringify!($t), "`], registered as `", $name, "`.")]
+            #[cfg_attr(docsrs, doc(cfg(feature = "math")))]
+            #[cfg(feature = "math")]
+            pub fn $function(args: &[String]) -> FuncResult {
+                unary::<$t>($name, args, Number::$function)
+            }
+        )*
+    };
+}
+
+gen_math_alias!(
+    i32,
+    "add" => add(add, false),
+    "sub" => sub(sub, false),
+    "mul" => mul(mul, false),
+    "div" => div(div, true),
+    "mod" => r#mod(rem, true),
+    "pow" => pow(pow, false),
+    "min" => min(min, false),
+    "max" => max(max, false)
+);
+gen_math_alias!(@i32, "abs" => abs);
+gen_math_alias!(@f64, "round" => round, "floor" => floor, "ceil" => ceil);
+
+/// Registers the math functions, every function is added for each type with the name of the
+/// type as suffix, like `add_u8`, and without suffix for `i32`, or for `f64` in the case of
+/// `round`, `floor` and `ceil`.
 #[doc(hidden)]
 #[macro_export]
 macro_rules! gen_math_use {
     ($tmp:ident) => {
-        gen_math_use!(@$tmp, add);
-        gen_math_use!(@$tmp, sub);
-        gen_math_use!(@$tmp, mul);
-        gen_math_use!(@$tmp, div);
+        gen_math_use!(
+            @$tmp,
+            "add" => add,
+            "sub" => sub,
+            "mul" => mul,
+            "div" => div,
+            "mod" => r#mod,
+            "pow" => pow,
+            "abs" => abs,
+            "min" => min,
+            "max" => max,
+            "round" => round,
+            "floor" => floor,
+            "ceil" => ceil
+        );
         gen_math_use!($tmp, add, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
         gen_math_use!($tmp, sub, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
         gen_math_use!($tmp, mul, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
         gen_math_use!($tmp, div, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
-
+        gen_math_use!($tmp, mod, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+        gen_math_use!($tmp, pow, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+        gen_math_use!($tmp, abs, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+        gen_math_use!($tmp, min, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+        gen_math_use!($tmp, max, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+        gen_math_use!($tmp, round, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+        gen_math_use!($tmp, floor, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+        gen_math_use!($tmp, ceil, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
     };
 
     ($tmp: ident, $name: ident, $( $t: ty ),* ) => {
@@ -107,7 +342,201 @@ macro_rules! gen_math_use {
             }
         )*
     };
-    (@$tmp: ident, $name: ident) => {
-        $tmp.add_function(stringify!($name), builtin::math::$name);
+    (@$tmp: ident, $( $name: literal => $function: ident ),* ) => {
+        $(
+            $tmp.add_function($name, builtin::math::$function);
+        )*
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(function: fn(&[String]) -> FuncResult, args: &[&str]) -> FuncResult {
+        let args: Vec<String> = args.iter().map(ToString::to_string).collect();
+        function(&args)
+    }
+
+    macro_rules! test_types {
+        ($( $t: ty ),*) => {
+            $(
+                paste! {
+                    #[test]
+                    fn [<fold_from_first_argument_ $t>]() {
+                        assert_eq!(call([<add_ $t>], &["10", "3", "2"]), Ok("15".to_string()));
+                        assert_eq!(call([<sub_ $t>], &["10", "3", "2"]), Ok("5".to_string()));
+                        assert_eq!(call([<mul_ $t>], &["10", "3", "2"]), Ok("60".to_string()));
+                        assert_eq!(call([<div_ $t>], &["60", "2", "3"]), Ok("10".to_string()));
+                        assert_eq!(call([<mod_ $t>], &["10", "4"]), Ok("2".to_string()));
+                        assert_eq!(call([<pow_ $t>], &["2", "3"]), Ok("8".to_string()));
+                        assert_eq!(call([<min_ $t>], &["10", "3", "7"]), Ok("3".to_string()));
+                        assert_eq!(call([<max_ $t>], &["10", "3", "70"]), Ok("70".to_string()));
+                        assert_eq!(call([<add_ $t>], &["10"]), Ok("10".to_string()));
+                    }
+
+                    #[test]
+                    fn [<unary_ $t>]() {
+                        for function in [[<abs_ $t>], [<round_ $t>], [<floor_ $t>], [<ceil_ $t>]] {
+                            assert_eq!(call(function, &["7"]), Ok("7".to_string()));
+                            assert_eq!(
+                                call(function, &["7", "8"]),
+                                Err(FunctionError::ArgumentsIncomplete(2, 1))
+                            );
+                        }
+                    }
+
+                    #[test]
+                    fn [<division_by_zero_ $t>]() {
+                        assert_eq!(
+                            call([<div_ $t>], &["1", "0"]),
+                            Err(FunctionError::RuntimeError(format!(
+                                "div_{}(1, 0) divides by zero",
+                                stringify!($t)
+                            )))
+                        );
+                        assert_eq!(
+                            call([<mod_ $t>], &["1", "0"]),
+                            Err(FunctionError::RuntimeError(format!(
+                                "mod_{}(1, 0) divides by zero",
+                                stringify!($t)
+                            )))
+                        );
+                    }
+
+                    #[test]
+                    fn [<invalid_arguments_ $t>]() {
+                        assert_eq!(
+                            call([<add_ $t>], &[]),
+                            Err(FunctionError::ArgumentsIncomplete(0, 1))
+                        );
+                        assert_eq!(
+                            call([<add_ $t>], &["1", "one"]),
+                            Err(FunctionError::InvalidType("one".to_string()))
+                        );
+                    }
+                }
+            )*
+        };
+        (@overflow $( $t: ty ),*) => {
+            $(
+                paste! {
+                    #[test]
+                    fn [<overflow_ $t>]() {
+                        let max = <$t>::MAX.to_string();
+                        let min = <$t>::MIN.to_string();
+                        let out_of_range = |function: &str, args: &[&str]| {
+                            Err(FunctionError::RuntimeError(format!(
+                                "{function}_{}({}) is out of the range of {}",
+                                stringify!($t),
+                                args.join(", "),
+                                stringify!($t)
+                            )))
+                        };
+
+                        assert_eq!(call([<add_ $t>], &[&max, "1"]), out_of_range("add", &[&max, "1"]));
+                        assert_eq!(call([<sub_ $t>], &[&min, "1"]), out_of_range("sub", &[&min, "1"]));
+                        assert_eq!(call([<mul_ $t>], &[&max, "2"]), out_of_range("mul", &[&max, "2"]));
+                        assert_eq!(call([<pow_ $t>], &[&max, "2"]), out_of_range("pow", &[&max, "2"]));
+                    }
+                }
+            )*
+        };
+    }
+
+    test_types!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+    test_types!(@overflow u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+    #[test]
+    fn signed_integers() {
+        assert_eq!(call(sub_i8, &["-100", "28"]), Ok("-128".to_string()));
+        assert_eq!(call(abs_i64, &["-5"]), Ok("5".to_string()));
+        assert_eq!(call(mod_i32, &["-7", "3"]), Ok("-1".to_string()));
+        assert_eq!(call(min_i16, &["3", "-3"]), Ok("-3".to_string()));
+        assert_eq!(
+            call(abs_i8, &["-128"]),
+            Err(FunctionError::RuntimeError(
+                "abs_i8(-128) is out of the range of i8".to_string()
+            ))
+        );
+        assert_eq!(
+            call(pow_i32, &["2", "-1"]),
+            Err(FunctionError::RuntimeError(
+                "pow_i32(2, -1) is out of the range of i32".to_string()
+            ))
+        );
+        assert_eq!(
+            call(div_i128, &[&i128::MIN.to_string(), "-1"]),
+            Err(FunctionError::RuntimeError(format!(
+                "div_i128({}, -1) is out of the range of i128",
+                i128::MIN
+            )))
+        );
+    }
+
+    #[test]
+    fn floats() {
+        assert_eq!(call(div_f64, &["7", "2"]), Ok("3.5".to_string()));
+        assert_eq!(call(mod_f32, &["7.5", "2"]), Ok("1.5".to_string()));
+        assert_eq!(call(pow_f64, &["4", "0.5"]), Ok("2".to_string()));
+        assert_eq!(call(abs_f32, &["-2.5"]), Ok("2.5".to_string()));
+        assert_eq!(call(round_f64, &["2.5"]), Ok("3".to_string()));
+        assert_eq!(call(floor_f64, &["-2.5"]), Ok("-3".to_string()));
+        assert_eq!(call(ceil_f32, &["2.1"]), Ok("3".to_string()));
+        assert_eq!(call(max_f64, &["2.1", "-3", "2.2"]), Ok("2.2".to_string()));
+        assert_eq!(
+            call(mul_f32, &[&f32::MAX.to_string(), "2"]),
+            Err(FunctionError::RuntimeError(format!(
+                "mul_f32({}, 2) is out of the range of f32",
+                f32::MAX
+            )))
+        );
+    }
+
+    #[test]
+    fn untyped_aliases() {
+        assert_eq!(call(add, &["2", "3"]), Ok("5".to_string()));
+        assert_eq!(call(sub, &["2", "3"]), Ok("-1".to_string()));
+        assert_eq!(call(mul, &["2", "3"]), Ok("6".to_string()));
+        assert_eq!(call(div, &["6", "3"]), Ok("2".to_string()));
+        assert_eq!(call(r#mod, &["7", "3"]), Ok("1".to_string()));
+        assert_eq!(call(round, &["2.5"]), Ok("3".to_string()));
+
+        // the errors name the function as it is registered
+        assert_eq!(
+            call(pow, &["2", "63"]),
+            Err(FunctionError::RuntimeError(
+                "pow(2, 63) is out of the range of i32".to_string()
+            ))
+        );
+        assert_eq!(
+            call(div, &["1", "0"]),
+            Err(FunctionError::RuntimeError(
+                "div(1, 0) divides by zero".to_string()
+            ))
+        );
+        // the integer ones only take integers
+        assert!(matches!(
+            call(add, &["1", "2.5"]),
+            Err(FunctionError::InvalidType(_))
+        ));
+    }
+
+    #[test]
+    fn registered_names() {
+        let ctx = crate::SrTemplate::default();
+
+        assert_eq!(ctx.render("{{ sub(10, 3) }}").unwrap(), "7");
+        assert_eq!(ctx.render("{{ div(10, 2) }}").unwrap(), "5");
+        assert_eq!(
+            ctx.render("{{ mod(10, 4) }}|{{ pow(2, 10) }}").unwrap(),
+            "2|1024"
+        );
+        assert_eq!(ctx.render("{{ round(2.5) }}|{{ abs(-3) }}").unwrap(), "3|3");
+        assert_eq!(ctx.render("{{ max_u8(3, 200) }}").unwrap(), "200");
+        assert!(matches!(
+            ctx.render("{{ add_u8(200, 100) }}"),
+            Err(crate::Error::Function(FunctionError::RuntimeError(_)))
+        ));
+    }
+}
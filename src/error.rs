@@ -11,6 +11,55 @@ pub enum Error {
     #[error("Variable not found: {0}")]
     VariableNotFound(String),
 
+    /// This error appears when a `for` block iterates over a value that is not a list.
+    #[error("Variable is not a list: {0}")]
+    NotIterable(String),
+
+    /// This error appears when a key of a map does not exist, like `user.address` if `user`
+    /// has no `address`, it holds the access as written in the template.
+    #[error("Key not found: {0}")]
+    KeyNotFound(String),
+
+    /// This error appears when an index is outside of a list or a text, like `items[5]` if
+    /// `items` has fewer items, it holds the access as written in the template.
+    #[error("Index out of range: {0}")]
+    IndexOutOfRange(String),
+
+    /// This error appears when a key or an index is taken from a value that does not have
+    /// them, like `count.name` if `count` is a number, it holds the access as written in the template.
+    #[error("Value can not be indexed: {0}")]
+    NotIndexable(String),
+
+    /// This error appears when an operator of an expression can not be applied to its
+    /// operands, like `"text" * 2`, or when its result is invalid, like a division by zero.
+    #[error("Invalid operation: {0}")]
+    InvalidOperation(String),
+
+    /// This error appears when a template that is not registered or found by the loader is
+    /// rendered by its name, included, extended or imported.
+    #[error("Template not found: {0}")]
+    TemplateNotFound(String),
+
+    /// This error appears when the name of a template is not valid for the loader, like a
+    /// name that leaves the directories of a [`FileSystemLoader`](crate::FileSystemLoader).
+    #[error("Invalid template name: {0}")]
+    InvalidTemplateName(String),
+
+    /// This error appears when the loader can not read a template, it holds the name of the
+    /// template and the reason.
+    #[error("Template {0} could not be loaded: {1}")]
+    LoadFailed(String, String),
+
+    /// This error appears when a template includes or extends itself, directly or through
+    /// other templates, it holds the names of the templates from the first one to the repeated one.
+    #[error("Include cycle: {}", .0.join(" -> "))]
+    IncludeCycle(Vec<String>),
+
+    /// This error appears when `super()` is called in a block that does not replace a block
+    /// of an extended template, it holds the name of the block.
+    #[error("Block without parent: {0}")]
+    NoParentBlock(String),
+
     /// This error appears when the function to be rendered does not exist.
     #[error("Function not implemented: {0}")]
     FunctionNotImplemented(String),
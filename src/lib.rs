@@ -3,7 +3,9 @@
 //! The library features are specified in your Cargo.toml file.
 //! - `text`: Text processing functions.
 //! - `os`: Functions related to the operating system.
-//! - `math`: Mathematical functions.
+//! - `math`: Mathematical functions. Besides the typed names (`add_i32`, `round_f64`...) they
+//!   are registered without suffix, see [`SrTemplate::default`] for the names that can clash
+//!   with your own functions.
 //! - `typed_args`: Enables typed arguments, if specified.
 //! - `debug`: Enable log for library
 //! - `macros`: Enable a easy way to create custom functions
@@ -31,6 +33,17 @@
 //! println!("Rendered: {}", ctx.render(template).unwrap());
 //! ```
 //!
+//! ## Reserved words
+//!
+//! The tags that start with `if`, `elif`, `for`, `include`, `extends`, `import`, `block`,
+//! `macro` or `set` are blocks, unless what follows the word can follow a variable, like
+//! `{{ set }}`, `{{ block | trim }}` or `{{ for + 1 }}`, which use the variables with those
+//! names. A tag with nothing but `else`, `end`, `raw` or `endraw` is always a block, so these
+//! variables can only be used in expressions, like `{{ end ?? "" }}`.
+//!
+//! `and`, `or` and `not` are operators and `true`, `false` and `null` are values, they are
+//! never variables.
+//!
 //! To see all function implemented for template syntax see [wiki](https://github.com/SergioRibera/srtemplate/wiki/Template-Syntaxis#builtin-functions)
 
 /// The `builtin` module provides a set of built-in functions for `SrTemplate`.
@@ -52,22 +65,63 @@ mod render;
 /// The `template` module contains the core functionality for `SrTemplate`, including the `function` module for custom functions.
 mod template;
 
+/// The `value` module defines the typed values stored as variables and passed to functions.
+mod value;
+
 /// Re-exports the `SrTemplateError` type for convenient use.
 pub use error::Error;
 
 /// Re-exports the [`template::function`], [`template::SrTemplate`], [`template::TemplateFunction`] type for convenient use.
-pub use template::{function, Function, SrTemplate};
+pub use template::{
+    function, ChainLoader, CompiledTemplate, FileSystemLoader, Function, MemoryLoader, Signature,
+    SrTemplate, TemplateLoader, UndefinedBehavior, ValueFunction,
+};
+
+/// Re-exports the [`value::Value`] type for convenient use.
+pub use value::Value;
 
+/// Turns a function with typed parameters into a [`Function`], parsing each argument into
+/// the type of its parameter.
+///
+/// The `Option<T>` parameters and the ones with a `#[default(value)]` can be omitted, and
+/// an empty argument is taken as omitted. It also generates a `<name>_signature` function
+/// that returns the [`Signature`] of the function, so it can be called with named arguments.
+///
+/// # Examples
+/// ```
+/// use srtemplate::{function, SrTemplate};
+///
+/// #[function]
+/// fn pad(text: String, #[default(8)] width: usize, fill: Option<char>) {
+///     let fill = fill.unwrap_or(' ').to_string();
+///     Ok(format!("{}{text}", fill.repeat(width.saturating_sub(text.len()))))
+/// }
+///
+/// let ctx = SrTemplate::default();
+/// ctx.add_function("pad", pad);
+/// ctx.add_signature("pad", pad_signature());
+///
+/// assert_eq!(ctx.render("{{ pad(\"abc\", 5) }}").unwrap(), "  abc");
+/// assert_eq!(ctx.render("{{ pad(\"abc\", fill=\"*\") }}").unwrap(), "*****abc");
+/// assert_eq!(ctx.render("{{ \"abc\" | pad(fill=\"-\", width=4) }}").unwrap(), "-abc");
+/// ```
+#[cfg(feature = "macros")]
+pub use helper_macros::function;
 #[cfg(feature = "macros")]
-pub use helper_macros::{function, Template};
+pub use helper_macros::Variable;
 
 /// The `prelude` module re-exports common items for easier use of `SrTemplate`.
 pub mod prelude {
     pub use super::builtin::*;
     pub use super::error::Error;
-    pub use super::template::function::{Error as FunctionError, FuncResult};
+    pub use super::template::function::{
+        Error as FunctionError, FuncResult, FunctionContext, ValueResult,
+    };
     pub use super::template::validations;
-    pub use super::{Function, SrTemplate};
+    pub use super::{
+        ChainLoader, CompiledTemplate, FileSystemLoader, Function, MemoryLoader, Signature,
+        SrTemplate, TemplateLoader, UndefinedBehavior, Value, ValueFunction,
+    };
 
     /// When the `typed_args` feature is enabled, this module re-exports serialization related items.
     #[cfg(feature = "typed_args")]
@@ -1,96 +1,461 @@
+use std::borrow::Cow;
+use std::ops::Range;
+
 #[cfg(feature = "debug")]
 use log::trace;
 
+mod blocks;
 mod error;
+mod expressions;
 mod functions;
 mod literals;
+mod source;
 
 #[cfg(test)]
 mod test;
 
-pub use error::{SyntaxError, SyntaxErrorKind};
+pub use error::{SyntaxError, SyntaxErrorKind, SyntaxErrorToken};
+pub use expressions::Operator;
 
-use functions::parse_function_arguments;
+use blocks::{
+    block_keyword, parse_block, parse_for, parse_if, parse_macro, parse_raw, parse_set, BlockTag,
+};
+use expressions::{follows_variable, is_close, parse_call_arguments, parse_template_expression};
 
 /// Variants of the types of nodes that exist in the syntax
-#[derive(Debug, PartialEq, Eq)]
+///
+/// Every piece of text is kept as a [`Cow`], borrowed from the parsed input until the
+/// node is converted with [`TemplateNode::into_owned`].
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum TemplateNode<'a> {
     /// Variables to be rendered
-    Variable(&'a str),
-    /// Functions to be rendered
-    Function(&'a str, Vec<TemplateNode<'a>>),
+    Variable(Cow<'a, str>),
+    /// Functions to be rendered, with the byte range of the call in the template
+    Function(Cow<'a, str>, Vec<TemplateNode<'a>>, Range<usize>),
     /// Plain text, pass as variable
-    String(&'a str),
+    String(Cow<'a, str>),
     /// Number, pass as variable
-    Number(&'a str),
+    Number(Cow<'a, str>),
     /// Decimal, pass as variable
-    Float(&'a str),
+    Float(Cow<'a, str>),
+    /// Binary operation, `a + b`, `a < b`, `a and b`...
+    Binary(Operator, Box<TemplateNode<'a>>, Box<TemplateNode<'a>>),
+    /// Unary operation, `-a` or `not a`
+    Unary(Operator, Box<TemplateNode<'a>>),
+    /// Index access, `items[0]` or `map["key"]`
+    Index(Box<TemplateNode<'a>>, Box<TemplateNode<'a>>),
+    /// Member access on a value that is not a variable, like `items[0].name`. The members
+    /// of a variable are part of its name, `user.name`, which is resolved when rendering.
+    Member(Box<TemplateNode<'a>>, Cow<'a, str>),
+    /// Named argument of a function, `width=10`, only found in the arguments of a call
+    /// after the positional ones
+    Named(Cow<'a, str>, Box<TemplateNode<'a>>),
+    /// Boolean literal, `true` or `false`
+    Bool(bool),
+    /// The `null` literal
+    Null,
     /// Plain text, this will be ignored in the rendering
-    RawText(&'a str),
+    RawText(Cow<'a, str>),
+    /// Conditional block, a list of `if`/`elif` conditions with their bodies and the
+    /// optional `else` body. The first condition whose value is truthy is rendered.
+    If(
+        Vec<(TemplateNode<'a>, Vec<TemplateNode<'a>>)>,
+        Option<Vec<TemplateNode<'a>>>,
+    ),
+    /// Loop block, the name of the loop variable, the list to iterate, the body rendered for
+    /// each item and the optional `else` body rendered when the list is empty.
+    For(
+        Cow<'a, str>,
+        Box<TemplateNode<'a>>,
+        Vec<TemplateNode<'a>>,
+        Option<Vec<TemplateNode<'a>>>,
+    ),
+    /// Include tag, `include "header"`, renders the registered template whose name is the
+    /// value of the expression with the variables of the template that includes it
+    Include(Box<TemplateNode<'a>>),
+    /// Extends tag, `extends "base"`, the template is rendered as the registered template
+    /// whose name is the value of the expression, with its blocks replaced by the ones of
+    /// this template. Only the tags outside of any block are used
+    Extends(Box<TemplateNode<'a>>),
+    /// Block that can be replaced by the templates that extend this one, the name of the
+    /// block and its body
+    Block(Cow<'a, str>, Vec<TemplateNode<'a>>),
+    /// Macro definition, `macro button(label, url="#")`, it can be called like a function by
+    /// the template that defines it or imports it. It holds the name of the macro, its
+    /// parameters, which are [`TemplateNode::Variable`] or [`TemplateNode::Named`] with their
+    /// default value, and its body
+    Macro(Cow<'a, str>, Vec<TemplateNode<'a>>, Vec<TemplateNode<'a>>),
+    /// Import tag, `import "forms"`, makes the macros of the registered template whose name
+    /// is the value of the expression available to this template
+    Import(Box<TemplateNode<'a>>),
+    /// Set tag, `set name = expr`, defines a variable with the value of the expression for
+    /// the rest of the block where it is, shadowing the variables with the same name
+    Set(Cow<'a, str>, Box<TemplateNode<'a>>),
+    /// A tag that writes or defines something, an expression, `if`, `for`, `include` or
+    /// `set`, with its source as written in the template, from its opening delimiter to the
+    /// closing one of the tag, or of the `end` tag of a block
+    Tag(Cow<'a, str>, Box<TemplateNode<'a>>),
+}
+
+impl TemplateNode<'_> {
+    /// Detaches the node from the input it was parsed from, copying every borrowed text.
+    pub fn into_owned(self) -> TemplateNode<'static> {
+        match self {
+            Self::Variable(name) => TemplateNode::Variable(Cow::Owned(name.into_owned())),
+            Self::Function(name, args, span) => TemplateNode::Function(
+                Cow::Owned(name.into_owned()),
+                args.into_iter().map(TemplateNode::into_owned).collect(),
+                span,
+            ),
+            Self::String(text) => TemplateNode::String(Cow::Owned(text.into_owned())),
+            Self::Number(text) => TemplateNode::Number(Cow::Owned(text.into_owned())),
+            Self::Float(text) => TemplateNode::Float(Cow::Owned(text.into_owned())),
+            Self::Binary(operator, lhs, rhs) => TemplateNode::Binary(
+                operator,
+                Box::new(lhs.into_owned()),
+                Box::new(rhs.into_owned()),
+            ),
+            Self::Unary(operator, operand) => {
+                TemplateNode::Unary(operator, Box::new(operand.into_owned()))
+            }
+            Self::Index(value, index) => {
+                TemplateNode::Index(Box::new(value.into_owned()), Box::new(index.into_owned()))
+            }
+            Self::Member(value, name) => {
+                TemplateNode::Member(Box::new(value.into_owned()), Cow::Owned(name.into_owned()))
+            }
+            Self::Named(name, value) => {
+                TemplateNode::Named(Cow::Owned(name.into_owned()), Box::new(value.into_owned()))
+            }
+            Self::Bool(value) => TemplateNode::Bool(value),
+            Self::Null => TemplateNode::Null,
+            Self::RawText(text) => TemplateNode::RawText(Cow::Owned(text.into_owned())),
+            Self::If(branches, otherwise) => TemplateNode::If(
+                branches
+                    .into_iter()
+                    .map(|(condition, body)| (condition.into_owned(), into_owned_nodes(body)))
+                    .collect(),
+                otherwise.map(into_owned_nodes),
+            ),
+            Self::For(item, iterable, body, otherwise) => TemplateNode::For(
+                Cow::Owned(item.into_owned()),
+                Box::new(iterable.into_owned()),
+                into_owned_nodes(body),
+                otherwise.map(into_owned_nodes),
+            ),
+            Self::Include(name) => TemplateNode::Include(Box::new(name.into_owned())),
+            Self::Extends(name) => TemplateNode::Extends(Box::new(name.into_owned())),
+            Self::Block(name, body) => {
+                TemplateNode::Block(Cow::Owned(name.into_owned()), into_owned_nodes(body))
+            }
+            Self::Macro(name, parameters, body) => TemplateNode::Macro(
+                Cow::Owned(name.into_owned()),
+                into_owned_nodes(parameters),
+                into_owned_nodes(body),
+            ),
+            Self::Import(name) => TemplateNode::Import(Box::new(name.into_owned())),
+            Self::Set(name, value) => {
+                TemplateNode::Set(Cow::Owned(name.into_owned()), Box::new(value.into_owned()))
+            }
+            Self::Tag(source, tnode) => TemplateNode::Tag(
+                Cow::Owned(source.into_owned()),
+                Box::new(tnode.into_owned()),
+            ),
+        }
+    }
+}
+
+/// The delimiters and options used to parse a template.
+#[derive(Clone, Copy, Debug)]
+pub struct Syntax<'s> {
+    /// The delimiter that opens a tag.
+    pub start: &'s str,
+    /// The delimiter that closes a tag.
+    pub close: &'s str,
+    /// The delimiters of the comments, which are removed from the output.
+    pub comment: Option<(&'s str, &'s str)>,
+    /// Removes the first newline after a block tag (`if`, `for`, `else`, `end`...).
+    pub trim_blocks: bool,
+    /// Removes the spaces and tabs from the start of a line to a block tag.
+    pub lstrip_blocks: bool,
+}
+
+impl<'s> Syntax<'s> {
+    /// Creates the syntax with the given delimiters, without comments and every option disabled.
+    pub const fn new(start: &'s str, close: &'s str) -> Self {
+        Self {
+            start,
+            close,
+            comment: None,
+            trim_blocks: false,
+            lstrip_blocks: false,
+        }
+    }
+}
+
+/// The marker that, next to a delimiter, removes the whitespace on that side of the tag.
+const TRIM_MARKER: &str = "-";
+
+/// Parse a string input into a vector of `TemplateNode`s with the given delimiters, the
+/// comments delimited by `{start}#` and `#{close}`, and every option of [`Syntax`] disabled.
+#[cfg(test)]
+pub fn parser<'a>(
+    input: &'a str,
+    start: &str,
+    close: &str,
+) -> Result<Vec<TemplateNode<'a>>, crate::Error> {
+    let comment = (format!("{start}#"), format!("#{close}"));
+    let syntax = Syntax {
+        comment: Some((&comment.0, &comment.1)),
+        ..Syntax::new(start, close)
+    };
+
+    parse(input, &syntax)
 }
 
 /// Parse a string input into a vector of `TemplateNode`s.
 ///
-/// This function takes a string input and parses it into a vector of `TemplateNode`s, representing different elements of the template. It uses the `nom` parser combinator library to handle the parsing.
+/// This function takes a string input and parses it into a vector of `TemplateNode`s,
+/// representing different elements of the template, using the delimiters and options of `syntax`.
 ///
 /// # Returns
 ///
-/// An `IResult` containing the remaining unparsed input (if any) and a vector of `TemplateNode`s, representing the parsed elements of the template.
-pub fn parser<'a>(
-    input: &'a str,
-    start: &'a str,
-    close: &'a str,
-) -> Result<Vec<TemplateNode<'a>>, crate::Error> {
+/// The parsed nodes, or a [`SyntaxError`] pointing to the position where the syntax is wrong.
+pub fn parse<'a>(input: &'a str, syntax: &Syntax) -> Result<Vec<TemplateNode<'a>>, crate::Error> {
     #[cfg(feature = "debug")]
-    trace!("Start Parser: {input} with delimiters: {start} - {close}");
-    let mut res = Vec::with_capacity(20);
+    trace!(
+        "Start Parser: {input} with delimiters: {} - {}",
+        syntax.start,
+        syntax.close
+    );
     let chars = input.as_bytes();
     let mut position = 0usize;
 
-    while !is_eof(chars, position) {
-        if advance_delimiter(chars, start, &mut position) {
-            let var = parse_template_expression(input, chars, &mut position)?;
+    let (res, tag) = parse_nodes(input, chars, syntax, &mut position)?;
 
-            // check end of sentence
-            if !advance_delimiter(chars, close, &mut position) {
-                return Err(SyntaxError::found_eof(input, position, close));
-            }
+    if let Some((tag, at)) = tag {
+        return Err(
+            SyntaxErrorKind::UnexpectedBlock(tag.keyword().to_owned()).into_error(input, at)
+        );
+    }
+
+    Ok(res)
+}
+
+/// Parsed nodes with the tag that stopped the parsing and its position, if any.
+type ParsedNodes<'a> = (Vec<TemplateNode<'a>>, Option<(BlockTag<'a>, usize)>);
 
-            res.push(var);
+/// Parses nodes until the end of the input or until a tag that closes a block
+/// (`elif`, `else` or `end`) is found, that tag is returned with the position where it starts.
+fn parse_nodes<'a>(
+    input: &'a str,
+    chars: &[u8],
+    syntax: &Syntax,
+    position: &mut usize,
+) -> Result<ParsedNodes<'a>, crate::Error> {
+    let mut res = Vec::with_capacity(20);
+
+    while !is_eof(chars, *position) {
+        let tag_start = *position;
+        if is_escaped_delimiter(chars, syntax, *position) {
+            res.push(raw_text(input, chars, syntax, position));
             continue;
         }
+        if let Some((comment_start, comment_close)) = syntax.comment {
+            if advance_delimiter(chars, comment_start, position) {
+                let trim_before = advance_delimiter(chars, TRIM_MARKER, position);
+                trim_before_tag(input, syntax, &mut res, tag_start, trim_before, true);
+
+                let Some(length) = input[*position..].find(comment_close) else {
+                    return Err(SyntaxErrorKind::UnclosedComment.into_error(input, tag_start));
+                };
+                let trim_after = input[*position..*position + length].ends_with(TRIM_MARKER);
+                *position += length + comment_close.len();
+                trim_after_tag(chars, syntax, trim_after, true, position);
+                continue;
+            }
+        }
+
+        if advance_delimiter(chars, syntax.start, position) {
+            let trim_before = advance_delimiter(chars, TRIM_MARKER, position);
+            skip_whitespace(chars, position);
+
+            let keyword = block_keyword(input, chars, syntax, position);
+            trim_before_tag(
+                input,
+                syntax,
+                &mut res,
+                tag_start,
+                trim_before,
+                keyword.is_some(),
+            );
+
+            let tag = match keyword {
+                Some("if") => {
+                    let block = parse_if(input, chars, syntax, position, tag_start)?;
+                    res.push(tag(input, tag_start, *position, block));
+                    continue;
+                }
+                Some("for") => {
+                    let block = parse_for(input, chars, syntax, position, tag_start)?;
+                    res.push(tag(input, tag_start, *position, block));
+                    continue;
+                }
+                Some("raw") => {
+                    res.extend(parse_raw(input, chars, syntax, position, tag_start)?);
+                    continue;
+                }
+                Some(keyword @ ("include" | "extends" | "import")) => {
+                    let name = Box::new(parse_template_expression(input, chars, syntax, position)?);
+                    close_tag(input, chars, syntax, true, position)?;
 
-        res.push(raw_text(input, chars, start, &mut position));
+                    res.push(match keyword {
+                        "include" => tag(input, tag_start, *position, TemplateNode::Include(name)),
+                        "extends" => TemplateNode::Extends(name),
+                        _ => TemplateNode::Import(name),
+                    });
+                    continue;
+                }
+                Some("block") => {
+                    res.push(parse_block(input, chars, syntax, position, tag_start)?);
+                    continue;
+                }
+                Some("macro") => {
+                    res.push(parse_macro(input, chars, syntax, position, tag_start)?);
+                    continue;
+                }
+                Some("set") => {
+                    let set = parse_set(input, chars, syntax, position)?;
+                    res.push(tag(input, tag_start, *position, set));
+                    continue;
+                }
+                Some("endraw") => {
+                    return Err(SyntaxErrorKind::UnexpectedBlock("endraw".to_owned())
+                        .into_error(input, tag_start))
+                }
+                Some("elif") => {
+                    BlockTag::Elif(parse_template_expression(input, chars, syntax, position)?)
+                }
+                Some("else") => BlockTag::Else,
+                Some("end") => BlockTag::End,
+                _ => {
+                    let var = parse_template_expression(input, chars, syntax, position)?;
+                    close_tag(input, chars, syntax, false, position)?;
+
+                    res.push(tag(input, tag_start, *position, var));
+                    continue;
+                }
+            };
+
+            close_tag(input, chars, syntax, true, position)?;
+
+            return Ok((res, Some((tag, tag_start))));
+        }
+
+        res.push(raw_text(input, chars, syntax, position));
     }
 
-    Ok(res)
+    Ok((res, None))
 }
 
-fn parse_template_expression<'a>(
-    input: &'a str,
+/// Consumes the closing delimiter of a tag and the whitespace that follows it when the
+/// delimiter has the trim marker, or the newline after a block tag with `trim_blocks`.
+fn close_tag(
+    input: &str,
     chars: &[u8],
+    syntax: &Syntax,
+    block: bool,
     position: &mut usize,
-) -> Result<TemplateNode<'a>, crate::Error> {
-    skip_whitespace(chars, position);
-    // expect ident
-    let (start, name_end) = identifier(chars, position);
+) -> Result<(), crate::Error> {
     skip_whitespace(chars, position);
 
-    if !is_eof(chars, *position) && chars[*position] == b'(' {
-        advance(chars, position);
-        skip_whitespace(chars, position);
+    let trim_after = check_delimiter(chars, TRIM_MARKER, *position)
+        && check_delimiter(chars, syntax.close, *position + TRIM_MARKER.len());
+    if trim_after {
+        advance_delimiter(chars, TRIM_MARKER, position);
+    }
 
-        let args = parse_function_arguments(input, chars, position)?;
-        skip_whitespace(chars, position);
+    expect_delimiter(input, chars, syntax.close, position)?;
+    trim_after_tag(chars, syntax, trim_after, block, position);
 
-        if !advance_delimiter(chars, ")", position) {
-            return Err(SyntaxErrorKind::UnterminatedArgument.into_error(input, *position));
+    Ok(())
+}
+
+/// Wraps the node of a tag with its source, from `tag_start` to the closing delimiter before
+/// `position`, without the whitespace skipped after the tag.
+fn tag<'a>(
+    input: &'a str,
+    tag_start: usize,
+    position: usize,
+    tnode: TemplateNode<'a>,
+) -> TemplateNode<'a> {
+    let source = input[tag_start..position].trim_end_matches(|c: char| c.is_ascii_whitespace());
+    TemplateNode::Tag(Cow::Borrowed(source), Box::new(tnode))
+}
+
+/// Removes the whitespace before a tag from the text that precedes it, all of it when the
+/// tag has the trim marker, or the indentation of a block tag with `lstrip_blocks`.
+fn trim_before_tag(
+    input: &str,
+    syntax: &Syntax,
+    res: &mut Vec<TemplateNode<'_>>,
+    tag_start: usize,
+    trim: bool,
+    block: bool,
+) {
+    if trim {
+        trim_previous_text(res, 0);
+    } else if block && syntax.lstrip_blocks {
+        let indent = line_indent(input, tag_start);
+        if indent > 0 {
+            trim_previous_text(res, indent);
         }
+    }
+}
+
+/// Skips the whitespace after a tag, all of it when the tag has the trim marker, or the
+/// newline after a block tag with `trim_blocks`.
+fn trim_after_tag(chars: &[u8], syntax: &Syntax, trim: bool, block: bool, position: &mut usize) {
+    if trim {
         skip_whitespace(chars, position);
+    } else if block && syntax.trim_blocks && !advance_delimiter(chars, "\r\n", position) {
+        advance_delimiter(chars, "\n", position);
+    }
+}
+
+/// Removes the whitespace at the end of the text that precedes a tag, or only its last
+/// `len` bytes if `len` is not zero. The text is removed if nothing is left of it.
+fn trim_previous_text(res: &mut Vec<TemplateNode<'_>>, len: usize) {
+    let Some(TemplateNode::RawText(text)) = res.last_mut() else {
+        return;
+    };
 
-        Ok(TemplateNode::Function(&input[start..name_end], args))
+    let keep = if len == 0 {
+        text.trim_end_matches(|c: char| c.is_ascii_whitespace())
+            .len()
     } else {
-        Ok(TemplateNode::Variable(&input[start..name_end]))
+        text.len().saturating_sub(len)
+    };
+    match text {
+        Cow::Borrowed(text) => *text = &text[..keep],
+        Cow::Owned(text) => text.truncate(keep),
+    }
+
+    if keep == 0 {
+        res.pop();
+    }
+}
+
+/// Returns the number of spaces and tabs before `position` if they are the only thing
+/// between the start of the line and `position`, otherwise zero.
+fn line_indent(input: &str, position: usize) -> usize {
+    let before = &input[..position];
+    let line = before.trim_end_matches([' ', '\t']);
+
+    if line.is_empty() || line.ends_with('\n') {
+        before.len() - line.len()
+    } else {
+        0
     }
 }
 
@@ -107,21 +472,67 @@ fn identifier(chars: &[u8], position: &mut usize) -> (usize, usize) {
     (start, *position)
 }
 
+/// Checks if the opening delimiter at `position` is written twice, which is text.
+fn is_escaped_delimiter(chars: &[u8], syntax: &Syntax, position: usize) -> bool {
+    check_delimiter(chars, syntax.start, position)
+        && check_delimiter(chars, syntax.start, position + syntax.start.len())
+}
+
+/// Parses the text until the next tag or comment.
+///
+/// An opening delimiter written twice is not a tag, it is kept once in the text, so
+/// `{{{{ name }}` is rendered as `{{ name }}`. A template could not have it before, since
+/// `{{ name` is not a valid expression.
 fn raw_text<'a>(
     input: &'a str,
     chars: &[u8],
-    open_delim: &str,
+    syntax: &Syntax,
     position: &mut usize,
 ) -> TemplateNode<'a> {
-    let start = *position;
+    let mut start = *position;
+    if is_escaped_delimiter(chars, syntax, start) {
+        // the first delimiter is dropped by starting the text after it
+        start += syntax.start.len();
+        *position = start + syntax.start.len();
+    }
+
     while !is_eof(chars, *position) {
-        if check_delimiter(chars, open_delim, *position) {
+        if check_delimiter(chars, syntax.start, *position)
+            || syntax
+                .comment
+                .is_some_and(|(comment_start, _)| check_delimiter(chars, comment_start, *position))
+        {
             break;
         }
         advance(chars, position);
     }
 
-    TemplateNode::RawText(&input[start..*position])
+    TemplateNode::RawText(Cow::Borrowed(&input[start..*position]))
+}
+
+fn found_token(input: &str, position: usize) -> SyntaxErrorToken {
+    input[position..]
+        .chars()
+        .next()
+        .map_or(SyntaxErrorToken::Eof, SyntaxErrorToken::Char)
+}
+
+fn into_owned_nodes(nodes: Vec<TemplateNode<'_>>) -> Vec<TemplateNode<'static>> {
+    nodes.into_iter().map(TemplateNode::into_owned).collect()
+}
+
+fn expect_delimiter(
+    input: &str,
+    chars: &[u8],
+    delim: &str,
+    position: &mut usize,
+) -> Result<(), crate::Error> {
+    // check end of sentence
+    if !advance_delimiter(chars, delim, position) {
+        return Err(SyntaxError::found_eof(input, *position, delim));
+    }
+
+    Ok(())
 }
 
 fn advance(chars: &[u8], position: &mut usize) {
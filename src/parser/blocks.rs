@@ -0,0 +1,331 @@
+use std::borrow::Cow;
+
+use crate::Error;
+
+use super::{
+    advance_delimiter, check_delimiter, close_tag, follows_variable, found_token, identifier,
+    is_close, parse_call_arguments, parse_nodes, parse_template_expression, skip_whitespace,
+    trim_before_tag, Syntax, SyntaxErrorKind, SyntaxErrorToken, TemplateNode, TRIM_MARKER,
+};
+
+/// Words that start or close a block when their tag is written like one, see [`block_keyword`].
+const KEYWORDS: [&str; 13] = [
+    "if", "elif", "else", "end", "for", "raw", "endraw", "include", "extends", "block", "macro",
+    "import", "set",
+];
+
+/// Tags that close the body of a block.
+pub enum BlockTag<'a> {
+    Elif(TemplateNode<'a>),
+    Else,
+    End,
+}
+
+impl BlockTag<'_> {
+    pub const fn keyword(&self) -> &'static str {
+        match self {
+            Self::Elif(_) => "elif",
+            Self::Else => "else",
+            Self::End => "end",
+        }
+    }
+}
+
+/// Consumes the keyword at `position` if the identifier found there is one and the tag is
+/// written like the tag of that keyword.
+///
+/// Otherwise the word is a variable, so `{{ set }}`, `{{ block | trim }}` or `{{ end + 1 }}`
+/// render the variables `set`, `block` and `end`. The keywords without arguments, `else`,
+/// `end`, `raw` and `endraw`, are only tags when nothing follows them.
+pub fn block_keyword(
+    input: &str,
+    chars: &[u8],
+    syntax: &Syntax,
+    position: &mut usize,
+) -> Option<&'static str> {
+    let mut end = *position;
+    let (start, name_end) = identifier(chars, &mut end);
+    let keyword = KEYWORDS
+        .into_iter()
+        .find(|keyword| *keyword == &input[start..name_end])?;
+
+    let mut next = end;
+    skip_whitespace(chars, &mut next);
+    let is_tag = match keyword {
+        "else" | "end" | "raw" | "endraw" => is_close(chars, syntax, next),
+        _ => !follows_variable(input, chars, syntax, next),
+    };
+    if !is_tag {
+        return None;
+    }
+
+    *position = end;
+    Some(keyword)
+}
+
+/// Parses an `if` block, `position` must be just after the `if` keyword.
+pub fn parse_if<'a>(
+    input: &'a str,
+    chars: &[u8],
+    syntax: &Syntax,
+    position: &mut usize,
+    tag_start: usize,
+) -> Result<TemplateNode<'a>, Error> {
+    let mut branches = Vec::new();
+    let mut condition = parse_template_expression(input, chars, syntax, position)?;
+    close_tag(input, chars, syntax, true, position)?;
+
+    loop {
+        let (body, tag) = parse_nodes(input, chars, syntax, position)?;
+        branches.push((condition, body));
+
+        match tag {
+            Some((BlockTag::Elif(next), _)) => condition = next,
+            Some((BlockTag::Else, _)) => {
+                let otherwise = parse_else(input, chars, syntax, position, "if", tag_start)?;
+                return Ok(TemplateNode::If(branches, Some(otherwise)));
+            }
+            Some((BlockTag::End, _)) => return Ok(TemplateNode::If(branches, None)),
+            None => {
+                return Err(
+                    SyntaxErrorKind::UnclosedBlock("if".to_owned()).into_error(input, tag_start)
+                )
+            }
+        }
+    }
+}
+
+/// Parses a `for` block, `position` must be just after the `for` keyword.
+pub fn parse_for<'a>(
+    input: &'a str,
+    chars: &[u8],
+    syntax: &Syntax,
+    position: &mut usize,
+    tag_start: usize,
+) -> Result<TemplateNode<'a>, Error> {
+    skip_whitespace(chars, position);
+    let (item_start, item_end) = identifier(chars, position);
+    let item = &input[item_start..item_end];
+    if item.is_empty() || item.contains('.') {
+        return Err(SyntaxErrorKind::Expected(
+            SyntaxErrorToken::String("loop variable".to_owned()),
+            found_token(input, *position),
+        )
+        .into_error(input, item_start));
+    }
+
+    skip_whitespace(chars, position);
+    let (in_start, in_end) = identifier(chars, position);
+    if &input[in_start..in_end] != "in" {
+        *position = in_start;
+        return Err(SyntaxErrorKind::Expected(
+            SyntaxErrorToken::String("in".to_owned()),
+            found_token(input, *position),
+        )
+        .into_error(input, in_start));
+    }
+
+    let iterable = parse_template_expression(input, chars, syntax, position)?;
+    close_tag(input, chars, syntax, true, position)?;
+
+    let (body, tag) = parse_nodes(input, chars, syntax, position)?;
+    let otherwise = match tag {
+        Some((BlockTag::End, _)) => None,
+        Some((BlockTag::Else, _)) => Some(parse_else(
+            input, chars, syntax, position, "for", tag_start,
+        )?),
+        Some((tag, at)) => return Err(expected_end(input, &tag, at)),
+        None => {
+            return Err(
+                SyntaxErrorKind::UnclosedBlock("for".to_owned()).into_error(input, tag_start)
+            )
+        }
+    };
+
+    Ok(TemplateNode::For(
+        Cow::Borrowed(item),
+        Box::new(iterable),
+        body,
+        otherwise,
+    ))
+}
+
+/// Parses a `block` block, `position` must be just after the `block` keyword.
+pub fn parse_block<'a>(
+    input: &'a str,
+    chars: &[u8],
+    syntax: &Syntax,
+    position: &mut usize,
+    tag_start: usize,
+) -> Result<TemplateNode<'a>, Error> {
+    skip_whitespace(chars, position);
+    let (name_start, name_end) = identifier(chars, position);
+    let name = &input[name_start..name_end];
+    if name.is_empty() || name.contains('.') {
+        return Err(SyntaxErrorKind::Expected(
+            SyntaxErrorToken::String("block name".to_owned()),
+            found_token(input, name_start),
+        )
+        .into_error(input, name_start));
+    }
+    close_tag(input, chars, syntax, true, position)?;
+
+    let (body, tag) = parse_nodes(input, chars, syntax, position)?;
+    match tag {
+        Some((BlockTag::End, _)) => Ok(TemplateNode::Block(Cow::Borrowed(name), body)),
+        Some((tag, at)) => Err(expected_end(input, &tag, at)),
+        None => {
+            Err(SyntaxErrorKind::UnclosedBlock("block".to_owned()).into_error(input, tag_start))
+        }
+    }
+}
+
+/// Parses a `macro` block, `position` must be just after the `macro` keyword.
+///
+/// The parameters are parsed like the arguments of a call, so the ones with a default value
+/// are written as named arguments and come after the other ones.
+pub fn parse_macro<'a>(
+    input: &'a str,
+    chars: &[u8],
+    syntax: &Syntax,
+    position: &mut usize,
+    tag_start: usize,
+) -> Result<TemplateNode<'a>, Error> {
+    skip_whitespace(chars, position);
+    let (name_start, name_end) = identifier(chars, position);
+    let name = &input[name_start..name_end];
+    if name.is_empty() || name.contains('.') {
+        return Err(SyntaxErrorKind::Expected(
+            SyntaxErrorToken::String("macro name".to_owned()),
+            found_token(input, name_start),
+        )
+        .into_error(input, name_start));
+    }
+
+    skip_whitespace(chars, position);
+    if !check_delimiter(chars, "(", *position) {
+        return Err(SyntaxErrorKind::Expected(
+            SyntaxErrorToken::String("(".to_owned()),
+            found_token(input, *position),
+        )
+        .into_error(input, *position));
+    }
+    let parameters_start = *position;
+    let parameters = parse_call_arguments(input, chars, syntax, position)?;
+    let invalid = parameters.iter().any(|parameter| match parameter {
+        TemplateNode::Variable(name) | TemplateNode::Named(name, _) => name.contains('.'),
+        _ => true,
+    });
+    if invalid {
+        return Err(SyntaxErrorKind::Expected(
+            SyntaxErrorToken::String("parameter name".to_owned()),
+            found_token(input, parameters_start + 1),
+        )
+        .into_error(input, parameters_start + 1));
+    }
+    close_tag(input, chars, syntax, true, position)?;
+
+    let (body, tag) = parse_nodes(input, chars, syntax, position)?;
+    match tag {
+        Some((BlockTag::End, _)) => Ok(TemplateNode::Macro(Cow::Borrowed(name), parameters, body)),
+        Some((tag, at)) => Err(expected_end(input, &tag, at)),
+        None => {
+            Err(SyntaxErrorKind::UnclosedBlock("macro".to_owned()).into_error(input, tag_start))
+        }
+    }
+}
+
+/// Parses a `set` tag, `position` must be just after the `set` keyword.
+pub fn parse_set<'a>(
+    input: &'a str,
+    chars: &[u8],
+    syntax: &Syntax,
+    position: &mut usize,
+) -> Result<TemplateNode<'a>, Error> {
+    skip_whitespace(chars, position);
+    let (name_start, name_end) = identifier(chars, position);
+    let name = &input[name_start..name_end];
+    if name.is_empty() || name.contains('.') {
+        return Err(SyntaxErrorKind::Expected(
+            SyntaxErrorToken::String("variable name".to_owned()),
+            found_token(input, name_start),
+        )
+        .into_error(input, name_start));
+    }
+
+    skip_whitespace(chars, position);
+    if !check_delimiter(chars, "=", *position) || check_delimiter(chars, "==", *position) {
+        return Err(SyntaxErrorKind::Expected(
+            SyntaxErrorToken::String("=".to_owned()),
+            found_token(input, *position),
+        )
+        .into_error(input, *position));
+    }
+    *position += 1;
+
+    let value = parse_template_expression(input, chars, syntax, position)?;
+    close_tag(input, chars, syntax, true, position)?;
+
+    Ok(TemplateNode::Set(Cow::Borrowed(name), Box::new(value)))
+}
+
+/// Parses a `raw` block, `position` must be just after the `raw` keyword.
+///
+/// Everything until the `endraw` tag is kept as text, without parsing tags or comments.
+pub fn parse_raw<'a>(
+    input: &'a str,
+    chars: &[u8],
+    syntax: &Syntax,
+    position: &mut usize,
+    tag_start: usize,
+) -> Result<Option<TemplateNode<'a>>, Error> {
+    close_tag(input, chars, syntax, true, position)?;
+    let body_start = *position;
+
+    let mut search = body_start;
+    while let Some(offset) = input[search..].find(syntax.start) {
+        let end_start = search + offset;
+        let mut end = end_start + syntax.start.len();
+        let trim = advance_delimiter(chars, TRIM_MARKER, &mut end);
+        skip_whitespace(chars, &mut end);
+
+        if block_keyword(input, chars, syntax, &mut end) == Some("endraw") {
+            let text = &input[body_start..end_start];
+            let mut body = Vec::with_capacity(1);
+            if !text.is_empty() {
+                body.push(TemplateNode::RawText(Cow::Borrowed(text)));
+            }
+            trim_before_tag(input, syntax, &mut body, end_start, trim, true);
+
+            *position = end;
+            close_tag(input, chars, syntax, true, position)?;
+            return Ok(body.pop());
+        }
+        search = end_start + syntax.start.len();
+    }
+
+    Err(SyntaxErrorKind::UnclosedBlock("raw".to_owned()).into_error(input, tag_start))
+}
+
+/// Parses the body of the `else` branch of a block, which must be closed by `end`.
+fn parse_else<'a>(
+    input: &'a str,
+    chars: &[u8],
+    syntax: &Syntax,
+    position: &mut usize,
+    block: &str,
+    tag_start: usize,
+) -> Result<Vec<TemplateNode<'a>>, Error> {
+    let (otherwise, tag) = parse_nodes(input, chars, syntax, position)?;
+
+    match tag {
+        Some((BlockTag::End, _)) => Ok(otherwise),
+        Some((tag, at)) => Err(expected_end(input, &tag, at)),
+        None => Err(SyntaxErrorKind::UnclosedBlock(block.to_owned()).into_error(input, tag_start)),
+    }
+}
+
+/// The error for a block whose body is closed by `tag` at `at` instead of `end`.
+fn expected_end(input: &str, tag: &BlockTag, at: usize) -> Error {
+    SyntaxErrorKind::MismatchedBlock("end", tag.keyword()).into_error(input, at)
+}
@@ -30,8 +30,35 @@ pub enum SyntaxErrorKind {
     #[error("Unterminated string literal")]
     UnterminatedString,
 
+    #[error("Invalid escape sequence \"{0}\" in string literal")]
+    InvalidEscape(String),
+
     #[error("Expected one '.' in a float")]
     FloatDotted,
+
+    #[error("Unclosed \"{0}\" block, expected \"end\"")]
+    UnclosedBlock(String),
+
+    #[error("Unexpected \"{0}\" outside of a block")]
+    UnexpectedBlock(String),
+
+    #[error("Expected \"{0}\" to close the block, but found \"{1}\"")]
+    MismatchedBlock(&'static str, &'static str),
+
+    #[error("Unclosed comment")]
+    UnclosedComment,
+
+    #[error("Expected an operand after \"{0}\"")]
+    MissingOperand(String),
+
+    #[error("Unclosed parenthesis, expected \")\"")]
+    UnclosedParenthesis,
+
+    #[error("Unclosed bracket, expected \"]\"")]
+    UnclosedBracket,
+
+    #[error("Positional argument after the named argument \"{0}\"")]
+    PositionalAfterNamed(String),
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -104,37 +131,22 @@ impl SyntaxError {
     }
 }
 
-fn get_line_from_offset(input: &str, mut offset: usize) -> (usize, usize, String) {
-    loop {
-        let last_newline = offset
-            - input[..offset]
-                .chars()
-                .rev()
-                .position(|c| c == '\n')
-                .unwrap_or(offset);
-
-        let line = input[..=last_newline.min(input.len() - 1)]
-            .chars()
-            .filter(|c| *c == '\n')
-            .count()
-            + 1;
-
-        let Some(column) = (offset - last_newline).checked_sub(1) else {
-            offset = last_newline.saturating_sub(1);
-            continue;
-        };
-
-        let next_newline = input[offset..]
-            .chars()
-            .skip(1)
-            .position(|c| c == '\n')
-            .unwrap_or_else(|| input.len() - offset)
-            + offset;
-
-        let context = input[last_newline..next_newline].to_owned();
-
-        break (line, column, context);
+fn get_line_from_offset(input: &str, offset: usize) -> (usize, usize, String) {
+    let mut offset = offset.min(input.len());
+    while !input.is_char_boundary(offset) {
+        offset -= 1;
     }
+
+    let line_start = input[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = input[offset..]
+        .find('\n')
+        .map_or(input.len(), |i| i + offset);
+
+    let line = input[..line_start].matches('\n').count() + 1;
+    let column = input[line_start..offset].chars().count();
+    let context = input[line_start..line_end].to_owned();
+
+    (line, column, context)
 }
 
 // pub fn make(
@@ -153,3 +165,43 @@ fn get_line_from_offset(input: &str, mut offset: usize) -> (usize, usize, String
 //         column,
 //     })
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_from_offset() {
+        let input = "first line\nsecond {{ line }}\n";
+
+        assert_eq!(
+            get_line_from_offset(input, 0),
+            (1, 0, "first line".to_owned())
+        );
+        assert_eq!(
+            get_line_from_offset(input, 6),
+            (1, 6, "first line".to_owned())
+        );
+        assert_eq!(
+            get_line_from_offset(input, 11),
+            (2, 0, "second {{ line }}".to_owned())
+        );
+        assert_eq!(
+            get_line_from_offset(input, 18),
+            (2, 7, "second {{ line }}".to_owned())
+        );
+        assert_eq!(
+            get_line_from_offset(input, input.len()),
+            (3, 0, String::new())
+        );
+    }
+
+    #[test]
+    fn test_line_from_offset_multibyte() {
+        assert_eq!(
+            get_line_from_offset("ñandú {{", 7),
+            (1, 5, "ñandú {{".to_owned())
+        );
+        assert_eq!(get_line_from_offset("", 0), (1, 0, String::new()));
+    }
+}
@@ -0,0 +1,541 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Range;
+
+use crate::Error;
+
+use super::functions::parse_function_arguments;
+use super::literals::{number_literal, raw_string_literal, string_literal};
+use super::{
+    advance, advance_delimiter, check_delimiter, found_token, identifier, is_eof, skip_whitespace,
+    Syntax, SyntaxErrorKind, SyntaxErrorToken, TemplateNode, TRIM_MARKER,
+};
+
+/// Operators of the expressions, see [`parse_template_expression`] for their precedence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    /// `a + b`, adds numbers or joins texts and lists, so `"1" + "1"` is `"11"`
+    Add,
+    /// `a - b`
+    Sub,
+    /// `a * b`
+    Mul,
+    /// `a / b`
+    Div,
+    /// `a % b`, the remainder of the division
+    Rem,
+    /// `a == b`
+    Eq,
+    /// `a != b`
+    Ne,
+    /// `a < b`
+    Lt,
+    /// `a <= b`
+    Le,
+    /// `a > b`
+    Gt,
+    /// `a >= b`
+    Ge,
+    /// `a and b`
+    And,
+    /// `a or b`
+    Or,
+    /// `a ?? b`, the right operand when the left one is a missing variable or `null`
+    Coalesce,
+    /// `not a`
+    Not,
+    /// `-a`
+    Neg,
+}
+
+impl Operator {
+    /// The binary operators, the longest ones first so `<=` is not parsed as `<`.
+    const BINARY: [Self; 14] = [
+        Self::Coalesce,
+        Self::Eq,
+        Self::Ne,
+        Self::Le,
+        Self::Ge,
+        Self::Lt,
+        Self::Gt,
+        Self::Add,
+        Self::Sub,
+        Self::Mul,
+        Self::Div,
+        Self::Rem,
+        Self::And,
+        Self::Or,
+    ];
+
+    /// The operand of `not` is parsed with this precedence, so `not a == b` is `not (a == b)`.
+    pub(super) const NOT_OPERAND: u8 = 5;
+
+    pub const fn symbol(self) -> &'static str {
+        match self {
+            Self::Add => "+",
+            Self::Sub | Self::Neg => "-",
+            Self::Mul => "*",
+            Self::Div => "/",
+            Self::Rem => "%",
+            Self::Eq => "==",
+            Self::Ne => "!=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+            Self::And => "and",
+            Self::Or => "or",
+            Self::Not => "not",
+            Self::Coalesce => "??",
+        }
+    }
+
+    pub(super) const fn precedence(self) -> u8 {
+        match self {
+            Self::Coalesce => 1,
+            Self::Or => 2,
+            Self::And => 3,
+            Self::Not => 4,
+            Self::Eq | Self::Ne | Self::Lt | Self::Le | Self::Gt | Self::Ge => 5,
+            Self::Add | Self::Sub => 6,
+            Self::Mul | Self::Div | Self::Rem => 7,
+            Self::Neg => 8,
+        }
+    }
+
+    const fn is_keyword(self) -> bool {
+        matches!(self, Self::And | Self::Or | Self::Not)
+    }
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.symbol())
+    }
+}
+
+/// Parses an expression followed by its pipes.
+///
+/// From the lowest to the highest precedence, the operators are:
+///
+/// 1. the pipe `value | f(x)`, which passes the value on its left as the first argument of
+///    the function on its right, so it is the same as `f(value, x)`, and the parentheses can
+///    be omitted when there are no more arguments. Pipes are applied from left to right, so
+///    `a + 1 | f | g(b)` is `g(f(a + 1), b)`.
+/// 2. `??`, which uses the value on its right when the one on its left is a missing variable
+///    or `null`
+/// 3. `or`
+/// 4. `and`
+/// 5. `not`
+/// 6. the comparisons `==`, `!=`, `<`, `<=`, `>` and `>=`
+/// 7. `+` and `-`
+/// 8. `*`, `/` and `%`
+/// 9. the negation `-a`
+/// 10. the accesses `items[0]` and `user.name`, and the method calls `value.f(x)`, which
+///     are the same as `f(value, x)`, so `user.name.trim().toUpper()` is
+///     `toUpper(trim(user.name))`
+///
+/// A name after a dot is a method call when it is followed by `(` and a member access
+/// otherwise, so in `user.name.trim()` the key `name` of `user` is passed to `trim`.
+///
+/// Texts are never read as numbers, so `"1" + "1"` is `"11"`. The variables added with
+/// [`SrTemplate::add_variable`](crate::SrTemplate::add_variable) and the results of the text
+/// functions are numbers when their text is written exactly as a number, so `count + 1`
+/// adds, also after the value is passed through `set`, a loop or a macro.
+///
+/// Binary operators are applied from left to right and parentheses group an expression.
+/// Inside the arguments of a function each argument is its own expression, so
+/// `f(a | g, b)` is `f(g(a), b)`.
+pub fn parse_template_expression<'a>(
+    input: &'a str,
+    chars: &[u8],
+    syntax: &Syntax,
+    position: &mut usize,
+) -> Result<TemplateNode<'a>, Error> {
+    skip_whitespace(chars, position);
+    let value = parse_binary(input, chars, syntax, position, 0)?;
+
+    parse_pipes(input, chars, syntax, value, position)
+}
+
+/// Parses the binary operations whose operator has at least `min_precedence`.
+fn parse_binary<'a>(
+    input: &'a str,
+    chars: &[u8],
+    syntax: &Syntax,
+    position: &mut usize,
+    min_precedence: u8,
+) -> Result<TemplateNode<'a>, Error> {
+    let mut lhs = parse_unary(input, chars, syntax, position)?;
+
+    loop {
+        skip_whitespace(chars, position);
+        let operator_start = *position;
+        let Some(operator) = binary_operator(input, chars, syntax, *position) else {
+            break;
+        };
+        if operator.precedence() < min_precedence {
+            break;
+        }
+
+        *position += operator.symbol().len();
+        expect_operand(input, chars, syntax, operator, operator_start, position)?;
+        let rhs = parse_binary(input, chars, syntax, position, operator.precedence() + 1)?;
+
+        lhs = TemplateNode::Binary(operator, Box::new(lhs), Box::new(rhs));
+    }
+
+    Ok(lhs)
+}
+
+/// Parses the negation `-a`, `not a` or a primary expression.
+fn parse_unary<'a>(
+    input: &'a str,
+    chars: &[u8],
+    syntax: &Syntax,
+    position: &mut usize,
+) -> Result<TemplateNode<'a>, Error> {
+    let operator_start = *position;
+
+    let operator = if chars.get(*position) == Some(&b'-')
+        && !chars.get(*position + 1).is_some_and(u8::is_ascii_digit)
+    {
+        Operator::Neg
+    } else if keyword(input, chars, *position, Operator::Not.symbol()) {
+        Operator::Not
+    } else {
+        return parse_primary(input, chars, syntax, position);
+    };
+
+    *position += operator.symbol().len();
+    expect_operand(input, chars, syntax, operator, operator_start, position)?;
+    let operand = if operator == Operator::Not {
+        parse_binary(input, chars, syntax, position, Operator::NOT_OPERAND)?
+    } else {
+        parse_unary(input, chars, syntax, position)?
+    };
+
+    Ok(TemplateNode::Unary(operator, Box::new(operand)))
+}
+
+/// Parses a literal, an expression between parentheses, a variable or a function call.
+fn parse_primary<'a>(
+    input: &'a str,
+    chars: &[u8],
+    syntax: &Syntax,
+    position: &mut usize,
+) -> Result<TemplateNode<'a>, Error> {
+    let value = match chars.get(*position) {
+        Some(b'"' | b'\'') => string_literal(input, chars, position)?,
+        Some(b'r') if chars.get(*position + 1) == Some(&b'"') => {
+            raw_string_literal(input, chars, position)?
+        }
+        Some(n) if n.is_ascii_digit() || *n == b'-' => number_literal(input, chars, position)?,
+        Some(b'(') => {
+            let group_start = *position;
+            advance(chars, position);
+            let value = parse_template_expression(input, chars, syntax, position)?;
+
+            if !advance_delimiter(chars, ")", position) {
+                return Err(SyntaxErrorKind::UnclosedParenthesis.into_error(input, group_start));
+            }
+            value
+        }
+        _ => parse_operand(input, chars, syntax, position)?,
+    };
+    skip_whitespace(chars, position);
+
+    parse_accesses(input, chars, syntax, value, position)
+}
+
+/// Parses the index accesses `value[index]`, the member accesses `value.name` and the
+/// method calls `value.f(x)` that follow a value.
+fn parse_accesses<'a>(
+    input: &'a str,
+    chars: &[u8],
+    syntax: &Syntax,
+    mut value: TemplateNode<'a>,
+    position: &mut usize,
+) -> Result<TemplateNode<'a>, Error> {
+    loop {
+        let access_start = *position;
+        if advance_delimiter(chars, "[", position) {
+            let index = parse_template_expression(input, chars, syntax, position)?;
+            if !advance_delimiter(chars, "]", position) {
+                return Err(SyntaxErrorKind::UnclosedBracket.into_error(input, access_start));
+            }
+            value = TemplateNode::Index(Box::new(value), Box::new(index));
+        } else if advance_delimiter(chars, ".", position) {
+            let (start, end) = identifier(chars, position);
+            if start == end || !chars[start].is_ascii_alphabetic() && chars[start] != b'_' {
+                return Err(SyntaxErrorKind::Expected(
+                    SyntaxErrorToken::String("name".to_owned()),
+                    found_token(input, start),
+                )
+                .into_error(input, start));
+            }
+            let names = &input[start..end];
+            let (members, method) = match names.rfind('.') {
+                Some(dot) => (start..start + dot, start + dot + 1),
+                None => (start..start, start),
+            };
+            for name in input[members.clone()]
+                .split('.')
+                .filter(|name| !name.is_empty())
+            {
+                value = TemplateNode::Member(Box::new(value), Cow::Borrowed(name));
+            }
+
+            skip_whitespace(chars, position);
+            value = if check_delimiter(chars, "(", *position) {
+                dotted_name(input, members)?;
+                parse_method(input, chars, syntax, value, method..end, position)?
+            } else {
+                dotted_name(input, start..end)?;
+                TemplateNode::Member(Box::new(value), Cow::Borrowed(&input[method..end]))
+            };
+        } else {
+            return Ok(value);
+        }
+        skip_whitespace(chars, position);
+    }
+}
+
+/// Checks that every `.` of the name read by [`identifier`] in `name` separates two names,
+/// so `user.`, `.name` and `user..name` are errors at the dot.
+fn dotted_name(input: &str, name: Range<usize>) -> Result<(), Error> {
+    let chars = &input.as_bytes()[name.clone()];
+    let dot = (0..chars.len())
+        .find(|&i| chars[i] == b'.' && (i == 0 || i + 1 == chars.len() || chars[i + 1] == b'.'));
+
+    match dot {
+        Some(dot) => Err(SyntaxErrorKind::Expected(
+            SyntaxErrorToken::String("name".to_owned()),
+            found_token(input, name.start + dot),
+        )
+        .into_error(input, name.start + dot)),
+        None => Ok(()),
+    }
+}
+
+/// Parses the arguments of the method `name` called on `receiver`, `position` must be at
+/// the `(`.
+fn parse_method<'a>(
+    input: &'a str,
+    chars: &[u8],
+    syntax: &Syntax,
+    receiver: TemplateNode<'a>,
+    name: Range<usize>,
+    position: &mut usize,
+) -> Result<TemplateNode<'a>, Error> {
+    if name.is_empty() || !chars[name.start].is_ascii_alphabetic() && chars[name.start] != b'_' {
+        return Err(SyntaxErrorKind::Expected(
+            SyntaxErrorToken::String("function".to_owned()),
+            found_token(input, name.start),
+        )
+        .into_error(input, name.start));
+    }
+
+    let mut args = vec![receiver];
+    args.extend(parse_call_arguments(input, chars, syntax, position)?);
+    let span = name.start..*position;
+
+    Ok(TemplateNode::Function(
+        Cow::Borrowed(&input[name]),
+        args,
+        span,
+    ))
+}
+
+/// Parses a variable, a function call, a method call on a variable or one of the `true`,
+/// `false` and `null` literals.
+fn parse_operand<'a>(
+    input: &'a str,
+    chars: &[u8],
+    syntax: &Syntax,
+    position: &mut usize,
+) -> Result<TemplateNode<'a>, Error> {
+    // expect ident
+    let (start, name_end) = identifier(chars, position);
+    if start == name_end {
+        return Err(SyntaxErrorKind::Expected(
+            SyntaxErrorToken::String("expression".to_owned()),
+            found_token(input, start),
+        )
+        .into_error(input, start));
+    }
+    skip_whitespace(chars, position);
+
+    if !is_eof(chars, *position) && chars[*position] == b'(' {
+        // in `user.name.trim()` the last name is the method, the rest is kept as a variable
+        // so the variables with dots in their name still work as receivers
+        if let Some(dot) = input[start..name_end].rfind('.') {
+            dotted_name(input, start..start + dot)?;
+            let receiver = TemplateNode::Variable(Cow::Borrowed(&input[start..start + dot]));
+            let method = parse_method(
+                input,
+                chars,
+                syntax,
+                receiver,
+                start + dot + 1..name_end,
+                position,
+            )?;
+            skip_whitespace(chars, position);
+            return Ok(method);
+        }
+
+        let args = parse_call_arguments(input, chars, syntax, position)?;
+        let end = *position;
+        skip_whitespace(chars, position);
+
+        Ok(TemplateNode::Function(
+            Cow::Borrowed(&input[start..name_end]),
+            args,
+            start..end,
+        ))
+    } else {
+        dotted_name(input, start..name_end)?;
+        Ok(match &input[start..name_end] {
+            "true" => TemplateNode::Bool(true),
+            "false" => TemplateNode::Bool(false),
+            "null" => TemplateNode::Null,
+            name => TemplateNode::Variable(Cow::Borrowed(name)),
+        })
+    }
+}
+
+/// Parses the pipes that follow `value`, every segment must be a function name with
+/// optional arguments.
+fn parse_pipes<'a>(
+    input: &'a str,
+    chars: &[u8],
+    syntax: &Syntax,
+    mut value: TemplateNode<'a>,
+    position: &mut usize,
+) -> Result<TemplateNode<'a>, Error> {
+    skip_whitespace(chars, position);
+
+    while !is_close(chars, syntax, *position) && advance_delimiter(chars, "|", position) {
+        skip_whitespace(chars, position);
+
+        let (start, name_end) = identifier(chars, position);
+        if start == name_end || !chars[start].is_ascii_alphabetic() && chars[start] != b'_' {
+            return Err(SyntaxErrorKind::Expected(
+                SyntaxErrorToken::String("function".to_owned()),
+                found_token(input, start),
+            )
+            .into_error(input, start));
+        }
+        skip_whitespace(chars, position);
+
+        let mut args = vec![value];
+        let mut end = name_end;
+        if !is_eof(chars, *position) && chars[*position] == b'(' {
+            args.extend(parse_call_arguments(input, chars, syntax, position)?);
+            end = *position;
+            skip_whitespace(chars, position);
+        }
+
+        value = TemplateNode::Function(Cow::Borrowed(&input[start..name_end]), args, start..end);
+    }
+
+    Ok(value)
+}
+
+/// Parses the arguments of a call between parentheses, `position` must be at the `(`.
+pub fn parse_call_arguments<'a>(
+    input: &'a str,
+    chars: &[u8],
+    syntax: &Syntax,
+    position: &mut usize,
+) -> Result<Vec<TemplateNode<'a>>, Error> {
+    advance(chars, position);
+    skip_whitespace(chars, position);
+
+    let args = parse_function_arguments(input, chars, syntax, position)?;
+    skip_whitespace(chars, position);
+
+    if !advance_delimiter(chars, ")", position) {
+        return Err(SyntaxErrorKind::UnterminatedArgument.into_error(input, *position));
+    }
+
+    Ok(args)
+}
+
+/// Returns the binary operator at `position`, if any.
+///
+/// The closing delimiter is never an operator, so with `{{ a -}}` or `{% a %}` the `-` and
+/// the `%` close the tag.
+fn binary_operator(
+    input: &str,
+    chars: &[u8],
+    syntax: &Syntax,
+    position: usize,
+) -> Option<Operator> {
+    if is_close(chars, syntax, position) {
+        return None;
+    }
+
+    Operator::BINARY.into_iter().find(|operator| {
+        if operator.is_keyword() {
+            keyword(input, chars, position, operator.symbol())
+        } else {
+            check_delimiter(chars, operator.symbol(), position)
+        }
+    })
+}
+
+/// Checks if what is at `position` can follow a variable in an expression: the end of the
+/// tag, a pipe, an index or a binary operator. The `-` is taken as the start of an operand,
+/// so `{{ if -x }}` is still a block.
+pub fn follows_variable(input: &str, chars: &[u8], syntax: &Syntax, position: usize) -> bool {
+    is_eof(chars, position)
+        || is_close(chars, syntax, position)
+        || check_delimiter(chars, "|", position)
+        || check_delimiter(chars, "[", position)
+        || binary_operator(input, chars, syntax, position)
+            .is_some_and(|operator| operator != Operator::Sub)
+}
+
+/// Checks if the close delimiter of the tag, with or without the trim marker, starts at
+/// `position`, so a delimiter like `|}` is not taken as an operator or a pipe.
+pub fn is_close(chars: &[u8], syntax: &Syntax, position: usize) -> bool {
+    check_delimiter(chars, syntax.close, position)
+        || check_delimiter(chars, TRIM_MARKER, position)
+            && check_delimiter(chars, syntax.close, position + TRIM_MARKER.len())
+}
+
+/// Checks if the identifier at `position` is the keyword `word`.
+fn keyword(input: &str, chars: &[u8], position: usize, word: &str) -> bool {
+    let mut end = position;
+    let (start, end) = identifier(chars, &mut end);
+    &input[start..end] == word
+}
+
+/// Skips the whitespace after an operator and checks that an operand follows it, otherwise
+/// the error points at the operator.
+fn expect_operand(
+    input: &str,
+    chars: &[u8],
+    syntax: &Syntax,
+    operator: Operator,
+    operator_start: usize,
+    position: &mut usize,
+) -> Result<(), Error> {
+    skip_whitespace(chars, position);
+
+    let starts_operand = chars.get(*position).is_some_and(|token| {
+        token.is_ascii_alphanumeric() || matches!(token, b'_' | b'"' | b'\'' | b'(' | b'-')
+    });
+    // `-` is the only binary operator that can also start an operand
+    let operator_follows = binary_operator(input, chars, syntax, *position)
+        .is_some_and(|operator| operator != Operator::Sub);
+    if !starts_operand || operator_follows {
+        return Err(
+            SyntaxErrorKind::MissingOperand(operator.symbol().to_owned())
+                .into_error(input, operator_start),
+        );
+    }
+
+    Ok(())
+}
@@ -1,14 +1,22 @@
+use std::borrow::Cow;
+
 use crate::Error;
 
-use super::literals::{number_literal, string_literal};
-use super::{advance_delimiter, is_eof, parse_template_expression, skip_whitespace, TemplateNode};
+use super::{
+    advance_delimiter, check_delimiter, identifier, is_eof, parse_template_expression,
+    skip_whitespace, Syntax, SyntaxErrorKind, TemplateNode,
+};
 
+/// Parses the arguments of a call, the positional ones first and then the named ones,
+/// `width=10`, which are parsed as [`TemplateNode::Named`].
 pub fn parse_function_arguments<'a>(
     input: &'a str,
     chars: &[u8],
+    syntax: &Syntax,
     position: &mut usize,
 ) -> Result<Vec<TemplateNode<'a>>, Error> {
     let mut args = Vec::new();
+    let mut last_named = None;
 
     while !is_eof(chars, *position) && chars[*position] != b')' {
         skip_whitespace(chars, position);
@@ -17,14 +25,18 @@ pub fn parse_function_arguments<'a>(
             break;
         }
 
-        match chars[*position] {
-            b'"' => {
-                args.push(string_literal(input, chars, position)?);
-            }
-            n if n.is_ascii_digit() => args.push(number_literal(input, chars, position)?),
-            _ => {
-                args.push(parse_template_expression(input, chars, position)?);
-            }
+        let arg_start = *position;
+        if let Some(name) = argument_name(input, chars, position) {
+            skip_whitespace(chars, position);
+            let value = parse_template_expression(input, chars, syntax, position)?;
+            args.push(TemplateNode::Named(Cow::Borrowed(name), Box::new(value)));
+            last_named = Some(name);
+        } else if let Some(name) = last_named {
+            return Err(
+                SyntaxErrorKind::PositionalAfterNamed(name.to_owned()).into_error(input, arg_start)
+            );
+        } else {
+            args.push(parse_template_expression(input, chars, syntax, position)?);
         }
 
         skip_whitespace(chars, position);
@@ -34,3 +46,23 @@ pub fn parse_function_arguments<'a>(
     }
     Ok(args)
 }
+
+/// Advances past `name=` if the argument at `position` is named, `==` is a comparison.
+fn argument_name<'a>(input: &'a str, chars: &[u8], position: &mut usize) -> Option<&'a str> {
+    let mut end = *position;
+    let (start, name_end) = identifier(chars, &mut end);
+    let starts_name = start < name_end
+        && (chars[start].is_ascii_alphabetic() || chars[start] == b'_')
+        && !input[start..name_end].contains('.');
+    if !starts_name {
+        return None;
+    }
+
+    skip_whitespace(chars, &mut end);
+    if !check_delimiter(chars, "=", end) || check_delimiter(chars, "==", end) {
+        return None;
+    }
+
+    *position = end + 1;
+    Some(&input[start..name_end])
+}
@@ -1,63 +1,222 @@
+use std::borrow::Cow;
+
 use crate::Error;
 
-use super::{advance, is_eof, SyntaxErrorKind, TemplateNode};
+use super::{advance, advance_delimiter, is_eof, SyntaxErrorKind, TemplateNode};
 
+/// Parses a string literal delimited by `"` or `'`, `position` must be at the opening quote.
+///
+/// The escape sequences `\\`, `\"`, `\'`, `\n`, `\t`, `\r` and `\u{...}` are decoded, the
+/// text is only copied when it contains any of them.
 pub fn string_literal<'a>(
     input: &'a str,
     chars: &[u8],
     position: &mut usize,
 ) -> Result<TemplateNode<'a>, Error> {
+    let quote = chars[*position];
     advance(chars, position);
     let start = *position;
-    let mut is_scapped = false;
+    let mut decoded: Option<String> = None;
+    let mut segment_start = start;
 
     while !is_eof(chars, *position) {
         let token = chars[*position];
-        if is_scapped {
-            is_scapped = false;
-        } else if token == b'\\' {
-            is_scapped = true;
-        } else if token == b'"' {
+        if token == b'\\' {
+            let escape_start = *position;
+            let decoded = decoded.get_or_insert_with(String::new);
+            decoded.push_str(&input[segment_start..escape_start]);
+            advance(chars, position);
+            decoded.push(escape_sequence(input, chars, position, escape_start)?);
+            segment_start = *position;
+        } else if token == quote {
+            let text = match decoded {
+                Some(mut decoded) => {
+                    decoded.push_str(&input[segment_start..*position]);
+                    Cow::Owned(decoded)
+                }
+                None => Cow::Borrowed(&input[start..*position]),
+            };
+            advance(chars, position);
+            return Ok(TemplateNode::String(text));
+        } else {
             advance(chars, position);
-            return Ok(TemplateNode::String(&input[start..*position - 1]));
         }
-        advance(chars, position);
     }
 
     Err(SyntaxErrorKind::UnterminatedString.into_error(input, *position))
 }
 
+/// Parses a raw string literal `r"..."`, `position` must be at the `r`.
+///
+/// The text is kept as written, backslashes included, until the next `"`.
+pub fn raw_string_literal<'a>(
+    input: &'a str,
+    chars: &[u8],
+    position: &mut usize,
+) -> Result<TemplateNode<'a>, Error> {
+    // skip `r"`
+    advance(chars, position);
+    advance(chars, position);
+    let start = *position;
+
+    let Some(length) = input[start..].find('"') else {
+        *position = input.len();
+        return Err(SyntaxErrorKind::UnterminatedString.into_error(input, *position));
+    };
+    *position += length + 1;
+
+    Ok(TemplateNode::String(Cow::Borrowed(
+        &input[start..start + length],
+    )))
+}
+
+/// Decodes the escape sequence after a `\`, `escape_start` is the position of the `\`.
+fn escape_sequence(
+    input: &str,
+    chars: &[u8],
+    position: &mut usize,
+    escape_start: usize,
+) -> Result<char, Error> {
+    let invalid = |end: usize| {
+        SyntaxErrorKind::InvalidEscape(input[escape_start..end].to_owned())
+            .into_error(input, escape_start)
+    };
+
+    let Some(escaped) = input[*position..].chars().next() else {
+        return Err(SyntaxErrorKind::UnterminatedString.into_error(input, *position));
+    };
+    *position += escaped.len_utf8();
+
+    match escaped {
+        '\\' => Ok('\\'),
+        '"' => Ok('"'),
+        '\'' => Ok('\''),
+        'n' => Ok('\n'),
+        't' => Ok('\t'),
+        'r' => Ok('\r'),
+        'u' => {
+            if !advance_delimiter(chars, "{", position) {
+                return Err(invalid(*position));
+            }
+            let digits_start = *position;
+            while !is_eof(chars, *position) && chars[*position].is_ascii_hexdigit() {
+                advance(chars, position);
+            }
+            let digits = &input[digits_start..*position];
+            if !advance_delimiter(chars, "}", position) || digits.is_empty() || digits.len() > 6 {
+                return Err(invalid(*position));
+            }
+
+            u32::from_str_radix(digits, 16)
+                .ok()
+                .and_then(char::from_u32)
+                .ok_or_else(|| invalid(*position))
+        }
+        _ => Err(invalid(*position)),
+    }
+}
+
+/// Parses a number literal, `position` must be at its first digit or at its `-` sign.
+///
+/// Integers can be written in hexadecimal, octal or binary with the `0x`, `0o` and `0b`
+/// prefixes, floats can have an exponent like `1e-3`, and the digits can be separated with
+/// `_`, like `1_000`.
 pub fn number_literal<'a>(
     input: &'a str,
     chars: &[u8],
     position: &mut usize,
 ) -> Result<TemplateNode<'a>, Error> {
-    let mut is_float = false;
     let start = *position;
+    advance_delimiter(chars, "-", position);
 
-    while !is_eof(chars, *position)
-        && (chars[*position].is_ascii_digit() || chars[*position] == b'.')
+    let radix_digit: Option<fn(&u8) -> bool> = if advance_delimiter(chars, "0x", position)
+        || advance_delimiter(chars, "0X", position)
     {
-        if chars[*position] == b'.' && is_float {
-            return Err(SyntaxErrorKind::FloatDotted.into_error(input, *position));
+        Some(u8::is_ascii_hexdigit)
+    } else if advance_delimiter(chars, "0o", position) || advance_delimiter(chars, "0O", position) {
+        Some(|digit| matches!(digit, b'0'..=b'7'))
+    } else if advance_delimiter(chars, "0b", position) || advance_delimiter(chars, "0B", position) {
+        Some(|digit| matches!(digit, b'0' | b'1'))
+    } else {
+        None
+    };
+
+    if let Some(is_digit) = radix_digit {
+        if !digits(chars, position, is_digit) {
+            return Err(SyntaxErrorKind::InvalidNumber.into_error(input, *position));
         }
-        if chars[*position] == b'.' {
-            is_float = true;
+        end_of_number(input, chars, position)?;
+
+        return Ok(TemplateNode::Number(Cow::Borrowed(
+            &input[start..*position],
+        )));
+    }
+
+    if !digits(chars, position, u8::is_ascii_digit) {
+        return Err(SyntaxErrorKind::InvalidNumber.into_error(input, *position));
+    }
+
+    let mut is_float = false;
+    if advance_delimiter(chars, ".", position) {
+        is_float = true;
+        digits(chars, position, u8::is_ascii_digit);
+
+        if chars.get(*position) == Some(&b'.') {
+            return Err(SyntaxErrorKind::FloatDotted.into_error(input, *position));
         }
-        advance(chars, position);
     }
 
-    if let Some(&token) = chars.get(*position) {
-        if !(token.is_ascii_digit() || token == b'.' || token == b',' || token == b')') {
+    if advance_delimiter(chars, "e", position) || advance_delimiter(chars, "E", position) {
+        is_float = true;
+        if !advance_delimiter(chars, "-", position) {
+            advance_delimiter(chars, "+", position);
+        }
+
+        if !digits(chars, position, u8::is_ascii_digit) {
             return Err(SyntaxErrorKind::InvalidNumber.into_error(input, *position));
         }
     }
+    end_of_number(input, chars, position)?;
 
     if is_float {
-        return Ok(TemplateNode::Float(&input[start..*position]));
+        // a float too large for an `f64`, like `1e400`, would be infinite
+        let text = &input[start..*position];
+        if !text
+            .replace('_', "")
+            .parse::<f64>()
+            .is_ok_and(f64::is_finite)
+        {
+            return Err(SyntaxErrorKind::InvalidNumber.into_error(input, start));
+        }
+        return Ok(TemplateNode::Float(Cow::Borrowed(text)));
     }
 
-    Ok(TemplateNode::Number(&input[start..*position]))
+    Ok(TemplateNode::Number(Cow::Borrowed(
+        &input[start..*position],
+    )))
+}
+
+/// Consumes the digits accepted by `is_digit` and the `_` that separate them, returns
+/// `false` if there is no digit.
+fn digits(chars: &[u8], position: &mut usize, is_digit: fn(&u8) -> bool) -> bool {
+    let start = *position;
+    while !is_eof(chars, *position)
+        && (is_digit(&chars[*position]) || chars[*position] == b'_' && *position > start)
+    {
+        advance(chars, position);
+    }
+
+    *position > start
+}
+
+/// Checks that the number is not followed by a letter, a digit or a dot, like in `14test`.
+fn end_of_number(input: &str, chars: &[u8], position: &usize) -> Result<(), Error> {
+    match chars.get(*position) {
+        Some(token) if token.is_ascii_alphanumeric() || matches!(token, b'_' | b'.') => {
+            Err(SyntaxErrorKind::InvalidNumber.into_error(input, *position))
+        }
+        _ => Ok(()),
+    }
 }
 
 #[cfg(test)]
@@ -93,13 +252,78 @@ mod tests {
         assert!(result.is_ok());
         let node = result.unwrap();
         if let TemplateNode::String(value) = node {
-            assert_eq!(value, r#"hello \"world\""#);
+            assert_eq!(value, r#"hello "world""#);
         } else {
             panic!("Expected a String node");
         }
         assert_eq!(position, 17);
     }
 
+    #[test]
+    fn test_string_literal_escapes() {
+        let (input, chars, mut position) = setup(r#""a\\b\n\t\r\'\u{48}\u{1F600}" rest"#);
+        let result = string_literal(input, &chars, &mut position);
+
+        assert_eq!(
+            result,
+            Ok(TemplateNode::String("a\\b\n\t\r'H\u{1F600}".into()))
+        );
+        assert_eq!(position, 29);
+    }
+
+    #[test]
+    fn test_string_literal_single_quoted() {
+        let (input, chars, mut position) = setup(r#"'say "hi" \'now\''"#);
+        let result = string_literal(input, &chars, &mut position);
+
+        assert_eq!(result, Ok(TemplateNode::String(r#"say "hi" 'now'"#.into())));
+        assert_eq!(position, input.len());
+    }
+
+    #[test]
+    fn test_string_literal_invalid_escape() {
+        for (literal, escape, at) in [
+            (r#""ab\q""#, r"\q", 3),
+            (r#""\u{110000}""#, r"\u{110000}", 1),
+            (r#""\u{}""#, r"\u{}", 1),
+            (r#""\u48""#, r"\u", 1),
+            (r#""\u{1234567}""#, r"\u{1234567}", 1),
+        ] {
+            let (input, chars, mut position) = setup(literal);
+            let result = string_literal(input, &chars, &mut position);
+
+            let Err(Error::BadSyntax(error)) = result else {
+                panic!("Expected a syntax error for {literal}");
+            };
+            assert_eq!(
+                error.kind,
+                SyntaxErrorKind::InvalidEscape(escape.to_owned())
+            );
+            assert_eq!(error.at, at);
+        }
+    }
+
+    #[test]
+    fn test_raw_string_literal() {
+        let (input, chars, mut position) = setup(r#"r"C:\path\n" rest"#);
+        let result = raw_string_literal(input, &chars, &mut position);
+
+        assert_eq!(result, Ok(TemplateNode::String(r"C:\path\n".into())));
+        assert_eq!(position, 12);
+    }
+
+    #[test]
+    fn test_raw_string_literal_unterminated() {
+        let (input, chars, mut position) = setup(r#"r"C:\path"#);
+        let result = raw_string_literal(input, &chars, &mut position);
+
+        let Err(Error::BadSyntax(error)) = result else {
+            panic!("Expected a syntax error");
+        };
+        assert_eq!(error.kind, SyntaxErrorKind::UnterminatedString);
+        assert_eq!(position, 9);
+    }
+
     #[test]
     fn test_string_literal_unterminated() {
         let (input, chars, mut position) = setup("\"hello world");
@@ -166,4 +390,53 @@ mod tests {
         }
         assert_eq!(position, 3);
     }
+
+    #[test]
+    fn test_number_literal_forms() {
+        for (literal, expected) in [
+            ("-5", TemplateNode::Number("-5".into())),
+            ("1_000_000", TemplateNode::Number("1_000_000".into())),
+            ("0xFF_ff", TemplateNode::Number("0xFF_ff".into())),
+            ("-0x10", TemplateNode::Number("-0x10".into())),
+            ("0o17", TemplateNode::Number("0o17".into())),
+            ("-0b1010_1010", TemplateNode::Number("-0b1010_1010".into())),
+            ("-2.5", TemplateNode::Float("-2.5".into())),
+            ("1e-3", TemplateNode::Float("1e-3".into())),
+            ("6.02E+23", TemplateNode::Float("6.02E+23".into())),
+            ("1_0.5e2", TemplateNode::Float("1_0.5e2".into())),
+        ] {
+            let (input, chars, mut position) = setup(literal);
+            let result = number_literal(input, &chars, &mut position);
+
+            assert_eq!(result, Ok(expected));
+            assert_eq!(position, literal.len());
+        }
+    }
+
+    #[test]
+    fn test_number_literal_invalid_forms() {
+        for (literal, at) in [
+            ("0x", 2),
+            ("0xFG", 3),
+            ("0x1.5", 3),
+            ("0o8", 2),
+            ("0b102", 4),
+            ("1e", 2),
+            ("1e+", 3),
+            ("2.5e-x", 5),
+            ("-_1", 1),
+            ("10_000a", 6),
+            ("1e400", 0),
+            ("-1_0.5e999", 0),
+        ] {
+            let (input, chars, mut position) = setup(literal);
+            let result = number_literal(input, &chars, &mut position);
+
+            let Err(Error::BadSyntax(error)) = result else {
+                panic!("Expected a syntax error for {literal}");
+            };
+            assert_eq!(error.kind, SyntaxErrorKind::InvalidNumber);
+            assert_eq!(error.at, at, "{literal}");
+        }
+    }
 }
@@ -0,0 +1,223 @@
+use std::fmt::Write;
+
+use super::{Operator, Syntax, TemplateNode};
+
+impl TemplateNode<'_> {
+    /// Writes the node back as template source with the delimiters of `syntax`, so parsing
+    /// the result gives the same node.
+    ///
+    /// Pipes and method calls are written as the function calls they are, and the whitespace that was
+    /// removed by trim markers or the options of [`Syntax`] is not written back.
+    pub fn write_source(&self, res: &mut String, syntax: &Syntax) {
+        match self {
+            Self::RawText(text) => write_text(res, text, syntax),
+            Self::If(branches, otherwise) => {
+                for (index, (condition, body)) in branches.iter().enumerate() {
+                    let keyword = if index == 0 { "if" } else { "elif" };
+                    write_tag(res, syntax, |res| {
+                        res.push_str(keyword);
+                        res.push(' ');
+                        condition.write_expression(res);
+                    });
+                    write_nodes(res, body, syntax);
+                }
+                write_else_end(res, otherwise.as_deref(), syntax);
+            }
+            Self::For(item, iterable, body, otherwise) => {
+                write_tag(res, syntax, |res| {
+                    write!(res, "for {item} in ").expect("writing to a String never fails");
+                    iterable.write_expression(res);
+                });
+                write_nodes(res, body, syntax);
+                write_else_end(res, otherwise.as_deref(), syntax);
+            }
+            Self::Include(name) => write_tag(res, syntax, |res| {
+                res.push_str("include ");
+                name.write_expression(res);
+            }),
+            Self::Extends(name) => write_tag(res, syntax, |res| {
+                res.push_str("extends ");
+                name.write_expression(res);
+            }),
+            Self::Block(name, body) => {
+                write_tag(res, syntax, |res| {
+                    write!(res, "block {name}").expect("writing to a String never fails");
+                });
+                write_nodes(res, body, syntax);
+                write_else_end(res, None, syntax);
+            }
+            Self::Macro(name, parameters, body) => {
+                write_tag(res, syntax, |res| {
+                    write!(res, "macro {name}(").expect("writing to a String never fails");
+                    for (index, parameter) in parameters.iter().enumerate() {
+                        if index > 0 {
+                            res.push_str(", ");
+                        }
+                        parameter.write_expression(res);
+                    }
+                    res.push(')');
+                });
+                write_nodes(res, body, syntax);
+                write_else_end(res, None, syntax);
+            }
+            Self::Import(name) => write_tag(res, syntax, |res| {
+                res.push_str("import ");
+                name.write_expression(res);
+            }),
+            Self::Set(name, value) => write_tag(res, syntax, |res| {
+                write!(res, "set {name} = ").expect("writing to a String never fails");
+                value.write_expression(res);
+            }),
+            // the tag is written again from its node, the trim markers were applied by parsing it
+            Self::Tag(_, tnode) => tnode.write_source(res, syntax),
+            _ => write_tag(res, syntax, |res| self.write_expression(res)),
+        }
+    }
+
+    /// Writes an expression without the delimiters of its tag.
+    pub fn write_expression(&self, res: &mut String) {
+        match self {
+            Self::Variable(name) => res.push_str(name),
+            Self::Function(name, args, _) => {
+                res.push_str(name);
+                res.push('(');
+                for (index, arg) in args.iter().enumerate() {
+                    if index > 0 {
+                        res.push_str(", ");
+                    }
+                    arg.write_expression(res);
+                }
+                res.push(')');
+            }
+            Self::String(text) | Self::RawText(text) => write_string(res, text),
+            Self::Number(text) | Self::Float(text) => res.push_str(text),
+            Self::Bool(value) => write!(res, "{value}").expect("writing to a String never fails"),
+            Self::Null => res.push_str("null"),
+            Self::Binary(operator, lhs, rhs) => {
+                lhs.write_operand(res, operator.precedence());
+                write!(res, " {operator} ").expect("writing to a String never fails");
+                rhs.write_operand(res, operator.precedence() + 1);
+            }
+            Self::Unary(Operator::Not, operand) => {
+                res.push_str("not ");
+                operand.write_operand(res, Operator::NOT_OPERAND);
+            }
+            Self::Unary(operator, operand) => {
+                res.push_str(operator.symbol());
+                operand.write_operand(res, operator.precedence());
+            }
+            Self::Index(value, index) => {
+                value.write_operand(res, u8::MAX);
+                res.push('[');
+                index.write_expression(res);
+                res.push(']');
+            }
+            Self::Member(value, name) => {
+                value.write_operand(res, u8::MAX);
+                res.push('.');
+                res.push_str(name);
+            }
+            Self::Named(name, value) => {
+                res.push_str(name);
+                res.push('=');
+                value.write_expression(res);
+            }
+            // blocks are not expressions, they are written as the text they render
+            Self::If(..)
+            | Self::For(..)
+            | Self::Include(..)
+            | Self::Extends(..)
+            | Self::Block(..)
+            | Self::Macro(..)
+            | Self::Import(..)
+            | Self::Set(..)
+            | Self::Tag(..) => {}
+        }
+    }
+
+    /// Writes an operand of an operator or an access, between parentheses if it has a lower
+    /// precedence than `min_precedence`.
+    fn write_operand(&self, res: &mut String, min_precedence: u8) {
+        let precedence = match self {
+            Self::Binary(operator, ..) | Self::Unary(operator, _) => operator.precedence(),
+            _ => u8::MAX,
+        };
+
+        if precedence < min_precedence {
+            res.push('(');
+            self.write_expression(res);
+            res.push(')');
+        } else {
+            self.write_expression(res);
+        }
+    }
+}
+
+fn write_nodes(res: &mut String, nodes: &[TemplateNode], syntax: &Syntax) {
+    for node in nodes {
+        node.write_source(res, syntax);
+    }
+}
+
+fn write_else_end(res: &mut String, otherwise: Option<&[TemplateNode]>, syntax: &Syntax) {
+    if let Some(otherwise) = otherwise {
+        write_tag(res, syntax, |res| res.push_str("else"));
+        write_nodes(res, otherwise, syntax);
+    }
+    write_tag(res, syntax, |res| res.push_str("end"));
+}
+
+fn write_tag(res: &mut String, syntax: &Syntax, content: impl FnOnce(&mut String)) {
+    res.push_str(syntax.start);
+    res.push(' ');
+    content(res);
+    res.push(' ');
+    res.push_str(syntax.close);
+}
+
+/// Writes a text doubling the opening delimiters in it, so they are not read as a tag. The
+/// opening delimiter of the comments is written as a quoted text in a tag, unless it starts
+/// with the one of the tags and is already escaped by it.
+fn write_text(res: &mut String, text: &str, syntax: &Syntax) {
+    let comment_start = syntax
+        .comment
+        .map(|(comment_start, _)| comment_start)
+        .filter(|comment_start| {
+            !comment_start.is_empty() && !comment_start.starts_with(syntax.start)
+        });
+
+    let mut rest = text;
+    while !rest.is_empty() {
+        if !syntax.start.is_empty() && rest.starts_with(syntax.start) {
+            res.push_str(syntax.start);
+            res.push_str(syntax.start);
+            rest = &rest[syntax.start.len()..];
+            continue;
+        }
+        if let Some(comment_start) = comment_start.filter(|delim| rest.starts_with(delim)) {
+            write_tag(res, syntax, |res| write_string(res, comment_start));
+            rest = &rest[comment_start.len()..];
+            continue;
+        }
+
+        let next = rest.chars().next().map_or(0, char::len_utf8);
+        res.push_str(&rest[..next]);
+        rest = &rest[next..];
+    }
+}
+
+/// Writes a string literal, escaping the characters that can not be written as they are.
+fn write_string(res: &mut String, text: &str) {
+    res.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+}
@@ -1,12 +1,56 @@
 use super::*;
 
+/// Parses like [`super::parser`], without the [`TemplateNode::Tag`] around the nodes of the
+/// tags, see `tag_source` for the tests of their source.
+fn parser<'a>(
+    input: &'a str,
+    start: &str,
+    close: &str,
+) -> Result<Vec<TemplateNode<'a>>, crate::Error> {
+    super::parser(input, start, close).map(without_tags)
+}
+
+/// Replaces every [`TemplateNode::Tag`] with the node of its tag.
+fn without_tags(tnodes: Vec<TemplateNode<'_>>) -> Vec<TemplateNode<'_>> {
+    tnodes
+        .into_iter()
+        .map(|tnode| match tnode {
+            TemplateNode::Tag(_, tnode) => without_tags(vec![*tnode]).remove(0),
+            TemplateNode::If(branches, otherwise) => TemplateNode::If(
+                branches
+                    .into_iter()
+                    .map(|(condition, body)| (condition, without_tags(body)))
+                    .collect(),
+                otherwise.map(without_tags),
+            ),
+            TemplateNode::For(item, iterable, body, otherwise) => TemplateNode::For(
+                item,
+                iterable,
+                without_tags(body),
+                otherwise.map(without_tags),
+            ),
+            TemplateNode::Block(name, body) => TemplateNode::Block(name, without_tags(body)),
+            TemplateNode::Macro(name, parameters, body) => {
+                TemplateNode::Macro(name, parameters, without_tags(body))
+            }
+            tnode => tnode,
+        })
+        .collect()
+}
+
+/// Byte range of the first occurrence of `call` in `input`.
+fn span(input: &str, call: &str) -> Range<usize> {
+    let start = input.find(call).expect("call in input");
+    start..start + call.len()
+}
+
 #[test]
 fn not_template() {
     let s = "Hello World!";
     let res = parser(s, "{{", "}}");
 
     assert!(res.is_ok());
-    assert_eq!(res, Ok(vec![TemplateNode::RawText("Hello World!")]));
+    assert_eq!(res, Ok(vec![TemplateNode::RawText("Hello World!".into())]));
 }
 
 #[test]
@@ -65,8 +109,12 @@ fn function_outside() {
     assert_eq!(
         res,
         Ok(vec![
-            TemplateNode::RawText("Hello trim(var) "),
-            TemplateNode::Function("toLowerCase", vec![TemplateNode::Variable("variable1")])
+            TemplateNode::RawText("Hello trim(var) ".into()),
+            TemplateNode::Function(
+                "toLowerCase".into(),
+                vec![TemplateNode::Variable("variable1".into())],
+                span(s, "toLowerCase(variable1)")
+            )
         ])
     );
 }
@@ -78,11 +126,13 @@ fn test_function_parser() {
     assert_eq!(
         result,
         Ok(vec![TemplateNode::Function(
-            "toLowerCase",
+            "toLowerCase".into(),
             vec![TemplateNode::Function(
-                "trim",
-                vec![TemplateNode::Variable("variable")]
-            )]
+                "trim".into(),
+                vec![TemplateNode::Variable("variable".into())],
+                span(input, "trim(variable)")
+            )],
+            span(input, "toLowerCase(trim(variable))")
         )])
     );
 }
@@ -93,7 +143,11 @@ fn test_function_without_args() {
     let result = parser(input, "{{", "}}");
     assert_eq!(
         result,
-        Ok(vec![TemplateNode::Function("toLowerCase", vec![])])
+        Ok(vec![TemplateNode::Function(
+            "toLowerCase".into(),
+            vec![],
+            span(input, "toLowerCase()")
+        )])
     );
 }
 
@@ -104,16 +158,28 @@ fn test_function_multiple_param() {
     assert_eq!(
         result,
         Ok(vec![TemplateNode::Function(
-            "toLowerCase",
+            "toLowerCase".into(),
             vec![
-                TemplateNode::Variable("variable1"),
-                TemplateNode::Function("trim", vec![TemplateNode::Variable("variable"),]),
+                TemplateNode::Variable("variable1".into()),
                 TemplateNode::Function(
-                    "add_u8",
-                    vec![TemplateNode::Number("10"), TemplateNode::Number("15")]
+                    "trim".into(),
+                    vec![TemplateNode::Variable("variable".into()),],
+                    span(input, "trim(variable)")
                 ),
-                TemplateNode::Variable("variable2"),
-            ]
+                TemplateNode::Function(
+                    "add_u8".into(),
+                    vec![
+                        TemplateNode::Number("10".into()),
+                        TemplateNode::Number("15".into())
+                    ],
+                    span(input, "add_u8(10, 15)")
+                ),
+                TemplateNode::Variable("variable2".into()),
+            ],
+            span(
+                input,
+                "toLowerCase(variable1, trim(variable), add_u8(10, 15), variable2)"
+            )
         )])
     );
 }
@@ -123,7 +189,13 @@ fn raw_text() {
     let s = r#"Hello {{ "ThIs Is a EXAMPLE" }}"#;
     let res = parser(s, "{{", "}}");
 
-    assert!(res.is_err());
+    assert_eq!(
+        res,
+        Ok(vec![
+            TemplateNode::RawText("Hello ".into()),
+            TemplateNode::String("ThIs Is a EXAMPLE".into()),
+        ])
+    );
 }
 
 #[test]
@@ -134,10 +206,11 @@ fn inner_function_raw_text() {
     assert_eq!(
         res,
         Ok(vec![
-            TemplateNode::RawText("Hello "),
+            TemplateNode::RawText("Hello ".into()),
             TemplateNode::Function(
-                "toLowerCase",
-                vec![TemplateNode::String("ThIs Is a EXAMPLE")]
+                "toLowerCase".into(),
+                vec![TemplateNode::String("ThIs Is a EXAMPLE".into())],
+                span(s, "toLowerCase(\"ThIs Is a EXAMPLE\")")
             ),
         ])
     );
@@ -151,15 +224,16 @@ fn numbers() {
     assert_eq!(
         res,
         Ok(vec![
-            TemplateNode::RawText("Hello "),
+            TemplateNode::RawText("Hello ".into()),
             TemplateNode::Function(
-                "test",
+                "test".into(),
                 vec![
-                    TemplateNode::Number("14"),
-                    TemplateNode::Float("0.25"),
-                    TemplateNode::Number("00000"),
-                    TemplateNode::Float("00000.0"),
-                ]
+                    TemplateNode::Number("14".into()),
+                    TemplateNode::Float("0.25".into()),
+                    TemplateNode::Number("00000".into()),
+                    TemplateNode::Float("00000.0".into()),
+                ],
+                span(s, "test(14, 0.25, 00000, 00000.0)")
             ),
         ])
     );
@@ -186,19 +260,22 @@ fn recursive_function_syntax() {
     assert_eq!(
         res,
         Ok(vec![
-            TemplateNode::RawText("Hello "),
+            TemplateNode::RawText("Hello ".into()),
             TemplateNode::Function(
-                "toLowerCase",
+                "toLowerCase".into(),
                 vec![TemplateNode::Function(
-                    "trim",
+                    "trim".into(),
                     vec![TemplateNode::Function(
-                        "split",
+                        "split".into(),
                         vec![
-                            TemplateNode::Variable("variable1"),
-                            TemplateNode::String("|")
-                        ]
-                    )]
-                )]
+                            TemplateNode::Variable("variable1".into()),
+                            TemplateNode::String("|".into())
+                        ],
+                        span(s, "split(variable1, \"|\")")
+                    )],
+                    span(s, "trim(split(variable1, \"|\"))")
+                )],
+                span(s, "toLowerCase(trim(split(variable1, \"|\")))")
             )
         ])
     );
@@ -211,16 +288,1370 @@ fn test_parser() {
     assert_eq!(
         result,
         Ok(vec![
-            TemplateNode::RawText("This is some text. "),
-            TemplateNode::Variable("variable"),
-            TemplateNode::RawText(" and "),
+            TemplateNode::RawText("This is some text. ".into()),
+            TemplateNode::Variable("variable".into()),
+            TemplateNode::RawText(" and ".into()),
             TemplateNode::Function(
-                "toLowerCase",
+                "toLowerCase".into(),
                 vec![TemplateNode::Function(
-                    "trim",
-                    vec![TemplateNode::Variable("variable")]
-                )]
+                    "trim".into(),
+                    vec![TemplateNode::Variable("variable".into())],
+                    span(input, "trim(variable)")
+                )],
+                span(input, "toLowerCase(trim(variable))")
             )
         ])
     );
 }
+
+#[test]
+fn if_block() {
+    let input = "{{ if admin }}Admin{{ end }}";
+    let result = parser(input, "{{", "}}");
+    assert_eq!(
+        result,
+        Ok(vec![TemplateNode::If(
+            vec![(
+                TemplateNode::Variable("admin".into()),
+                vec![TemplateNode::RawText("Admin".into())]
+            )],
+            None
+        )])
+    );
+}
+
+#[test]
+fn if_elif_else_block() {
+    let input = "A{{ if user.admin }}Admin{{elif trim(name)}}{{ name }}{{ else }}Guest{{ end }}B";
+    let result = parser(input, "{{", "}}");
+    assert_eq!(
+        result,
+        Ok(vec![
+            TemplateNode::RawText("A".into()),
+            TemplateNode::If(
+                vec![
+                    (
+                        TemplateNode::Variable("user.admin".into()),
+                        vec![TemplateNode::RawText("Admin".into())]
+                    ),
+                    (
+                        TemplateNode::Function(
+                            "trim".into(),
+                            vec![TemplateNode::Variable("name".into())],
+                            span(input, "trim(name)")
+                        ),
+                        vec![TemplateNode::Variable("name".into())]
+                    ),
+                ],
+                Some(vec![TemplateNode::RawText("Guest".into())])
+            ),
+            TemplateNode::RawText("B".into()),
+        ])
+    );
+}
+
+#[test]
+fn nested_if_block() {
+    let input = "{{ if a }}{{ if b }}ab{{ end }}{{ else }}none{{ end }}";
+    let result = parser(input, "{{", "}}");
+    assert_eq!(
+        result,
+        Ok(vec![TemplateNode::If(
+            vec![(
+                TemplateNode::Variable("a".into()),
+                vec![TemplateNode::If(
+                    vec![(
+                        TemplateNode::Variable("b".into()),
+                        vec![TemplateNode::RawText("ab".into())]
+                    )],
+                    None
+                )]
+            )],
+            Some(vec![TemplateNode::RawText("none".into())])
+        )])
+    );
+}
+
+#[test]
+fn keyword_prefixed_variable() {
+    let input = "{{ ending }}{{ iffy }}";
+    let result = parser(input, "{{", "}}");
+    assert_eq!(
+        result,
+        Ok(vec![
+            TemplateNode::Variable("ending".into()),
+            TemplateNode::Variable("iffy".into()),
+        ])
+    );
+}
+
+#[test]
+fn unclosed_if_block() {
+    let input = "Hello\n  {{ if admin }}Admin";
+    let result = parser(input, "{{", "}}");
+
+    let Err(crate::Error::BadSyntax(error)) = result else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(error.kind, SyntaxErrorKind::UnclosedBlock("if".to_string()));
+    assert_eq!(error.line, 2);
+    assert_eq!(error.column, 2);
+    assert_eq!(error.at, 8);
+}
+
+#[test]
+fn unexpected_end_block() {
+    let input = "Hello {{ end }}";
+    let result = parser(input, "{{", "}}");
+
+    let Err(crate::Error::BadSyntax(error)) = result else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(
+        error.kind,
+        SyntaxErrorKind::UnexpectedBlock("end".to_string())
+    );
+    assert_eq!(error.at, 6);
+}
+
+#[test]
+fn mismatched_else_block() {
+    let input = "{{ if a }}a{{ else }}b{{ elif c }}c{{ end }}";
+    let result = parser(input, "{{", "}}");
+
+    let Err(crate::Error::BadSyntax(error)) = result else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(error.kind, SyntaxErrorKind::MismatchedBlock("end", "elif"));
+}
+
+#[test]
+fn for_block() {
+    let input = "{{ for item in items }}- {{ item }}{{ end }}";
+    let result = parser(input, "{{", "}}");
+    assert_eq!(
+        result,
+        Ok(vec![TemplateNode::For(
+            "item".into(),
+            Box::new(TemplateNode::Variable("items".into())),
+            vec![
+                TemplateNode::RawText("- ".into()),
+                TemplateNode::Variable("item".into())
+            ],
+            None
+        )])
+    );
+}
+
+#[test]
+fn for_else_nested_block() {
+    let input =
+        "{{ for row in rows }}{{ for col in row }}{{ col }}{{ end }}{{ else }}empty{{ end }}";
+    let result = parser(input, "{{", "}}");
+    assert_eq!(
+        result,
+        Ok(vec![TemplateNode::For(
+            "row".into(),
+            Box::new(TemplateNode::Variable("rows".into())),
+            vec![TemplateNode::For(
+                "col".into(),
+                Box::new(TemplateNode::Variable("row".into())),
+                vec![TemplateNode::Variable("col".into())],
+                None
+            )],
+            Some(vec![TemplateNode::RawText("empty".into())])
+        )])
+    );
+}
+
+#[test]
+fn for_without_in() {
+    let input = "{{ for item of items }}{{ end }}";
+    let result = parser(input, "{{", "}}");
+
+    let Err(crate::Error::BadSyntax(error)) = result else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(
+        error.kind,
+        SyntaxErrorKind::Expected(
+            SyntaxErrorToken::String("in".to_string()),
+            SyntaxErrorToken::Char('o')
+        )
+    );
+    assert_eq!(error.at, 12);
+}
+
+#[test]
+fn unclosed_for_block() {
+    let input = "{{ for item in items }}{{ if item }}{{ end }}";
+    let result = parser(input, "{{", "}}");
+
+    let Err(crate::Error::BadSyntax(error)) = result else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(
+        error.kind,
+        SyntaxErrorKind::UnclosedBlock("for".to_string())
+    );
+    assert_eq!(error.at, 0);
+}
+
+#[test]
+fn for_elif_block() {
+    let input = "{{ for item in items }}{{ elif item }}{{ end }}";
+    let result = parser(input, "{{", "}}");
+
+    let Err(crate::Error::BadSyntax(error)) = result else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(error.kind, SyntaxErrorKind::MismatchedBlock("end", "elif"));
+}
+
+#[test]
+fn pipe_filters() {
+    let input = "{{ name | trim | toUpper }}";
+    let result = parser(input, "{{", "}}");
+    assert_eq!(
+        result,
+        Ok(vec![TemplateNode::Function(
+            "toUpper".into(),
+            vec![TemplateNode::Function(
+                "trim".into(),
+                vec![TemplateNode::Variable("name".into())],
+                span(input, "trim")
+            )],
+            span(input, "toUpper")
+        )])
+    );
+}
+
+#[test]
+fn pipe_with_arguments() {
+    let input = r#"{{ toLower(name) | trim("!", 2 | abs) |pad }}"#;
+    let result = parser(input, "{{", "}}");
+    assert_eq!(
+        result,
+        Ok(vec![TemplateNode::Function(
+            "pad".into(),
+            vec![TemplateNode::Function(
+                "trim".into(),
+                vec![
+                    TemplateNode::Function(
+                        "toLower".into(),
+                        vec![TemplateNode::Variable("name".into())],
+                        span(input, "toLower(name)")
+                    ),
+                    TemplateNode::String("!".into()),
+                    TemplateNode::Function(
+                        "abs".into(),
+                        vec![TemplateNode::Number("2".into())],
+                        span(input, "abs")
+                    ),
+                ],
+                span(input, r#"trim("!", 2 | abs)"#)
+            )],
+            span(input, "pad")
+        )])
+    );
+}
+
+#[test]
+fn pipe_with_pipe_delimiters() {
+    let input = "{| name |} {| name | trim |} {|- name -|}";
+    let result = parser(input, "{|", "|}");
+    assert_eq!(
+        result,
+        Ok(vec![
+            TemplateNode::Variable("name".into()),
+            TemplateNode::RawText(" ".into()),
+            TemplateNode::Function(
+                "trim".into(),
+                vec![TemplateNode::Variable("name".into())],
+                span(input, "trim")
+            ),
+            TemplateNode::Variable("name".into()),
+        ])
+    );
+}
+
+#[test]
+fn pipe_in_block_condition() {
+    let input = "{{ if name | trim }}{{ name }}{{ end }}";
+    let result = parser(input, "{{", "}}");
+    assert_eq!(
+        result,
+        Ok(vec![TemplateNode::If(
+            vec![(
+                TemplateNode::Function(
+                    "trim".into(),
+                    vec![TemplateNode::Variable("name".into())],
+                    span(input, "trim")
+                ),
+                vec![TemplateNode::Variable("name".into())]
+            )],
+            None
+        )])
+    );
+}
+
+#[test]
+fn pipe_without_function() {
+    let input = "{{ name | trim | }}";
+    let result = parser(input, "{{", "}}");
+
+    let Err(crate::Error::BadSyntax(error)) = result else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(
+        error.kind,
+        SyntaxErrorKind::Expected(
+            SyntaxErrorToken::String("function".to_string()),
+            SyntaxErrorToken::Char('}')
+        )
+    );
+    assert_eq!(error.at, 17);
+
+    let input = r#"{{ name | "trim" }}"#;
+    let Err(crate::Error::BadSyntax(error)) = parser(input, "{{", "}}") else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(
+        error.kind,
+        SyntaxErrorKind::Expected(
+            SyntaxErrorToken::String("function".to_string()),
+            SyntaxErrorToken::Char('"')
+        )
+    );
+    assert_eq!(error.at, 10);
+}
+
+#[test]
+fn pipe_unterminated_arguments() {
+    let input = "{{ name | trim | pad(2 }}";
+    let result = parser(input, "{{", "}}");
+
+    let Err(crate::Error::BadSyntax(error)) = result else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(error.kind, SyntaxErrorKind::UnterminatedArgument);
+    assert_eq!(error.at, 23);
+}
+
+#[test]
+fn trim_markers() {
+    let input = "Hello  \n {{- name -}} \n\t!";
+    let result = parser(input, "{{", "}}");
+    assert_eq!(
+        result,
+        Ok(vec![
+            TemplateNode::RawText("Hello".into()),
+            TemplateNode::Variable("name".into()),
+            TemplateNode::RawText("!".into()),
+        ])
+    );
+}
+
+#[test]
+fn trim_markers_on_blocks() {
+    let input = "<ul>\n  {{- for item in items -}}\n  <li>{{ item }}</li>\n  {{- end }}\n</ul>";
+    let result = parser(input, "{{", "}}");
+    assert_eq!(
+        result,
+        Ok(vec![
+            TemplateNode::RawText("<ul>".into()),
+            TemplateNode::For(
+                "item".into(),
+                Box::new(TemplateNode::Variable("items".into())),
+                vec![
+                    TemplateNode::RawText("<li>".into()),
+                    TemplateNode::Variable("item".into()),
+                    TemplateNode::RawText("</li>".into()),
+                ],
+                None
+            ),
+            TemplateNode::RawText("\n</ul>".into()),
+        ])
+    );
+}
+
+#[test]
+fn trim_markers_custom_delimiters() {
+    let input = "a \n<%- if x -%>\n b \n<%- end -%>\n c";
+    let result = parser(input, "<%", "%>");
+    assert_eq!(
+        result,
+        Ok(vec![
+            TemplateNode::RawText("a".into()),
+            TemplateNode::If(
+                vec![(
+                    TemplateNode::Variable("x".into()),
+                    vec![TemplateNode::RawText("b".into())]
+                )],
+                None
+            ),
+            TemplateNode::RawText("c".into()),
+        ])
+    );
+}
+
+#[test]
+fn trim_blocks_and_lstrip_blocks() {
+    let input = "list:\n  {{ if show }}\n  - {{ name }}\n  {{ end }}\ndone {{ name }}\n";
+    let syntax = Syntax {
+        trim_blocks: true,
+        lstrip_blocks: true,
+        ..Syntax::new("{{", "}}")
+    };
+    let result = parse(input, &syntax).map(without_tags);
+    assert_eq!(
+        result,
+        Ok(vec![
+            TemplateNode::RawText("list:\n".into()),
+            TemplateNode::If(
+                vec![(
+                    TemplateNode::Variable("show".into()),
+                    vec![
+                        TemplateNode::RawText("  - ".into()),
+                        TemplateNode::Variable("name".into()),
+                        TemplateNode::RawText("\n".into()),
+                    ]
+                )],
+                None
+            ),
+            TemplateNode::RawText("done ".into()),
+            TemplateNode::Variable("name".into()),
+            TemplateNode::RawText("\n".into()),
+        ])
+    );
+}
+
+#[test]
+fn lstrip_blocks_only_at_line_start() {
+    let input = "a {{ if x }}b{{ end }}";
+    let syntax = Syntax {
+        lstrip_blocks: true,
+        ..Syntax::new("{{", "}}")
+    };
+    let result = parse(input, &syntax).map(without_tags);
+    assert_eq!(
+        result,
+        Ok(vec![
+            TemplateNode::RawText("a ".into()),
+            TemplateNode::If(
+                vec![(
+                    TemplateNode::Variable("x".into()),
+                    vec![TemplateNode::RawText("b".into())]
+                )],
+                None
+            ),
+        ])
+    );
+}
+
+#[test]
+fn comments() {
+    let input = "Hello {{# the name\n of the {{ user }} #}}{{ name }}{{#-#}} !";
+    let result = parser(input, "{{", "}}");
+    assert_eq!(
+        result,
+        Ok(vec![
+            TemplateNode::RawText("Hello ".into()),
+            TemplateNode::Variable("name".into()),
+            TemplateNode::RawText(" !".into()),
+        ])
+    );
+}
+
+#[test]
+fn comments_with_trim_markers() {
+    let input = "a\n  {{#- note -#}}\n  b <%# kept #%>";
+    let result = parser(input, "{{", "}}");
+    assert_eq!(
+        result,
+        Ok(vec![
+            TemplateNode::RawText("a".into()),
+            TemplateNode::RawText("b <%# kept #%>".into()),
+        ])
+    );
+}
+
+#[test]
+fn comments_custom_delimiters() {
+    let input = "a <%# {{ not a tag }} #%><% b %>";
+    let result = parser(input, "<%", "%>");
+    assert_eq!(
+        result,
+        Ok(vec![
+            TemplateNode::RawText("a ".into()),
+            TemplateNode::Variable("b".into()),
+        ])
+    );
+}
+
+#[test]
+fn comment_line_with_trim_blocks() {
+    let input = "a\n  {{# note #}}\nb";
+    let syntax = Syntax {
+        comment: Some(("{{#", "#}}")),
+        trim_blocks: true,
+        lstrip_blocks: true,
+        ..Syntax::new("{{", "}}")
+    };
+    let result = parse(input, &syntax).map(without_tags);
+    assert_eq!(
+        result,
+        Ok(vec![
+            TemplateNode::RawText("a\n".into()),
+            TemplateNode::RawText("b".into()),
+        ])
+    );
+}
+
+#[test]
+fn unclosed_comment() {
+    let input = "Hello\n{{# name }}";
+    let result = parser(input, "{{", "}}");
+
+    let Err(crate::Error::BadSyntax(error)) = result else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(error.kind, SyntaxErrorKind::UnclosedComment);
+    assert_eq!(error.line, 2);
+    assert_eq!(error.at, 6);
+}
+
+#[test]
+fn empty_tag() {
+    let input = "Hello {{ }}";
+    let result = parser(input, "{{", "}}");
+
+    let Err(crate::Error::BadSyntax(error)) = result else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(
+        error.kind,
+        SyntaxErrorKind::Expected(
+            SyntaxErrorToken::String("expression".to_string()),
+            SyntaxErrorToken::Char('}')
+        )
+    );
+    assert_eq!(error.at, 9);
+}
+
+#[test]
+fn escaped_delimiter() {
+    let input = "Use {{{{ name }} for {{ name }}, {{{{# too {{{{{{ x }}";
+    let result = parser(input, "{{", "}}");
+    assert_eq!(
+        result,
+        Ok(vec![
+            TemplateNode::RawText("Use ".into()),
+            TemplateNode::RawText("{{ name }} for ".into()),
+            TemplateNode::Variable("name".into()),
+            TemplateNode::RawText(", ".into()),
+            TemplateNode::RawText("{{# too ".into()),
+            TemplateNode::RawText("{{".into()),
+            TemplateNode::Variable("x".into()),
+        ])
+    );
+}
+
+#[test]
+fn backslash_is_text() {
+    let input = r"C:\Users\{{ user }} \\{{ a }}";
+    let result = parser(input, "{{", "}}");
+    assert_eq!(
+        result,
+        Ok(vec![
+            TemplateNode::RawText(r"C:\Users\".into()),
+            TemplateNode::Variable("user".into()),
+            TemplateNode::RawText(r" \\".into()),
+            TemplateNode::Variable("a".into()),
+        ])
+    );
+}
+
+#[test]
+fn escaped_custom_delimiter() {
+    let input = "<%<% a %> {{ b }}";
+    let result = parser(input, "<%", "%>");
+    assert_eq!(
+        result,
+        Ok(vec![TemplateNode::RawText("<% a %> {{ b }}".into())])
+    );
+}
+
+#[test]
+fn include_tag() {
+    let input = "a\n  {{ include \"header\" }}\n{{- include name }} b";
+    let syntax = Syntax {
+        trim_blocks: true,
+        lstrip_blocks: true,
+        ..Syntax::new("{{", "}}")
+    };
+    let result = without_tags(parse(input, &syntax).unwrap());
+
+    assert_eq!(
+        result,
+        vec![
+            TemplateNode::RawText("a\n".into()),
+            TemplateNode::Include(Box::new(TemplateNode::String("header".into()))),
+            TemplateNode::Include(Box::new(variable("name"))),
+            TemplateNode::RawText(" b".into()),
+        ]
+    );
+}
+
+#[test]
+fn extends_and_block_tags() {
+    let input =
+        "{{ extends layout }}{{ block title }}T{{ block inner }}{{ super() }}{{ end }}{{ end }}";
+    let result = parser(input, "{{", "}}").unwrap();
+
+    assert_eq!(
+        result,
+        vec![
+            TemplateNode::Extends(Box::new(variable("layout"))),
+            TemplateNode::Block(
+                "title".into(),
+                vec![
+                    TemplateNode::RawText("T".into()),
+                    TemplateNode::Block(
+                        "inner".into(),
+                        vec![TemplateNode::Function("super".into(), vec![], 58..65)],
+                    ),
+                ],
+            ),
+        ]
+    );
+}
+
+#[test]
+fn block_errors() {
+    let error = |input: &str| match parser(input, "{{", "}}") {
+        Err(crate::Error::BadSyntax(error)) => (error.kind, error.at),
+        result => panic!("Expected a syntax error, got {result:?}"),
+    };
+
+    assert_eq!(
+        error("a{{ block title }}T"),
+        (SyntaxErrorKind::UnclosedBlock("block".to_string()), 1)
+    );
+    assert_eq!(
+        error("{{ block title }}T{{ else }}{{ end }}").0,
+        SyntaxErrorKind::MismatchedBlock("end", "else")
+    );
+    assert!(matches!(
+        error("{{ block a.b }}{{ end }}").0,
+        SyntaxErrorKind::Expected(..)
+    ));
+}
+
+#[test]
+fn macro_and_import_tags() {
+    let input = r##"{{ import "forms" }}{{ macro button(label, url="#") }}{{ label }}{{ end }}"##;
+    let result = parser(input, "{{", "}}").unwrap();
+
+    assert_eq!(
+        result,
+        vec![
+            TemplateNode::Import(Box::new(TemplateNode::String("forms".into()))),
+            TemplateNode::Macro(
+                "button".into(),
+                vec![
+                    variable("label"),
+                    TemplateNode::Named("url".into(), Box::new(TemplateNode::String("#".into()))),
+                ],
+                vec![variable("label")],
+            ),
+        ]
+    );
+    assert_eq!(
+        parser("{{ macro empty() }}{{ end }}", "{{", "}}"),
+        Ok(vec![TemplateNode::Macro("empty".into(), vec![], vec![])])
+    );
+}
+
+#[test]
+fn macro_errors() {
+    let error = |input: &str| match parser(input, "{{", "}}") {
+        Err(crate::Error::BadSyntax(error)) => (error.kind, error.at),
+        result => panic!("Expected a syntax error, got {result:?}"),
+    };
+
+    assert_eq!(
+        error("{{ macro m(a) }}"),
+        (SyntaxErrorKind::UnclosedBlock("macro".to_string()), 0)
+    );
+    assert!(matches!(
+        error("{{ macro m }}{{ end }}"),
+        (SyntaxErrorKind::Expected(..), 11)
+    ));
+    assert!(matches!(
+        error("{{ macro m(a.b) }}{{ end }}"),
+        (SyntaxErrorKind::Expected(..), 11)
+    ));
+    assert!(matches!(
+        error("{{ macro m(\"a\") }}{{ end }}"),
+        (SyntaxErrorKind::Expected(..), 11)
+    ));
+    assert_eq!(
+        error("{{ macro m(a=1, b) }}{{ end }}").0,
+        SyntaxErrorKind::PositionalAfterNamed("a".to_string())
+    );
+}
+
+#[test]
+fn set_tag() {
+    let input = "{{ set name = trim(user.name) }}\n{{ name }}";
+    let syntax = Syntax {
+        trim_blocks: true,
+        ..Syntax::new("{{", "}}")
+    };
+    let result = without_tags(parse(input, &syntax).unwrap());
+
+    assert_eq!(
+        result,
+        vec![
+            TemplateNode::Set(
+                "name".into(),
+                Box::new(TemplateNode::Function(
+                    "trim".into(),
+                    vec![variable("user.name")],
+                    14..29
+                ))
+            ),
+            variable("name"),
+        ]
+    );
+}
+
+#[test]
+fn set_errors() {
+    let error = |input: &str| match parser(input, "{{", "}}") {
+        Err(crate::Error::BadSyntax(error)) => (error.kind, error.at),
+        result => panic!("Expected a syntax error, got {result:?}"),
+    };
+
+    assert!(matches!(
+        error("{{ set = 1 }}"),
+        (SyntaxErrorKind::Expected(..), 7)
+    ));
+    assert!(matches!(
+        error("{{ set a.b = 1 }}"),
+        (SyntaxErrorKind::Expected(..), 7)
+    ));
+    assert!(matches!(
+        error("{{ set a 1 }}"),
+        (SyntaxErrorKind::Expected(..), 9)
+    ));
+    assert!(matches!(
+        error("{{ set a == 1 }}"),
+        (SyntaxErrorKind::Expected(..), 9)
+    ));
+}
+
+#[test]
+fn raw_block() {
+    let input = "a {{ raw }}{{ if x }}{{ name }}{{# c #}}{{ end }}{{ endraw }} b";
+    let result = parser(input, "{{", "}}");
+    assert_eq!(
+        result,
+        Ok(vec![
+            TemplateNode::RawText("a ".into()),
+            TemplateNode::RawText("{{ if x }}{{ name }}{{# c #}}{{ end }}".into()),
+            TemplateNode::RawText(" b".into()),
+        ])
+    );
+}
+
+#[test]
+fn raw_block_with_trim_markers() {
+    let input = "steps:\n{{- raw -}}\n  run: ${{ github.sha }}\n{{- endraw }}\n";
+    let result = parser(input, "{{", "}}");
+    assert_eq!(
+        result,
+        Ok(vec![
+            TemplateNode::RawText("steps:".into()),
+            TemplateNode::RawText("run: ${{ github.sha }}".into()),
+            TemplateNode::RawText("\n".into()),
+        ])
+    );
+}
+
+#[test]
+fn empty_raw_block() {
+    let input = "{{ raw }}{{ endraw }}";
+    let result = parser(input, "{{", "}}");
+    assert_eq!(result, Ok(vec![]));
+}
+
+#[test]
+fn unclosed_raw_block() {
+    let input = "a {{ raw }}{{ end }}";
+    let result = parser(input, "{{", "}}");
+
+    let Err(crate::Error::BadSyntax(error)) = result else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(
+        error.kind,
+        SyntaxErrorKind::UnclosedBlock("raw".to_string())
+    );
+    assert_eq!(error.at, 2);
+}
+
+#[test]
+fn unexpected_endraw() {
+    let input = "a {{ endraw }}";
+    let result = parser(input, "{{", "}}");
+
+    let Err(crate::Error::BadSyntax(error)) = result else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(
+        error.kind,
+        SyntaxErrorKind::UnexpectedBlock("endraw".to_string())
+    );
+    assert_eq!(error.at, 2);
+}
+
+#[test]
+fn string_literal_forms() {
+    let input = r#"{{ f("a\"b", 'c', r"d\e") }}"#;
+    let result = parser(input, "{{", "}}");
+    assert_eq!(
+        result,
+        Ok(vec![TemplateNode::Function(
+            "f".into(),
+            vec![
+                TemplateNode::String("a\"b".into()),
+                TemplateNode::String("c".into()),
+                TemplateNode::String(r"d\e".into()),
+            ],
+            span(input, r#"f("a\"b", 'c', r"d\e")"#)
+        )])
+    );
+}
+
+#[test]
+fn literal_arguments() {
+    let input = "{{ add(-5, 1e-3, true, null, nullable) }}";
+    let result = parser(input, "{{", "}}");
+    assert_eq!(
+        result,
+        Ok(vec![TemplateNode::Function(
+            "add".into(),
+            vec![
+                TemplateNode::Number("-5".into()),
+                TemplateNode::Float("1e-3".into()),
+                TemplateNode::Bool(true),
+                TemplateNode::Null,
+                TemplateNode::Variable("nullable".into()),
+            ],
+            span(input, "add(-5, 1e-3, true, null, nullable)")
+        )])
+    );
+}
+
+#[test]
+fn invalid_number_argument_position() {
+    let input = "{{ add(1, 0x1G) }}";
+    let result = parser(input, "{{", "}}");
+
+    let Err(crate::Error::BadSyntax(error)) = result else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(error.kind, SyntaxErrorKind::InvalidNumber);
+    assert_eq!(error.at, 13);
+    assert_eq!(error.column, 13);
+}
+
+fn binary<'a>(
+    operator: Operator,
+    lhs: TemplateNode<'a>,
+    rhs: TemplateNode<'a>,
+) -> TemplateNode<'a> {
+    TemplateNode::Binary(operator, Box::new(lhs), Box::new(rhs))
+}
+
+fn variable(name: &str) -> TemplateNode<'_> {
+    TemplateNode::Variable(name.into())
+}
+
+#[test]
+fn operator_precedence() {
+    let result = parser("{{ a + b * 2 - c % 3 }}", "{{", "}}");
+    assert_eq!(
+        result,
+        Ok(vec![binary(
+            Operator::Sub,
+            binary(
+                Operator::Add,
+                variable("a"),
+                binary(
+                    Operator::Mul,
+                    variable("b"),
+                    TemplateNode::Number("2".into())
+                ),
+            ),
+            binary(
+                Operator::Rem,
+                variable("c"),
+                TemplateNode::Number("3".into())
+            ),
+        )])
+    );
+
+    let result = parser("{{ not a == 1 or b and -c >= (d-1) * 2 }}", "{{", "}}");
+    assert_eq!(
+        result,
+        Ok(vec![binary(
+            Operator::Or,
+            TemplateNode::Unary(
+                Operator::Not,
+                Box::new(binary(
+                    Operator::Eq,
+                    variable("a"),
+                    TemplateNode::Number("1".into())
+                )),
+            ),
+            binary(
+                Operator::And,
+                variable("b"),
+                binary(
+                    Operator::Ge,
+                    TemplateNode::Unary(Operator::Neg, Box::new(variable("c"))),
+                    binary(
+                        Operator::Mul,
+                        binary(
+                            Operator::Sub,
+                            variable("d"),
+                            TemplateNode::Number("1".into())
+                        ),
+                        TemplateNode::Number("2".into()),
+                    ),
+                ),
+            ),
+        )])
+    );
+}
+
+#[test]
+fn operators_in_arguments_and_pipes() {
+    let input = "{{ price * qty | round(x != y) }}";
+    let result = parser(input, "{{", "}}");
+    assert_eq!(
+        result,
+        Ok(vec![TemplateNode::Function(
+            "round".into(),
+            vec![
+                binary(Operator::Mul, variable("price"), variable("qty")),
+                binary(Operator::Ne, variable("x"), variable("y")),
+            ],
+            span(input, "round(x != y)")
+        )])
+    );
+}
+
+#[test]
+fn operators_next_to_close_delimiter() {
+    let result = parser("{{ a - 1 -}} x", "{{", "}}");
+    assert_eq!(
+        result,
+        Ok(vec![
+            binary(
+                Operator::Sub,
+                variable("a"),
+                TemplateNode::Number("1".into())
+            ),
+            TemplateNode::RawText("x".into()),
+        ])
+    );
+
+    let result = parser("{% a % b %}", "{%", "%}");
+    assert_eq!(
+        result,
+        Ok(vec![binary(Operator::Rem, variable("a"), variable("b"))])
+    );
+}
+
+#[test]
+fn operator_condition() {
+    let result = parser("{{ if count > 0 }}yes{{ end }}", "{{", "}}");
+    assert_eq!(
+        result,
+        Ok(vec![TemplateNode::If(
+            vec![(
+                binary(
+                    Operator::Gt,
+                    variable("count"),
+                    TemplateNode::Number("0".into())
+                ),
+                vec![TemplateNode::RawText("yes".into())]
+            )],
+            None
+        )])
+    );
+}
+
+#[test]
+fn missing_operand() {
+    for (input, operator, at) in [
+        ("{{ a + }}", "+", 5),
+        ("{{ a * * b }}", "*", 5),
+        ("{{ a and }}", "and", 5),
+        ("{{ not }}", "not", 3),
+        ("{{ f(1 <=) }}", "<=", 7),
+    ] {
+        let Err(crate::Error::BadSyntax(error)) = parser(input, "{{", "}}") else {
+            panic!("Expected a syntax error for {input}");
+        };
+        assert_eq!(
+            error.kind,
+            SyntaxErrorKind::MissingOperand(operator.to_string()),
+            "{input}"
+        );
+        assert_eq!(error.at, at, "{input}");
+    }
+}
+
+#[test]
+fn unclosed_parenthesis() {
+    let result = parser("{{ (a + 1 }}", "{{", "}}");
+
+    let Err(crate::Error::BadSyntax(error)) = result else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(error.kind, SyntaxErrorKind::UnclosedParenthesis);
+    assert_eq!(error.at, 3);
+}
+
+#[test]
+fn index_and_member_access() {
+    let nodes = parser(
+        r#"{{ items[0] }}{{ map["key"] }}{{ items[ -1 ].a.b }}{{ user.name }}"#,
+        "{{",
+        "}}",
+    )
+    .unwrap();
+
+    let index =
+        |value, index: TemplateNode<'static>| TemplateNode::Index(Box::new(value), Box::new(index));
+    let member = |value, name: &'static str| TemplateNode::Member(Box::new(value), name.into());
+    assert_eq!(
+        nodes,
+        vec![
+            index(variable("items"), TemplateNode::Number("0".into())),
+            index(variable("map"), TemplateNode::String("key".into())),
+            member(
+                member(
+                    index(variable("items"), TemplateNode::Number("-1".into())),
+                    "a"
+                ),
+                "b"
+            ),
+            // a dotted name is kept as a variable, so variables with dots in their name still work
+            variable("user.name"),
+        ]
+    );
+
+    // a dot without a name after it is an error at the dot
+    for (input, at) in [
+        ("{{ n. }}", 4),
+        ("{{ .n }}", 3),
+        ("{{ user..name }}", 7),
+        ("{{ n..trim() }}", 4),
+        ("{{ items[0].a. }}", 13),
+    ] {
+        let Err(crate::Error::BadSyntax(error)) = parser(input, "{{", "}}") else {
+            panic!("Expected a syntax error in {input}");
+        };
+        assert_eq!(
+            error.kind,
+            SyntaxErrorKind::Expected(
+                SyntaxErrorToken::String("name".to_owned()),
+                SyntaxErrorToken::Char('.')
+            )
+        );
+        assert_eq!(error.at, at, "{input}");
+    }
+}
+
+#[test]
+fn method_calls() {
+    let nodes = parser(
+        r#"{{ user.name.trim().toUpper() }}{{ items[0].pad(3, "x") }}{{ "a".f ( ).b }}"#,
+        "{{",
+        "}}",
+    )
+    .unwrap();
+
+    assert_eq!(
+        nodes,
+        vec![
+            TemplateNode::Function(
+                "toUpper".into(),
+                vec![TemplateNode::Function(
+                    "trim".into(),
+                    vec![variable("user.name")],
+                    13..19
+                )],
+                20..29
+            ),
+            TemplateNode::Function(
+                "pad".into(),
+                vec![
+                    TemplateNode::Index(
+                        Box::new(variable("items")),
+                        Box::new(TemplateNode::Number("0".into()))
+                    ),
+                    TemplateNode::Number("3".into()),
+                    TemplateNode::String("x".into()),
+                ],
+                44..55
+            ),
+            TemplateNode::Member(
+                Box::new(TemplateNode::Function(
+                    "f".into(),
+                    vec![TemplateNode::String("a".into())],
+                    65..70
+                )),
+                "b".into()
+            ),
+        ]
+    );
+
+    let result = parser("{{ user.() }}", "{{", "}}");
+    let Err(crate::Error::BadSyntax(error)) = result else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(
+        error.kind,
+        SyntaxErrorKind::Expected(
+            SyntaxErrorToken::String("function".to_owned()),
+            SyntaxErrorToken::Char('(')
+        )
+    );
+    assert_eq!(error.at, 8);
+}
+
+#[test]
+fn named_arguments() {
+    let nodes = parser(
+        r#"{{ pad(name, width = 10, fill="*") }}{{ f(a == b) }}"#,
+        "{{",
+        "}}",
+    )
+    .unwrap();
+
+    assert_eq!(
+        nodes,
+        vec![
+            TemplateNode::Function(
+                "pad".into(),
+                vec![
+                    variable("name"),
+                    TemplateNode::Named(
+                        "width".into(),
+                        Box::new(TemplateNode::Number("10".into()))
+                    ),
+                    TemplateNode::Named("fill".into(), Box::new(TemplateNode::String("*".into()))),
+                ],
+                3..34
+            ),
+            TemplateNode::Function(
+                "f".into(),
+                vec![binary(Operator::Eq, variable("a"), variable("b"))],
+                40..49
+            ),
+        ]
+    );
+
+    let result = parser("{{ pad(width=10, name) }}", "{{", "}}");
+    let Err(crate::Error::BadSyntax(error)) = result else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(
+        error.kind,
+        SyntaxErrorKind::PositionalAfterNamed("width".to_owned())
+    );
+    assert_eq!(error.at, 17);
+}
+
+#[test]
+fn unclosed_bracket() {
+    let result = parser("{{ items[0 }}", "{{", "}}");
+
+    let Err(crate::Error::BadSyntax(error)) = result else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(error.kind, SyntaxErrorKind::UnclosedBracket);
+    assert_eq!(error.at, 8);
+}
+
+#[test]
+fn keywords_as_variables() {
+    let input = "{{ set }}{{ block | trim }}{{ end + 1 }}{{ include[0] }}{{ for and x -}}";
+    let result = parser(input, "{{", "}}");
+
+    assert_eq!(
+        result,
+        Ok(vec![
+            variable("set"),
+            TemplateNode::Function("trim".into(), vec![variable("block")], span(input, "trim")),
+            binary(
+                Operator::Add,
+                variable("end"),
+                TemplateNode::Number("1".into())
+            ),
+            TemplateNode::Index(
+                Box::new(variable("include")),
+                Box::new(TemplateNode::Number("0".into()))
+            ),
+            binary(Operator::And, variable("for"), variable("x")),
+        ])
+    );
+}
+
+#[test]
+fn reserved_words() {
+    for (input, message) in [
+        ("{{ end }}", "Unexpected \"end\" outside of a block"),
+        ("{{ else -}}", "Unexpected \"else\" outside of a block"),
+        ("{{ set name }}", "Expected \"=\", but found \"}\""),
+        ("{{ for item }}", "Expected \"in\", but found \"}\""),
+    ] {
+        let Err(crate::Error::BadSyntax(error)) = parser(input, "{{", "}}") else {
+            panic!("Expected a syntax error for {input}");
+        };
+        assert_eq!(error.kind.to_string(), message, "{input}");
+    }
+}
+
+#[test]
+fn coalesce_precedence() {
+    let input = r#"{{ name ?? "a" or b | f }}"#;
+    let result = parser(input, "{{", "}}");
+    assert_eq!(
+        result,
+        Ok(vec![TemplateNode::Function(
+            "f".into(),
+            vec![binary(
+                Operator::Coalesce,
+                variable("name"),
+                binary(
+                    Operator::Or,
+                    TemplateNode::String("a".into()),
+                    variable("b")
+                ),
+            )],
+            span(input, "f")
+        )])
+    );
+
+    let Err(crate::Error::BadSyntax(error)) = parser("{{ name ?? }}", "{{", "}}") else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(
+        error.kind,
+        SyntaxErrorKind::MissingOperand("??".to_string())
+    );
+    assert_eq!(error.at, 8);
+}
+
+#[test]
+fn tag_source() {
+    let input = "a {{-name  -}}\n{{ if x }}{{y}}{{ end }}\n{{set z=1}}{{ raw }}{{ q }}{{ endraw }}";
+    let syntax = Syntax {
+        trim_blocks: true,
+        ..Syntax::new("{{", "}}")
+    };
+    let sources: Vec<_> = parse(input, &syntax)
+        .unwrap()
+        .into_iter()
+        .filter_map(|tnode| match tnode {
+            TemplateNode::Tag(source, _) => Some(source),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(
+        sources,
+        ["{{-name  -}}", "{{ if x }}{{y}}{{ end }}", "{{set z=1}}"]
+    );
+}
+
+#[test]
+fn write_source_round_trip() {
+    let input = concat!(
+        r#"a {{{{ b {{ f(x, "q\"\n", 1.5, -2, null) }}"#,
+        "{{ if not a and (b or c) }}1{{ elif -(x + 1) * 2 >= y % 3 - z }}2{{ else }}3{{ end }}",
+        "{{ for i in items | f }}{{ i ?? true }}{{ else }}none{{ end }}",
+        "{{ (not a) == b }}{{ a - (b - c) }}",
+        "{{ items[i + 1].name }}{{ (a ?? b)[0] }}{{ -f(x).y[\"k\"] }}",
+        "{{ x | pad(width = a + 1) }}",
+        "{{ extends \"base\" }}{{ block body }}x{{ super() }}{{ end }}",
+        "{{ import \"forms\" }}{{ macro link( url , text = url | f) }}{{ text }}{{ end }}",
+        "{{ set x=a == b }}",
+    );
+    let nodes = parser(input, "{{", "}}").unwrap();
+
+    let mut source = String::new();
+    let syntax = Syntax::new("{{", "}}");
+    for node in &nodes {
+        node.write_source(&mut source, &syntax);
+    }
+    assert_eq!(
+        source,
+        concat!(
+            r#"a {{{{ b {{ f(x, "q\"\n", 1.5, -2, null) }}"#,
+            "{{ if not a and (b or c) }}1{{ elif -(x + 1) * 2 >= y % 3 - z }}2{{ else }}3{{ end }}",
+            "{{ for i in f(items) }}{{ i ?? true }}{{ else }}none{{ end }}",
+            "{{ (not a) == b }}{{ a - (b - c) }}",
+            "{{ items[i + 1].name }}{{ (a ?? b)[0] }}{{ -f(x).y[\"k\"] }}",
+            "{{ pad(x, width=a + 1) }}",
+            "{{ extends \"base\" }}{{ block body }}x{{ super() }}{{ end }}",
+            "{{ import \"forms\" }}{{ macro link(url, text=f(url)) }}{{ text }}{{ end }}",
+            "{{ set x = a == b }}",
+        )
+    );
+
+    // spans aside, the source is parsed into the same nodes
+    let strip_spans = |nodes: Vec<TemplateNode>| format!("{nodes:?}").replace(char::is_numeric, "");
+    assert_eq!(
+        strip_spans(parser(&source, "{{", "}}").unwrap()),
+        strip_spans(nodes)
+    );
+}
+
+#[test]
+fn write_source_escapes() {
+    let input = r"C:\Users\{{ user }} {{{{ b }} {{{{{{{{ c }}{ {{ d }}";
+    let nodes = parser(input, "{{", "}}").unwrap();
+
+    let mut source = String::new();
+    let syntax = Syntax::new("{{", "}}");
+    for node in &nodes {
+        node.write_source(&mut source, &syntax);
+    }
+    assert_eq!(source, input);
+    assert_eq!(parser(&source, "{{", "}}").unwrap(), nodes);
+}
+
+#[test]
+fn write_source_custom_delimiters() {
+    let nodes = parser("<% x %> <%<% y <# z", "<%", "%>").unwrap();
+    let syntax = Syntax {
+        comment: Some(("<#", "#>")),
+        ..Syntax::new("<%", "%>")
+    };
+
+    let mut source = String::new();
+    for node in &nodes {
+        node.write_source(&mut source, &syntax);
+    }
+    // the start of a comment that is not escaped by the tag delimiter is quoted
+    assert_eq!(source, r#"<% x %> <%<% y <% "<#" %> z"#);
+}
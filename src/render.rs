@@ -1,202 +1,2271 @@
-use std::borrow::Cow;
-
-use dashmap::DashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::ops::Range;
+use std::sync::Arc;
 
 use crate::error::Error;
-use crate::parser::TemplateNode;
-use crate::template::Function;
+use crate::parser::{Operator, TemplateNode};
+use crate::template::function::{Error as FunctionError, FunctionContext};
+use crate::template::{Callable, CompiledTemplate, Signature, SrTemplate, UndefinedBehavior};
+use crate::value::Value;
 #[cfg(feature = "debug")]
 use log::debug;
 
-/// Renders a vector of `TemplateNode`s, replacing variables and processing functions.
+mod macros;
+mod operators;
+mod partial;
+
+use macros::Macros;
+
+pub use partial::partial_render;
+
+/// The function that returns its first argument that exists and is not `null`, or its last
+/// argument, like `a ?? b`. It is evaluated by the renderer, so its arguments can be missing
+/// variables, unless a function with the same name is added to the template.
+const DEFAULT_FUNCTION: &str = "default";
+
+/// The function that renders the block replaced by the block being rendered, in a template
+/// that extends another one. It is evaluated by the renderer inside of blocks, unless a
+/// function with the same name is added to the template.
+const SUPER_FUNCTION: &str = "super";
+
+/// The variable with the state of the iteration inside of a loop, a map with its `index`,
+/// `index0`, `first`, `last` and `length`.
+const LOOP_VARIABLE: &str = "loop";
+
+/// The bodies of a block, from the template that is rendered to the extended template
+/// that defines it first.
+type BlockBodies = Vec<Arc<[TemplateNode<'static>]>>;
+
+/// Variables and functions available while rendering.
+///
+/// The variables defined by the template itself, like the item of a loop or the ones of a
+/// `set` tag, are stacked on top of the ones added to the [`SrTemplate`], so they shadow them
+/// without modifying them. Each block has its own frame of variables, which is dropped after
+/// the block.
+pub struct Scope<'r, 'a> {
+    template: &'r SrTemplate<'a>,
+    name: Option<&'r str>,
+    locals: Vec<HashMap<String, Value>>,
+    /// The templates being included, the last one is the one being rendered.
+    includes: Vec<CompiledTemplate>,
+    /// The bodies of the blocks of the template being rendered and the templates it extends.
+    blocks: HashMap<String, BlockBodies>,
+    /// The blocks being rendered and the index of their body being rendered.
+    block_stack: Vec<(String, usize)>,
+    macros: Macros,
+}
+
+impl<'r, 'a> Scope<'r, 'a> {
+    pub fn new(template: &'r SrTemplate<'a>) -> Self {
+        Self {
+            template,
+            name: None,
+            locals: Vec::new(),
+            includes: Vec::new(),
+            blocks: HashMap::new(),
+            block_stack: Vec::new(),
+            macros: Macros::default(),
+        }
+    }
+
+    /// Sets the name of the template being rendered.
+    pub fn with_name(mut self, name: Option<&'r str>) -> Self {
+        self.name = name;
+        self
+    }
+
+    pub fn template(&self) -> &'r SrTemplate<'a> {
+        self.template
+    }
+
+    /// Returns the name of the template being rendered, which is the last included one.
+    pub fn name(&self) -> Option<&str> {
+        self.includes
+            .last()
+            .map_or(self.name, CompiledTemplate::name)
+    }
+
+    /// Takes the registered template `name` to render it in place of an `include` tag, it
+    /// must be left with [`Scope::exit_include`] after rendering it.
+    fn enter_include(&mut self, name: &str) -> Result<CompiledTemplate, Error> {
+        let chain = self
+            .name
+            .into_iter()
+            .chain(self.includes.iter().filter_map(CompiledTemplate::name));
+        if chain.clone().any(|included| included == name) {
+            return Err(Error::IncludeCycle(
+                chain.chain([name]).map(str::to_owned).collect(),
+            ));
+        }
+
+        let template = self.template.find_template(name)?;
+        self.includes.push(template.clone());
+        Ok(template)
+    }
+
+    fn exit_include(&mut self) {
+        self.includes.pop();
+    }
+
+    fn push_variable(&self, res: &mut String, name: &str) -> Result<(), Error> {
+        if let Some(value) = self.local(name) {
+            write!(res, "{value}").expect("writing to a String never fails");
+            return Ok(());
+        }
+
+        if let Some(value) = self.template.variables.get(name) {
+            write!(res, "{}", *value).expect("writing to a String never fails");
+            return Ok(());
+        }
+
+        write!(res, "{}", self.variable(name)?).expect("writing to a String never fails");
+        Ok(())
+    }
+
+    /// Returns the value of a variable, or the value given by the [`UndefinedBehavior`] of
+    /// the template if it does not exist.
+    pub fn variable(&self, name: &str) -> Result<Value, Error> {
+        self.resolve(name)?.map_or_else(|| self.undefined(name), Ok)
+    }
+
+    /// Returns the value of a variable whose name can be a path of keys, like `user.address.city`.
+    ///
+    /// A variable with the whole name is used if it exists, otherwise the keys are taken
+    /// from the variable with the longest name that is a prefix of the path. It returns
+    /// `None` if no variable matches and an error if a key of the path is missing.
+    pub fn resolve(&self, name: &str) -> Result<Option<Value>, Error> {
+        if let Some(value) = self.lookup(name) {
+            return Ok(Some(value));
+        }
+
+        let mut end = name.len();
+        while let Some(dot) = name[..end].rfind('.') {
+            if let Some(value) = self.lookup(&name[..dot]) {
+                let mut path_end = dot;
+                return name[dot + 1..]
+                    .split('.')
+                    .try_fold(value, |value, key| {
+                        path_end += key.len() + 1;
+                        access(&value, &Value::from(key), || name[..path_end].to_owned())
+                    })
+                    .map(Some);
+            }
+            end = dot;
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the value of a variable if it exists.
+    pub fn lookup(&self, name: &str) -> Option<Value> {
+        if let Some(value) = self.local(name) {
+            return Some(value.clone());
+        }
+
+        self.template.variables.get(name).map(|value| value.clone())
+    }
+
+    fn undefined(&self, name: &str) -> Result<Value, Error> {
+        match &self.template.undefined {
+            UndefinedBehavior::Empty => Ok(Value::Null),
+            UndefinedBehavior::Hook(hook) => {
+                hook(name).ok_or_else(|| Error::VariableNotFound(name.to_owned()))
+            }
+            UndefinedBehavior::Strict | UndefinedBehavior::Keep => {
+                Err(Error::VariableNotFound(name.to_owned()))
+            }
+        }
+    }
+
+    /// Writes the `source` of a tag that failed with `error` if the error is a variable that
+    /// is not found and the template keeps them, otherwise returns the error.
+    fn keep_undefined(&self, res: &mut String, source: &str, error: Error) -> Result<(), Error> {
+        match (error, &self.template.undefined) {
+            (Error::VariableNotFound(_), UndefinedBehavior::Keep) => {
+                res.push_str(source);
+                Ok(())
+            }
+            (error, _) => Err(error),
+        }
+    }
+
+    fn local(&self, name: &str) -> Option<&Value> {
+        self.locals.iter().rev().find_map(|frame| frame.get(name))
+    }
+
+    /// Defines a variable in the frame of the block being rendered.
+    fn set_local(&mut self, name: &str, value: Value) {
+        if self.locals.is_empty() {
+            self.locals.push(HashMap::new());
+        }
+        self.locals
+            .last_mut()
+            .expect("frame of locals")
+            .insert(name.to_owned(), value);
+    }
+
+    /// Renders the body of a block in a new frame of locals.
+    fn render_body(&mut self, res: &mut String, body: &[TemplateNode]) -> Result<(), Error> {
+        self.locals.push(HashMap::new());
+        let rendered = body.iter().try_for_each(|tnode| nodes(res, tnode, self));
+        self.locals.pop();
+        rendered
+    }
+
+    fn list(&mut self, iterable: &TemplateNode) -> Result<Vec<Value>, Error> {
+        let value = node(iterable, self)?;
+        iterable_values(value, iterable)
+    }
+
+    /// Sets the item of a loop and the `loop` map with the state of the iteration in the
+    /// last frame of locals, the variables set by the previous iteration are dropped.
+    fn set_loop_variables(&mut self, item: &str, value: Value, index: usize, length: usize) {
+        let state = BTreeMap::from([
+            ("index", Value::from(index + 1)),
+            ("index0", Value::from(index)),
+            ("first", Value::from(index == 0)),
+            ("last", Value::from(index + 1 == length)),
+            ("length", Value::from(length)),
+        ]);
+
+        let frame = self.locals.last_mut().expect("loop frame");
+        frame.clear();
+        frame.insert(item.to_owned(), value);
+        frame.insert(LOOP_VARIABLE.to_owned(), Value::from(state));
+    }
+
+    /// Moves the named arguments of a call to the positions given by the [`Signature`] of
+    /// the function and fills the ones that are not given, the functions without a
+    /// signature only take positional arguments.
+    pub fn bind_arguments(
+        &self,
+        function: &str,
+        positional: Vec<Value>,
+        named: Vec<(String, Value)>,
+    ) -> Result<Vec<Value>, FunctionError> {
+        let signature: Option<Arc<Signature>> = self
+            .template
+            .signatures
+            .get(function)
+            .map(|signature| Arc::clone(&signature));
+        match signature {
+            Some(signature) => signature.bind(function, positional, named),
+            None if named.is_empty() => Ok(positional),
+            None => Err(FunctionError::InvalidArgument(format!(
+                "{function}() does not take named arguments"
+            ))),
+        }
+    }
+
+    /// Checks if `function` is the [`DEFAULT_FUNCTION`] evaluated by the renderer.
+    fn is_default_function(&self, function: &str) -> bool {
+        function == DEFAULT_FUNCTION && !self.template.functions.contains_key(DEFAULT_FUNCTION)
+    }
+
+    /// Checks if `function` is registered as a function that receives the text of its arguments.
+    fn is_text_function(&self, function: &str) -> bool {
+        matches!(
+            self.template.functions.get(function).as_deref(),
+            Some(Callable::Text(_))
+        )
+    }
+
+    /// Checks if `function` is the [`SUPER_FUNCTION`] of the block being rendered.
+    fn is_super_function(&self, function: &str) -> bool {
+        function == SUPER_FUNCTION
+            && !self.block_stack.is_empty()
+            && !self.template.functions.contains_key(SUPER_FUNCTION)
+    }
+
+    /// Renders the body at `index` of the bodies of the block `name`, or `body` if no
+    /// template replaces the block.
+    fn render_block(
+        &mut self,
+        res: &mut String,
+        name: &str,
+        index: usize,
+        body: &[TemplateNode],
+    ) -> Result<(), Error> {
+        let replaced = self
+            .blocks
+            .get(name)
+            .and_then(|bodies| bodies.get(index))
+            .cloned();
+
+        self.block_stack.push((name.to_owned(), index));
+        let rendered = match &replaced {
+            Some(replaced) => self.render_body(res, replaced),
+            None => self.render_body(res, body),
+        };
+        self.block_stack.pop();
+        rendered
+    }
+
+    /// Renders the body of the block being rendered that comes from the template extended
+    /// by the one its current body comes from.
+    fn render_super(&mut self) -> Result<Value, Error> {
+        let (name, index) = self
+            .block_stack
+            .last()
+            .cloned()
+            .expect("block being rendered");
+        let parent = index + 1;
+        if self.blocks.get(&name).map_or(0, Vec::len) <= parent {
+            return Err(Error::NoParentBlock(name));
+        }
+
+        let mut res = String::new();
+        self.render_block(&mut res, &name, parent, &[])?;
+        Ok(Value::String(res))
+    }
+}
+
+/// Renders the nodes of a whole template.
+///
+/// A template with an `extends` tag is rendered as the template it extends, with the blocks
+/// of both templates, so the blocks of the extended template are replaced by the ones with
+/// the same name. The extended template can extend another one, and only the blocks of the
+/// templates that extend another one are rendered.
+///
+/// The macros defined at the top level of the template, and the ones of the templates it
+/// imports or extends, can be called by the template.
+pub fn render_template(
+    res: &mut String,
+    tnodes: &[TemplateNode],
+    scope: &mut Scope,
+) -> Result<(), Error> {
+    scope.enter_module(tnodes)?;
+    let rendered = render_extended(res, tnodes, scope);
+    scope.exit_module();
+    rendered
+}
+
+/// Renders a template as the template it extends, if any.
+fn render_extended(
+    res: &mut String,
+    tnodes: &[TemplateNode],
+    scope: &mut Scope,
+) -> Result<(), Error> {
+    let Some(extends) = extended(tnodes) else {
+        return tnodes.iter().try_for_each(|tnode| nodes(res, tnode, scope));
+    };
+
+    let mut blocks = HashMap::new();
+    collect_blocks(tnodes, &mut blocks);
+    let mut name = node(extends, scope)?.to_string();
+    let mut entered = 0;
+    let base = loop {
+        let parent = match scope.enter_include(&name) {
+            Ok(parent) => parent,
+            Err(e) => break Err(e),
+        };
+        entered += 1;
+        collect_blocks(parent.nodes(), &mut blocks);
+
+        match extended(parent.nodes()).map(|extends| node(extends, scope)) {
+            Some(Ok(extends)) => name = extends.to_string(),
+            Some(Err(e)) => break Err(e),
+            None => break Ok(parent),
+        }
+    };
+
+    let rendered = base.and_then(|base| {
+        let outer = std::mem::replace(&mut scope.blocks, blocks);
+        let rendered = base
+            .nodes()
+            .iter()
+            .try_for_each(|tnode| nodes(res, tnode, scope));
+        scope.blocks = outer;
+        rendered
+    });
+    for _ in 0..entered {
+        scope.exit_include();
+    }
+    rendered
+}
+
+/// Returns the name of the template extended by a template, the `extends` tags inside of
+/// blocks are not used.
+fn extended<'n, 't>(tnodes: &'n [TemplateNode<'t>]) -> Option<&'n TemplateNode<'t>> {
+    tnodes.iter().find_map(|tnode| match tnode {
+        TemplateNode::Extends(name) => Some(name.as_ref()),
+        _ => None,
+    })
+}
+
+/// Adds the bodies of the blocks of a template after the ones of the templates that extend
+/// it, a block defined twice by the same template keeps its first body.
+fn collect_blocks(tnodes: &[TemplateNode], blocks: &mut HashMap<String, BlockBodies>) {
+    fn collect<'n>(
+        tnodes: &'n [TemplateNode],
+        found: &mut Vec<&'n str>,
+        blocks: &mut HashMap<String, BlockBodies>,
+    ) {
+        for tnode in tnodes {
+            match tnode {
+                TemplateNode::Block(name, body) => {
+                    if !found.contains(&name.as_ref()) {
+                        found.push(name);
+                        blocks
+                            .entry(name.to_string())
+                            .or_default()
+                            .push(body.iter().cloned().map(TemplateNode::into_owned).collect());
+                    }
+                    collect(body, found, blocks);
+                }
+                TemplateNode::If(branches, otherwise) => {
+                    for (_, body) in branches {
+                        collect(body, found, blocks);
+                    }
+                    collect(otherwise.as_deref().unwrap_or_default(), found, blocks);
+                }
+                TemplateNode::For(_, _, body, otherwise) => {
+                    collect(body, found, blocks);
+                    collect(otherwise.as_deref().unwrap_or_default(), found, blocks);
+                }
+                TemplateNode::Tag(_, tnode) => collect(std::slice::from_ref(tnode), found, blocks),
+                _ => {}
+            }
+        }
+    }
+
+    collect(tnodes, &mut Vec::new(), blocks);
+}
+
+/// Returns the items of the value iterated by a loop, which must be a list.
+fn iterable_values(value: Value, iterable: &TemplateNode) -> Result<Vec<Value>, Error> {
+    match (value, iterable) {
+        (Value::List(values), _) => Ok(values),
+        (_, TemplateNode::Variable(name)) => Err(Error::NotIterable(name.to_string())),
+        (_, TemplateNode::Function(name, ..)) => Err(Error::NotIterable(format!("{name}()"))),
+        (value, _) => Err(Error::NotIterable(value.to_string())),
+    }
+}
+
+/// Renders a `TemplateNode`, replacing variables and processing functions.
 ///
-/// This function processes a list of `TemplateNode`s and returns a `Result` containing the rendered template as a `String` or a [`SrTemplateError`] in case of an error.
+/// This function processes a `TemplateNode` and pushes the rendered text to `res`, or returns a [`SrTemplateError`] in case of an error.
 ///
 /// # Arguments
 ///
-/// * `nodes`: A vector of `TemplateNode`s to be processed.
-/// * `vars`: A reference to a `DashMap` containing variable names as keys and `Cow<'_, str>` as values.
-/// * `funcs`: A reference to a `DashMap` containing function names as keys and `TemplateFunction` closures as values.
+/// * `res`: The buffer where the rendered text is pushed.
+/// * `tnode`: The `TemplateNode` to be processed.
+/// * `scope`: The variables and functions available to the node.
 ///
 /// # Returns
 ///
-/// A `Result` where `Ok` contains the rendered template as a `String`, and `Err` holds a [`SrTemplateError`] if an error occurs.
-pub fn nodes(
-    res: &mut String,
-    tnode: TemplateNode,
-    vars: &DashMap<Cow<'_, str>, String>,
-    funcs: &DashMap<Cow<'_, str>, Box<Function>>,
-) -> Result<(), Error> {
+/// A `Result` where `Ok` means that the node was rendered, and `Err` holds a [`SrTemplateError`] if an error occurs.
+pub fn nodes(res: &mut String, tnode: &TemplateNode, scope: &mut Scope) -> Result<(), Error> {
     match tnode {
         TemplateNode::RawText(text)
         | TemplateNode::String(text)
         | TemplateNode::Float(text)
         | TemplateNode::Number(text) => res.push_str(text),
-        TemplateNode::Variable(variable) => {
-            let variable = vars
-                .get(variable)
-                .ok_or(Error::VariableNotFound(variable.to_owned()))?;
+        TemplateNode::Bool(value) => {
+            write!(res, "{value}").expect("writing to a String never fails")
+        }
+        TemplateNode::Null => {}
+        TemplateNode::Variable(variable) => scope.push_variable(res, variable)?,
+        TemplateNode::Function(..)
+        | TemplateNode::Binary(..)
+        | TemplateNode::Unary(..)
+        | TemplateNode::Index(..)
+        | TemplateNode::Member(..)
+        | TemplateNode::Named(..) => {
+            let value = node(tnode, scope)?;
+            write!(res, "{value}").expect("writing to a String never fails");
+        }
+        TemplateNode::If(branches, otherwise) => {
+            let mut body = otherwise.as_ref();
+            for (condition, branch) in branches {
+                if node(condition, scope)?.is_truthy() {
+                    body = Some(branch);
+                    break;
+                }
+            }
 
-            res.push_str(&variable);
+            if let Some(body) = body {
+                scope.render_body(res, body)?;
+            }
         }
-        TemplateNode::Function(function, arguments) => {
-            let evaluated_arguments: Result<Vec<String>, Error> = arguments
-                .into_iter()
-                .map(|arg| node(arg, vars, funcs))
-                .collect();
+        TemplateNode::For(item, iterable, body, otherwise) => {
+            let values = scope.list(iterable)?;
 
-            let evaluated_arguments = evaluated_arguments?;
-            #[cfg(feature = "debug")]
-            debug!("Evaluated Args: {evaluated_arguments:?}");
+            if values.is_empty() {
+                if let Some(otherwise) = otherwise {
+                    scope.render_body(res, otherwise)?;
+                }
+                return Ok(());
+            }
 
-            let result_of_function = funcs
-                .get(function)
-                .ok_or(Error::FunctionNotImplemented(function.to_owned()))?(
-                &evaluated_arguments
-            )?;
+            let length = values.len();
+            scope.locals.push(HashMap::with_capacity(6));
+            for (index, value) in values.into_iter().enumerate() {
+                scope.set_loop_variables(item, value, index, length);
 
-            #[cfg(feature = "debug")]
-            debug!("Result of function: {result_of_function:?}");
+                if let Err(e) = body.iter().try_for_each(|tnode| nodes(res, tnode, scope)) {
+                    scope.locals.pop();
+                    return Err(e);
+                }
+            }
+            scope.locals.pop();
+        }
+        TemplateNode::Include(name) => {
+            let name = node(name, scope)?.to_string();
 
-            res.push_str(&result_of_function);
+            // the blocks of the including template do not replace the ones of the included one
+            let template = scope.enter_include(&name)?;
+            let blocks = std::mem::take(&mut scope.blocks);
+            let block_stack = std::mem::take(&mut scope.block_stack);
+            scope.locals.push(HashMap::new());
+            let included = render_template(res, template.nodes(), scope);
+            scope.locals.pop();
+            scope.blocks = blocks;
+            scope.block_stack = block_stack;
+            scope.exit_include();
+            included?;
+        }
+        TemplateNode::Block(name, body) => scope.render_block(res, name, 0, body)?,
+        // they are used by `render_template`
+        TemplateNode::Extends(_) | TemplateNode::Macro(..) | TemplateNode::Import(_) => {}
+        TemplateNode::Set(name, value) => {
+            let value = node(value, scope)?;
+            scope.set_local(name, value);
+        }
+        TemplateNode::Tag(source, tnode) => {
+            let len = res.len();
+            if let Err(e) = nodes(res, tnode, scope) {
+                // a kept block replaces what its body wrote before the error
+                res.truncate(len);
+                scope.keep_undefined(res, source, e)?;
+            }
         }
     }
 
     Ok(())
 }
 
-pub fn node(
-    tnode: TemplateNode,
-    vars: &DashMap<Cow<'_, str>, String>,
-    funcs: &DashMap<Cow<'_, str>, Box<Function>>,
-) -> Result<String, Error> {
+/// Evaluates a `TemplateNode` into its value, this is used for the arguments of functions and the conditions of blocks.
+pub fn node(tnode: &TemplateNode, scope: &mut Scope) -> Result<Value, Error> {
     match tnode {
-        TemplateNode::RawText(text)
-        | TemplateNode::String(text)
-        | TemplateNode::Float(text)
-        | TemplateNode::Number(text) => Ok(text.to_owned()),
-        TemplateNode::Variable(variable) => {
-            let variable = vars
-                .get(variable)
-                .ok_or(Error::VariableNotFound(variable.to_owned()))?;
+        TemplateNode::RawText(text) | TemplateNode::String(text) => Ok(Value::from(text.as_ref())),
+        TemplateNode::Number(text) => Ok(integer_literal(text)),
+        TemplateNode::Float(text) => Ok(float_literal(text)),
+        TemplateNode::Bool(value) => Ok(Value::Bool(*value)),
+        TemplateNode::Null => Ok(Value::Null),
+        TemplateNode::Variable(variable) => scope.variable(variable),
+        TemplateNode::Function(function, arguments, _) if scope.is_default_function(function) => {
+            default_arguments(arguments)?;
 
-            Ok(variable.to_owned())
+            for argument in &arguments[..arguments.len() - 1] {
+                if let Some(value) = existing(argument, scope)? {
+                    return Ok(value);
+                }
+            }
+            node(&arguments[arguments.len() - 1], scope)
         }
-        TemplateNode::Function(function, arguments) => {
-            let evaluated_arguments: Result<Vec<String>, Error> = arguments
-                .into_iter()
-                .map(|arg| node(arg, vars, funcs))
-                .collect();
+        TemplateNode::Function(function, arguments, _) if scope.is_super_function(function) => {
+            if !arguments.is_empty() {
+                return Err(FunctionError::InvalidArgument(format!(
+                    "{SUPER_FUNCTION}() does not take arguments"
+                ))
+                .into());
+            }
+            scope.render_super()
+        }
+        TemplateNode::Function(function, arguments, span) => {
+            let definition = scope.find_macro(function);
+            let as_written = definition.is_none() && scope.is_text_function(function);
 
-            let evaluated_arguments = evaluated_arguments?;
-            #[cfg(feature = "debug")]
-            debug!("Evaluated Args: {evaluated_arguments:?}");
+            let mut positional = Vec::with_capacity(arguments.len());
+            let mut named = Vec::new();
+            for argument in arguments {
+                match argument {
+                    TemplateNode::Named(name, value) => {
+                        named.push((name.to_string(), argument_value(value, as_written, scope)?));
+                    }
+                    argument => positional.push(argument_value(argument, as_written, scope)?),
+                }
+            }
 
-            let result_of_function = funcs
-                .get(function)
-                .ok_or(Error::FunctionNotImplemented(function.to_owned()))?(
-                &evaluated_arguments
-            )?;
+            match definition {
+                Some(definition) => scope.call_macro(function, &definition, positional, named),
+                None => call(scope, function, positional, named, span),
+            }
+        }
+        TemplateNode::Binary(Operator::And, lhs, rhs) => {
+            let lhs = node(lhs, scope)?;
+            if lhs.is_truthy() {
+                node(rhs, scope)
+            } else {
+                Ok(lhs)
+            }
+        }
+        TemplateNode::Binary(Operator::Or, lhs, rhs) => {
+            let lhs = node(lhs, scope)?;
+            if lhs.is_truthy() {
+                Ok(lhs)
+            } else {
+                node(rhs, scope)
+            }
+        }
+        TemplateNode::Binary(Operator::Coalesce, lhs, rhs) => match existing(lhs, scope)? {
+            Some(value) => Ok(value),
+            None => node(rhs, scope),
+        },
+        TemplateNode::Binary(operator, lhs, rhs) => {
+            let lhs = node(lhs, scope)?;
+            let rhs = node(rhs, scope)?;
+
+            operators::binary(*operator, &lhs, &rhs)
+        }
+        TemplateNode::Unary(operator, operand) => {
+            let value = node(operand, scope)?;
 
-            #[cfg(feature = "debug")]
-            debug!("Result of function: {result_of_function:?}");
+            operators::unary(*operator, &value)
+        }
+        TemplateNode::Index(value, index) => {
+            let value = node(value, scope)?;
+            let index = node(index, scope)?;
+
+            access(&value, &index, || access_path(tnode))
+        }
+        TemplateNode::Member(value, name) => {
+            access(&node(value, scope)?, &Value::from(name.as_ref()), || {
+                access_path(tnode)
+            })
+        }
+        // only found in the arguments of a call, which takes its name
+        TemplateNode::Named(_, value) => node(value, scope),
+        TemplateNode::If(..)
+        | TemplateNode::For(..)
+        | TemplateNode::Include(..)
+        | TemplateNode::Extends(..)
+        | TemplateNode::Block(..)
+        | TemplateNode::Macro(..)
+        | TemplateNode::Import(..)
+        | TemplateNode::Set(..)
+        | TemplateNode::Tag(..) => {
+            let mut res = String::new();
+            nodes(&mut res, tnode, scope)?;
 
-            Ok(result_of_function)
+            Ok(Value::String(res))
         }
     }
 }
 
+/// Evaluates an argument of a function, the number literals passed to a text function are
+/// kept as they are written, so `trim(007)` receives `007` like it did before the typed values.
+fn argument_value(
+    tnode: &TemplateNode,
+    as_written: bool,
+    scope: &mut Scope,
+) -> Result<Value, Error> {
+    match number_text(tnode) {
+        Some(text) if as_written => Ok(Value::from(text)),
+        _ => node(tnode, scope),
+    }
+}
+
+/// Returns the source text of a number literal, or of the number literal given to a named
+/// argument.
+fn number_text<'t>(tnode: &'t TemplateNode) -> Option<&'t str> {
+    match tnode {
+        TemplateNode::Number(text) | TemplateNode::Float(text) => Some(text),
+        TemplateNode::Named(_, value) => number_text(value),
+        _ => None,
+    }
+}
+
+/// Calls a registered function with its evaluated arguments.
+fn call(
+    scope: &Scope,
+    name: &str,
+    positional: Vec<Value>,
+    named: Vec<(String, Value)>,
+    span: &Range<usize>,
+) -> Result<Value, Error> {
+    // cloned out of the registry, so the function can add or call other functions
+    let function: Callable = scope
+        .template
+        .functions
+        .get(name)
+        .ok_or_else(|| Error::FunctionNotImplemented(name.to_owned()))?
+        .clone();
+    let arguments = scope.bind_arguments(name, positional, named)?;
+
+    #[cfg(feature = "debug")]
+    debug!("Evaluated Args: {arguments:?}");
+
+    let context = FunctionContext::new(scope, span.clone());
+    let result_of_function = function.call(&context, &arguments)?;
+
+    #[cfg(feature = "debug")]
+    debug!("Result of function: {result_of_function:?}");
+
+    Ok(result_of_function)
+}
+
+/// Checks that the [`DEFAULT_FUNCTION`] has a value and a default for it.
+fn default_arguments(arguments: &[TemplateNode]) -> Result<(), Error> {
+    if arguments
+        .iter()
+        .any(|argument| matches!(argument, TemplateNode::Named(..)))
+    {
+        return Err(FunctionError::InvalidArgument(format!(
+            "{DEFAULT_FUNCTION}() does not take named arguments"
+        ))
+        .into());
+    }
+    if arguments.len() < 2 {
+        return Err(FunctionError::ArgumentsIncomplete(arguments.len(), 2).into());
+    }
+    Ok(())
+}
+
+/// Takes the item of a list or a text at an integer index, counting from the end if it is
+/// negative, or the value of a map at a key, `path` is the access written in the template.
+fn access(value: &Value, key: &Value, path: impl FnOnce() -> String) -> Result<Value, Error> {
+    let index = match key {
+        Value::Integer(index) => Some(*index),
+        Value::String(text) => text.parse::<i64>().ok(),
+        _ => None,
+    };
+    let position = |index: i64, length: usize| {
+        let length = i64::try_from(length).ok()?;
+        let index = if index < 0 { index + length } else { index };
+        usize::try_from(index).ok().filter(|_| index < length)
+    };
+
+    match (value, index) {
+        (Value::Map(values), _) if matches!(key, Value::String(_) | Value::Integer(_)) => values
+            .get(&key.to_string())
+            .cloned()
+            .ok_or_else(|| Error::KeyNotFound(path())),
+        (Value::List(values), Some(index)) => position(index, values.len())
+            .map(|index| values[index].clone())
+            .ok_or_else(|| Error::IndexOutOfRange(path())),
+        (Value::String(text), Some(index)) => position(index, text.chars().count())
+            .and_then(|index| text.chars().nth(index))
+            .map(|c| Value::String(c.to_string()))
+            .ok_or_else(|| Error::IndexOutOfRange(path())),
+        _ => Err(Error::NotIndexable(path())),
+    }
+}
+
+/// Writes an access as it is written in the template, for its errors.
+fn access_path(tnode: &TemplateNode) -> String {
+    let mut path = String::new();
+    tnode.write_expression(&mut path);
+    path
+}
+
+/// Evaluates a node that may be replaced by a default value, returning `None` when its value
+/// is `null` or when a variable, key or index used by it does not exist.
+fn existing(tnode: &TemplateNode, scope: &mut Scope) -> Result<Option<Value>, Error> {
+    match node(tnode, scope) {
+        Ok(Value::Null)
+        | Err(Error::VariableNotFound(_) | Error::KeyNotFound(_) | Error::IndexOutOfRange(_)) => {
+            Ok(None)
+        }
+        Ok(value) => Ok(Some(value)),
+        Err(e) => Err(e),
+    }
+}
+
+/// Converts the text of an integer literal, the integers that do not fit in an `i64` are
+/// kept as their decimal text, so they can still be received by the functions that parse them.
+fn integer_literal(text: &str) -> Value {
+    let digits = text.replace('_', "");
+    let (sign, unsigned) = digits
+        .strip_prefix('-')
+        .map_or((1, digits.as_str()), |unsigned| (-1, unsigned));
+    let (radix, unsigned) = match unsigned.get(..2) {
+        Some("0x" | "0X") => (16, &unsigned[2..]),
+        Some("0o" | "0O") => (8, &unsigned[2..]),
+        Some("0b" | "0B") => (2, &unsigned[2..]),
+        _ => (10, unsigned),
+    };
+
+    match i128::from_str_radix(unsigned, radix).map(|value| sign * value) {
+        Ok(value) => i64::try_from(value).map_or_else(|_| Value::from(value), Value::Integer),
+        Err(_) => Value::String(digits),
+    }
+}
+
+/// Converts the text of a float literal.
+fn float_literal(text: &str) -> Value {
+    let digits = text.replace('_', "");
+    digits
+        .parse::<f64>()
+        .map_or(Value::String(digits), Value::Float)
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
+
     use crate::builtin;
     use crate::parser::parser;
 
-    use dashmap::DashMap;
-
     use super::*;
 
-    #[test]
-    fn basic_render() {
-        let vars = DashMap::from_iter([(Cow::Borrowed("var"), "World".to_string())]);
-        let template = "Hello {{ var }}";
+    fn render(template: &str, ctx: &SrTemplate) -> Result<String, Error> {
         let tnodes = parser(template, "{{", "}}").unwrap();
+        let mut scope = Scope::new(ctx);
         let mut res = String::new();
 
-        for tnode in tnodes.into_iter() {
-            let out = nodes(&mut res, tnode, &vars, &DashMap::new());
-            assert!(out.is_ok());
-        }
+        render_template(&mut res, &tnodes, &mut scope)?;
+        Ok(res)
+    }
+
+    #[test]
+    fn basic_render() {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("var", "World");
 
-        assert_eq!(&res, "Hello World");
+        assert_eq!(render("Hello {{ var }}", &ctx).unwrap(), "Hello World");
     }
 
     #[test]
     fn basic_function_render() {
-        let vars = DashMap::from_iter([(Cow::Borrowed("var"), "WoRlD".to_string())]);
-        let funcs = DashMap::from_iter([(
-            Cow::Borrowed("toLowerCase"),
-            Box::new(builtin::text::to_lower as Function),
-        )]);
-        let template = "Hello {{ toLowerCase(var) }}";
-        let tnodes = parser(template, "{{", "}}").unwrap();
-        let mut res = String::new();
+        let ctx = SrTemplate::default();
+        ctx.add_variable("var", "WoRlD");
+        ctx.add_function("toLowerCase", builtin::text::to_lower);
+
+        assert_eq!(
+            render("Hello {{ toLowerCase(var) }}", &ctx).unwrap(),
+            "Hello world"
+        );
+    }
+
+    #[test]
+    fn recursive_function_render() {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("var", "WoRlD");
+        ctx.add_function("toLowerCase", builtin::text::to_lower);
+        ctx.add_function("trim", builtin::text::trim);
+
+        assert_eq!(
+            render("Hello {{ toLowerCase(trim(var)) }}", &ctx).unwrap(),
+            "Hello world"
+        );
+    }
+
+    #[test]
+    fn raw_string_render() {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("var", "    WoRlD");
+        ctx.add_function("toLowerCase", builtin::text::to_lower);
+        ctx.add_function("trim", builtin::text::trim);
+
+        let template = r#"Hello
+{{ toLowerCase(trim(var, "  !   ")) }}"#;
+
+        assert_eq!(render(template, &ctx).unwrap(), "Hello\nworld !");
+    }
+
+    #[test]
+    fn escaped_delimiter_render() {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("user", "Sergio");
+
+        assert_eq!(
+            render("{{{{ user }} is {{ user }}, {{{{{{{{ x", &ctx).unwrap(),
+            "{{ user }} is Sergio, {{{{ x"
+        );
+        // a backslash is only text, like before the escapes existed
+        assert_eq!(
+            render(r"C:\Users\{{ user }} \{{{{ user }}", &ctx).unwrap(),
+            r"C:\Users\Sergio \{{ user }}"
+        );
+    }
+
+    #[test]
+    fn conditional_render() {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("admin", false);
+        ctx.add_variable("count", 3);
+
+        let template =
+            "{{ if admin }}admin{{ elif count }}{{ count }} items{{ else }}empty{{ end }}";
+
+        assert_eq!(render(template, &ctx).unwrap(), "3 items");
+    }
+
+    #[test]
+    fn conditional_else_render() {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("name", "");
+
+        let template = "Hello {{ if name }}{{ name }}{{ else }}Anonymous{{ end }}!";
+
+        assert_eq!(render(template, &ctx).unwrap(), "Hello Anonymous!");
+    }
+
+    #[test]
+    fn loop_render() {
+        let ctx = SrTemplate::default();
+        ctx.add_list("items", ["a", "b", "c"]);
+
+        let template = "{{ for item in items }}{{ loop.index }}:{{ toUpper(item) }}{{ if loop.last }}.{{ else }}, {{ end }}{{ end }}";
+
+        assert_eq!(render(template, &ctx).unwrap(), "1:A, 2:B, 3:C.");
+    }
+
+    #[test]
+    fn loop_metadata_map() {
+        let ctx = SrTemplate::default();
+        ctx.add_list("items", ["a", "b"]);
+        ctx.add_value_function("last", |args: &[Value]| {
+            let state = args[0].as_map().expect("loop map");
+            Ok(state["last"].clone())
+        });
+
+        let template = "{{ for item in items }}{{ loop[\"index0\"] }}/{{ loop.length }}{{ last(loop) }};{{ end }}";
+        assert_eq!(render(template, &ctx).unwrap(), "0/2false;1/2true;");
+
+        let template = "{{ for item in items }}{{ loop }}{{ end }}";
+        assert_eq!(
+            render(template, &ctx).unwrap(),
+            concat!(
+                "{first: true, index: 1, index0: 0, last: false, length: 2}",
+                "{first: false, index: 2, index0: 1, last: true, length: 2}",
+            )
+        );
+    }
 
-        for tnode in tnodes.into_iter() {
-            let out = nodes(&mut res, tnode, &vars, &funcs);
-            assert!(out.is_ok());
+    #[test]
+    fn loop_else_render() {
+        let ctx = SrTemplate::default();
+        ctx.add_list("items", Vec::<String>::new());
+
+        let template = "{{ for item in items }}{{ item }}{{ else }}No items{{ end }}";
+
+        assert_eq!(render(template, &ctx).unwrap(), "No items");
+    }
+
+    #[test]
+    fn nested_loop_render() {
+        let ctx = SrTemplate::default();
+        ctx.add_list("rows", [1, 2]);
+        ctx.add_list("cols", ["x", "y"]);
+
+        let template = "{{ for row in rows }}{{ for col in cols }}{{ row }}{{ col }}{{ if loop.first }}-{{ end }}{{ end }};{{ end }}";
+
+        assert_eq!(render(template, &ctx).unwrap(), "1x-1y;2x-2y;");
+    }
+
+    #[test]
+    fn loop_variable_does_not_leak() {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("item", "global");
+        ctx.add_list("items", ["local"]);
+
+        let template = "{{ for item in items }}{{ item }}{{ end }} {{ item }}";
+
+        assert_eq!(render(template, &ctx).unwrap(), "local global");
+    }
+
+    #[test]
+    fn typed_values_render() {
+        fn describe(args: &[Value]) -> crate::template::function::ValueResult {
+            Ok(args
+                .iter()
+                .map(|arg| match arg {
+                    Value::Null => "null",
+                    Value::Bool(_) => "bool",
+                    Value::Integer(_) => "integer",
+                    Value::Float(_) => "float",
+                    Value::String(_) => "string",
+                    Value::List(_) => "list",
+                    Value::Map(_) => "map",
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+                .into())
         }
 
-        assert_eq!(&res, "Hello world");
+        let ctx = SrTemplate::default();
+        ctx.add_value("flag", true);
+        ctx.add_value("nothing", None::<i32>);
+        ctx.add_value("user", [("name", "Sergio")].into_iter().collect::<Value>());
+        ctx.add_list("items", [1, 2]);
+        ctx.add_value_function("describe", describe);
+
+        assert_eq!(
+            render(
+                "{{ describe(flag, nothing, 1, 1.5, \"a\", items, user) }}",
+                &ctx
+            )
+            .unwrap(),
+            "bool null integer float string list map"
+        );
+        assert_eq!(
+            render("{{ nothing }}|{{ items }}|{{ user }}", &ctx).unwrap(),
+            "|[1, 2]|{name: Sergio}"
+        );
     }
 
     #[test]
-    fn recursive_function_render() {
-        let vars = DashMap::from_iter([(Cow::Borrowed("var"), "WoRlD".to_string())]);
-        let funcs = DashMap::from_iter([
-            (
-                Cow::Borrowed("toLowerCase"),
-                Box::new(builtin::text::to_lower as Function),
+    fn text_function_receives_rendered_values() {
+        let ctx = SrTemplate::default();
+        ctx.add_value("price", 9.5);
+        ctx.add_list("items", ["a", "b"]);
+
+        assert_eq!(
+            render("{{ toUpper(items) }} {{ trim(price) }}", &ctx).unwrap(),
+            "[A, B] 9.5"
+        );
+    }
+
+    #[test]
+    fn text_function_receives_literals_as_written() {
+        let ctx = SrTemplate::default();
+
+        assert_eq!(
+            render(
+                "{{ trim(007) }} {{ trim(2.50) }} {{ trim(1.0) }} {{ trim(0x1F) }} {{ 1e3 | trim }}",
+                &ctx
+            )
+            .unwrap(),
+            "007 2.50 1.0 0x1F 1e3"
+        );
+        assert_eq!(render("{{ trim(1.0 + 1) }}", &ctx).unwrap(), "2");
+        assert_eq!(
+            ctx.partial_render("{{ trim(007) }} {{ toUpper(n) }}")
+                .unwrap(),
+            "007 {{ toUpper(n) }}"
+        );
+    }
+
+    #[test]
+    fn loop_over_function_result() {
+        fn range(args: &[Value]) -> crate::template::function::ValueResult {
+            let end = args.first().and_then(Value::as_integer).unwrap_or_default();
+            Ok((1..=end).collect())
+        }
+
+        let ctx = SrTemplate::default();
+        ctx.add_value_function("range", range);
+
+        assert_eq!(
+            render("{{ for i in range(3) }}{{ i }}{{ end }}", &ctx).unwrap(),
+            "123"
+        );
+    }
+
+    #[test]
+    fn closure_function_render() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let ctx = SrTemplate::default();
+        let locale = String::from("es");
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        ctx.add_function("greet", move |args: &[String]| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            match locale.as_str() {
+                "es" => Ok(format!("Hola {}", args.join(" "))),
+                _ => Ok(format!("Hello {}", args.join(" "))),
+            }
+        });
+        ctx.add_value_function("double", |args: &[Value]| {
+            Ok(Value::from(args[0].as_integer().unwrap_or_default() * 2))
+        });
+
+        assert_eq!(
+            render("{{ greet(\"Sergio\") }}, {{ double(21) }}", &ctx).unwrap(),
+            "Hola Sergio, 42"
+        );
+
+        // clones of the template share the same function and its state
+        let other = ctx.clone();
+        render("{{ greet(\"Mundo\") }}", &other).unwrap();
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn function_replaced_while_rendering() {
+        let ctx = Arc::new(SrTemplate::default());
+        let inner = Arc::clone(&ctx);
+        ctx.add_function("swap", move |_: &[String]| {
+            inner.add_function("swap", |_: &[String]| Ok("new".to_owned()));
+            Ok("old".to_owned())
+        });
+
+        assert_eq!(
+            render("{{ swap() }} {{ swap() }}", &ctx).unwrap(),
+            "old new"
+        );
+    }
+
+    #[test]
+    fn context_function_render() {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("user.name", "Sergio");
+        ctx.add_list("items", ["a", "b"]);
+        ctx.add_context_function("lookup", |ctx: &FunctionContext, args: &[Value]| {
+            Ok(ctx.variable(&args[0].to_string()).unwrap_or_default())
+        });
+        ctx.add_context_function("callIfExists", |ctx: &FunctionContext, args: &[Value]| {
+            let name = args[0].to_string();
+            ctx.call(&name, &args[1..]).unwrap_or(Ok(Value::Null))
+        });
+
+        assert_eq!(
+            render(
+                "{{ lookup(\"user.name\") }} {{ for item in items }}{{ lookup(\"item\") }}{{ end }}",
+                &ctx
+            )
+            .unwrap(),
+            "Sergio ab"
+        );
+        assert_eq!(
+            render(
+                "{{ callIfExists(\"toUpper\", \"x\") }}{{ callIfExists(\"missing\") }}",
+                &ctx
+            )
+            .unwrap(),
+            "X"
+        );
+        assert_eq!(
+            render("{{ callIfExists(\"lookup\", \"missing\") }}", &ctx).unwrap(),
+            ""
+        );
+    }
+
+    #[test]
+    fn pipe_render() {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("name", "  Sergio ");
+        ctx.add_list("items", ["a", "b"]);
+
+        assert_eq!(
+            render("Hello {{ name | trim | toUpper }}!", &ctx).unwrap(),
+            "Hello SERGIO!"
+        );
+        assert_eq!(
+            render("{{ for item in items }}{{ item | toUpper }}{{ end }}", &ctx).unwrap(),
+            "AB"
+        );
+
+        let ctx = SrTemplate::with_delimiter("{|", "|}");
+        ctx.add_variable("name", "  Sergio ");
+        assert_eq!(
+            ctx.render("{| name |}|{| name | trim | toUpper |}")
+                .unwrap(),
+            "  Sergio |SERGIO"
+        );
+    }
+
+    #[test]
+    fn method_render() {
+        let ctx = SrTemplate::default();
+        ctx.add_value("user", BTreeMap::from([("name", "  Sergio ")]));
+        ctx.add_variable("title", " srtemplate ");
+        ctx.add_list("items", ["a", "b"]);
+
+        assert_eq!(
+            render("Hello {{ user.name.trim().toUpper() }}!", &ctx).unwrap(),
+            "Hello SERGIO!"
+        );
+        assert_eq!(
+            render(
+                "{{ title.trim().toUpper() == (title | trim | toUpper) }}",
+                &ctx
+            )
+            .unwrap(),
+            "true"
+        );
+        assert_eq!(
+            render(
+                r#"{{ for item in items }}{{ item.toUpper() }}{{ end }} {{ user.role.default("guest") }}"#,
+                &ctx
+            )
+            .unwrap(),
+            "AB guest"
+        );
+        assert_eq!(
+            render("{{ user.name.missing() }}", &ctx),
+            Err(Error::FunctionNotImplemented("missing".to_owned()))
+        );
+    }
+
+    #[test]
+    fn named_arguments_render() {
+        fn join(args: &[Value]) -> crate::template::function::ValueResult {
+            Ok(Value::String(
+                args.iter()
+                    .map(|arg| {
+                        if arg.is_null() {
+                            "-".to_owned()
+                        } else {
+                            arg.to_string()
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ))
+        }
+
+        let ctx = SrTemplate::default();
+        ctx.add_value_function("join", join);
+        ctx.add_signature(
+            "join",
+            Signature::new()
+                .required("a")
+                .optional("b")
+                .with_default("c", 3)
+                .optional("d"),
+        );
+
+        assert_eq!(render("{{ join(1) }}", &ctx).unwrap(), "1,-,3");
+        assert_eq!(render("{{ join(1, d=4) }}", &ctx).unwrap(), "1,-,3,4");
+        assert_eq!(render("{{ join(c=5, a=1, b=2) }}", &ctx).unwrap(), "1,2,5");
+        assert_eq!(render("{{ 1 | join(c = 1 + 1) }}", &ctx).unwrap(), "1,-,2");
+        assert_eq!(
+            render("{{ join(b=2) }}", &ctx),
+            Err(Error::Function(FunctionError::InvalidArgument(
+                "join() is missing the argument \"a\"".to_owned()
+            )))
+        );
+        assert_eq!(
+            render("{{ toUpper(\"a\", case=1) }}", &ctx),
+            Err(Error::Function(FunctionError::InvalidArgument(
+                "toUpper() does not take named arguments".to_owned()
+            )))
+        );
+        assert_eq!(
+            render("{{ default(missing, value=1) }}", &ctx),
+            Err(Error::Function(FunctionError::InvalidArgument(
+                "default() does not take named arguments".to_owned()
+            )))
+        );
+
+        // the signature is kept when the function is replaced, and removed with it
+        ctx.add_function("join", |args: &[String]| Ok(args.join("+")));
+        assert_eq!(render("{{ join(1, c=2) }}", &ctx).unwrap(), "1++2");
+        ctx.remove_function("join");
+        ctx.add_function("join", |args: &[String]| Ok(args.join("+")));
+        assert!(render("{{ join(1, c=2) }}", &ctx).is_err());
+    }
+
+    #[test]
+    fn literal_values_render() {
+        let ctx = SrTemplate::default();
+        ctx.add_value_function("debug", |args: &[Value]| Ok(format!("{args:?}").into()));
+
+        assert_eq!(
+            render(
+                "{{ debug(-5, 0xff, 1_000, 1e-3, -2.5, true, false, null) }}",
+                &ctx
+            )
+            .unwrap(),
+            "[Integer(-5), Integer(255), Integer(1000), Float(0.001), Float(-2.5), Bool(true), Bool(false), Null]"
+        );
+        assert_eq!(
+            render(
+                "{{ debug(340_282_366_920_938_463_463_374_607_431_768_211_455) }}",
+                &ctx
+            )
+            .unwrap(),
+            format!("[String({:?})]", u128::MAX.to_string())
+        );
+        assert_eq!(
+            render("{{ if true }}{{ true }}|{{ null }}|{{ end }}", &ctx).unwrap(),
+            "true||"
+        );
+    }
+
+    #[test]
+    fn number_literals_render() {
+        let ctx = SrTemplate::default();
+
+        assert_eq!(
+            render(
+                "{{ 0xff }} {{ -0X10 }} {{ 0o17 }} {{ 0b1010 }} {{ 1_000 }} {{ 1e-3 }} {{ 2.5E2 }} {{ 1_0.5 }}",
+                &ctx
+            )
+            .unwrap(),
+            "0xff -0X10 0o17 0b1010 1_000 1e-3 2.5E2 1_0.5"
+        );
+        assert_eq!(
+            render("{{ 00000.0 }}|{{ 007 }}|{{ 2.50 }}|{{ 1.0 }}", &ctx).unwrap(),
+            "00000.0|007|2.50|1.0"
+        );
+        assert_eq!(
+            render("{{ 0xff + 0 }}|{{ 1e-3 * 1 }}|{{ 1.0 * 1 }}", &ctx).unwrap(),
+            "255|0.001|1"
+        );
+        assert_eq!(
+            ctx.partial_render("{{ 0b11 }} {{ 1_000 + n }}").unwrap(),
+            "0b11 {{ 1000 + n }}"
+        );
+        assert!(matches!(
+            ctx.render("{{ 1e400 }}"),
+            Err(Error::BadSyntax(error)) if error.kind == crate::parser::SyntaxErrorKind::InvalidNumber
+        ));
+    }
+
+    #[test]
+    fn loop_not_iterable() {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("name", "Sergio");
+
+        assert_eq!(
+            render("{{ for c in name }}{{ c }}{{ end }}", &ctx),
+            Err(Error::NotIterable("name".to_owned()))
+        );
+        assert_eq!(
+            render("{{ for c in missing }}{{ c }}{{ end }}", &ctx),
+            Err(Error::VariableNotFound("missing".to_owned()))
+        );
+    }
+
+    #[test]
+    fn arithmetic_render() {
+        let ctx = SrTemplate::default();
+        ctx.add_value("price", 2.5);
+        ctx.add_value("qty", 4);
+        ctx.add_variable("count", 3);
+        ctx.add_value("tags", vec!["a"]);
+
+        assert_eq!(render("{{ price * qty }}", &ctx).unwrap(), "10");
+        assert_eq!(
+            render("{{ count + 1 }}|{{ count * 2 - 1 }}", &ctx).unwrap(),
+            "4|5"
+        );
+        assert_eq!(
+            render("{{ (count + 1) * 2 }}|{{ -count }}", &ctx).unwrap(),
+            "8|-3"
+        );
+        assert_eq!(
+            render("{{ 7 / 2 }}|{{ 8 / 2 }}|{{ 7 % 4 }}", &ctx).unwrap(),
+            "3.5|4|3"
+        );
+        assert_eq!(
+            render("{{ 1 + 0.5 }}|{{ 7.5 % 2 }}", &ctx).unwrap(),
+            "1.5|1.5"
+        );
+        assert_eq!(render(r#"{{ "n" + count }}"#, &ctx).unwrap(), "n3");
+        assert_eq!(
+            render(r#"{{ "1" + "1" }}|{{ "1" + "a" }}"#, &ctx).unwrap(),
+            "11|1a"
+        );
+        assert!(render(r#"{{ " 2 " * "1.5" }}"#, &ctx).is_err());
+        assert_eq!(render("{{ tags + tags }}", &ctx).unwrap(), "[a, a]");
+    }
+
+    #[test]
+    fn text_variables_in_operators() {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("count", 3);
+        ctx.add_variable("price", 1.5);
+        ctx.add_variable("zip", "01234");
+        ctx.add_value("typed", "7");
+
+        // only the texts written exactly as a number are numbers
+        assert_eq!(
+            render(r#"{{ count + 1 }}|{{ price * 2 }}|{{ zip + 1 }}"#, &ctx).unwrap(),
+            "4|3|012341"
+        );
+        assert_eq!(
+            render(r#"{{ typed + 1 }}|{{ count == "3" }}|{{ zip }}"#, &ctx).unwrap(),
+            "71|true|01234"
+        );
+        assert_eq!(
+            render(
+                "{{ trim(count) * 2 }}|{{ set count = \"4\" }}{{ count + 1 }}",
+                &ctx
+            )
+            .unwrap(),
+            "6|41"
+        );
+
+        assert_eq!(
+            ctx.partial_render(r#"{{ count + n }} {{ zip + "5" }} {{ typed + n }}"#)
+                .unwrap(),
+            r#"{{ 3 + n }} 012345 {{ "7" + n }}"#
+        );
+    }
+
+    #[test]
+    fn text_variables_keep_their_numbers() {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("count", 3);
+        ctx.add_value_function("list", |args: &[Value]| Ok(Value::List(args.to_vec())));
+
+        assert_eq!(render("{{ set x = count }}{{ x + 1 }}", &ctx).unwrap(), "4");
+        assert_eq!(
+            render(
+                "{{ for n in list(count, trim(count)) }}{{ n + 1 }},{{ end }}",
+                &ctx
+            )
+            .unwrap(),
+            "4,4,"
+        );
+        assert_eq!(
+            render(
+                "{{ macro next(n) }}{{ n + 1 }}{{ end }}{{ next(count) }}",
+                &ctx
+            )
+            .unwrap(),
+            "4"
+        );
+    }
+
+    #[test]
+    fn invalid_operations() {
+        let ctx = SrTemplate::default();
+        ctx.add_value("tags", vec!["a"]);
+
+        assert_eq!(
+            render("{{ 1 / 0 }}", &ctx),
+            Err(Error::InvalidOperation("1 / 0 divides by zero".to_owned()))
+        );
+        assert_eq!(
+            render("{{ 1.0 / 0 }}", &ctx),
+            Err(Error::InvalidOperation(
+                "1.0 / 0 divides by zero".to_owned()
+            ))
+        );
+        assert_eq!(
+            render("{{ 1e308 * 10 }}", &ctx),
+            Err(Error::InvalidOperation("1e308 * 10 overflows".to_owned()))
+        );
+        assert_eq!(
+            render("{{ tags * 2 }}", &ctx),
+            Err(Error::InvalidOperation(
+                "cannot apply \"*\" to list and integer".to_owned()
+            ))
+        );
+        assert_eq!(
+            render("{{ 9223372036854775807 + 1 }}", &ctx),
+            Err(Error::InvalidOperation(
+                "9223372036854775807 + 1 overflows".to_owned()
+            ))
+        );
+        assert_eq!(
+            render(r#"{{ "a" < 1 }}"#, &ctx),
+            Err(Error::InvalidOperation(
+                "cannot apply \"<\" to text and integer".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn comparison_render() {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("count", 3);
+        ctx.add_variable("name", "Sergio");
+        ctx.add_variable("enabled", true);
+
+        assert_eq!(
+            render("{{ if count > 0 }}some{{ else }}none{{ end }}", &ctx).unwrap(),
+            "some"
+        );
+        assert_eq!(
+            render("{{ count == 3.0 }}|{{ count != 3 }}|{{ count <= 2 }}", &ctx).unwrap(),
+            "true|false|false"
+        );
+        assert_eq!(
+            render(
+                r#"{{ name == "Sergio" }}|{{ name < "Z" }}|{{ enabled == true }}"#,
+                &ctx
+            )
+            .unwrap(),
+            "true|true|true"
+        );
+        assert_eq!(
+            render("{{ null == 0 }}|{{ 10 > 9 }}", &ctx).unwrap(),
+            "false|true"
+        );
+    }
+
+    #[test]
+    fn logical_render() {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("count", 3);
+        ctx.add_variable("name", "");
+
+        assert_eq!(
+            render("{{ count > 1 and count < 5 }}|{{ not count }}", &ctx).unwrap(),
+            "true|false"
+        );
+        assert_eq!(
+            render(r#"{{ name or "anonymous" }}"#, &ctx).unwrap(),
+            "anonymous"
+        );
+        // the right operand is not evaluated when the left one decides the result
+        assert_eq!(
+            render("{{ false and missing }}|{{ 1 or missing }}", &ctx).unwrap(),
+            "false|1"
+        );
+        assert_eq!(
+            render("{{ true and missing }}", &ctx),
+            Err(Error::VariableNotFound("missing".to_owned()))
+        );
+    }
+
+    #[test]
+    fn coalesce_render() {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("name", "Sergio");
+        ctx.add_value("nothing", Value::Null);
+
+        assert_eq!(
+            render(r#"{{ name ?? "Anonymous" }}"#, &ctx).unwrap(),
+            "Sergio"
+        );
+        assert_eq!(
+            render(r#"{{ missing ?? "Anonymous" }}"#, &ctx).unwrap(),
+            "Anonymous"
+        );
+        assert_eq!(
+            render(r#"{{ nothing ?? "Anonymous" }}"#, &ctx).unwrap(),
+            "Anonymous"
+        );
+        assert_eq!(
+            render("{{ missing ?? nothing ?? name }}", &ctx).unwrap(),
+            "Sergio"
+        );
+        assert_eq!(
+            render("{{ if missing ?? false }}yes{{ else }}no{{ end }}", &ctx).unwrap(),
+            "no"
+        );
+        assert_eq!(
+            render("{{ missing ?? other }}", &ctx),
+            Err(Error::VariableNotFound("other".to_owned()))
+        );
+        assert_eq!(
+            render("{{ missing }}", &ctx),
+            Err(Error::VariableNotFound("missing".to_owned()))
+        );
+    }
+
+    #[test]
+    fn access_render() {
+        let ctx = SrTemplate::default();
+        let address = BTreeMap::from([("city", Value::from("Lima"))]);
+        ctx.add_value(
+            "user",
+            BTreeMap::from([
+                ("name", Value::from("Sergio")),
+                ("address", Value::from(address)),
+                ("tags", Value::from(["admin", "dev"])),
+            ]),
+        );
+        ctx.add_value("items", [10, 20, 30]);
+        ctx.add_value("users", vec![BTreeMap::from([("name", "Ana")])]);
+        ctx.add_variable("user.id", "7");
+
+        assert_eq!(
+            render(
+                r#"{{ user.name }} {{ user["address"].city }} {{ user.address.city }}"#,
+                &ctx
+            )
+            .unwrap(),
+            "Sergio Lima Lima"
+        );
+        assert_eq!(
+            render(
+                "{{ items[0] }} {{ items[-1] }} {{ items[1 + 1] }} {{ items.1 }}",
+                &ctx
+            )
+            .unwrap(),
+            "10 30 30 20"
+        );
+        assert_eq!(
+            render(
+                "{{ user.tags[0] }} {{ user.name[0] }} {{ users[0].name }}",
+                &ctx
+            )
+            .unwrap(),
+            "admin S Ana"
+        );
+        assert_eq!(
+            render("{{ for u in users }}{{ u.name }}{{ end }}", &ctx).unwrap(),
+            "Ana"
+        );
+        // a variable with a dotted name is used before the key of a map
+        assert_eq!(render("{{ user.id }}", &ctx).unwrap(), "7");
+        assert_eq!(
+            render(
+                r#"{{ user.role ?? "guest" }} {{ default(items[9], 0) }}"#,
+                &ctx
+            )
+            .unwrap(),
+            "guest 0"
+        );
+    }
+
+    #[test]
+    fn access_errors() {
+        let ctx = SrTemplate::default();
+        ctx.add_value("user", BTreeMap::from([("name", "Sergio")]));
+        ctx.add_value("items", [10, 20, 30]);
+        ctx.add_value("count", 3);
+
+        assert_eq!(
+            render("{{ user.address.city }}", &ctx),
+            Err(Error::KeyNotFound("user.address".to_owned()))
+        );
+        assert_eq!(
+            render(r#"{{ user["address"] }}"#, &ctx),
+            Err(Error::KeyNotFound(r#"user["address"]"#.to_owned()))
+        );
+        assert_eq!(
+            render("{{ items[5] }}", &ctx),
+            Err(Error::IndexOutOfRange("items[5]".to_owned()))
+        );
+        assert_eq!(
+            render("{{ items[-4] }}", &ctx),
+            Err(Error::IndexOutOfRange("items[-4]".to_owned()))
+        );
+        assert_eq!(
+            render("{{ count.name }}", &ctx),
+            Err(Error::NotIndexable("count.name".to_owned()))
+        );
+        assert_eq!(
+            render(r#"{{ items["a"] }}"#, &ctx),
+            Err(Error::NotIndexable(r#"items["a"]"#.to_owned()))
+        );
+        assert_eq!(
+            render("{{ missing.name }}", &ctx),
+            Err(Error::VariableNotFound("missing.name".to_owned()))
+        );
+    }
+
+    #[test]
+    fn default_render() {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("name", "Sergio");
+
+        assert_eq!(
+            render(r#"{{ default(name, "Anonymous") }}"#, &ctx).unwrap(),
+            "Sergio"
+        );
+        assert_eq!(
+            render(r#"{{ default(missing, "Anonymous") }}"#, &ctx).unwrap(),
+            "Anonymous"
+        );
+        assert_eq!(
+            render(r#"{{ missing | default("Anonymous") }}"#, &ctx).unwrap(),
+            "Anonymous"
+        );
+        assert_eq!(
+            render("{{ default(missing, null, 3) }}", &ctx).unwrap(),
+            "3"
+        );
+        assert_eq!(
+            render("{{ default(missing) }}", &ctx),
+            Err(Error::Function(FunctionError::ArgumentsIncomplete(1, 2)))
+        );
+        assert_eq!(
+            render("{{ default(missing) }}", &ctx)
+                .unwrap_err()
+                .to_string(),
+            "Error Processing Function: This function require 2 arguments, but found 1"
+        );
+
+        // a function added to the template replaces it
+        ctx.add_function("default", |_: &[String]| Ok("custom".to_string()));
+        assert_eq!(render("{{ default(name, 1) }}", &ctx).unwrap(), "custom");
+        assert_eq!(
+            render("{{ default(missing, 1) }}", &ctx),
+            Err(Error::VariableNotFound("missing".to_owned()))
+        );
+    }
+
+    #[test]
+    fn undefined_behaviors() {
+        let mut ctx = SrTemplate::default();
+        ctx.add_variable("name", "Sergio");
+        let template = "{{ name }}:{{ role }}:{{ toUpper(role) }}:{{ role ?? 1 }}";
+
+        assert_eq!(
+            render(template, &ctx),
+            Err(Error::VariableNotFound("role".to_owned()))
+        );
+
+        ctx.set_undefined_behavior(UndefinedBehavior::Empty);
+        assert_eq!(render(template, &ctx).unwrap(), "Sergio:::1");
+        assert_eq!(
+            render("{{ if role }}a{{ else }}b{{ end }}", &ctx).unwrap(),
+            "b"
+        );
+
+        ctx.set_undefined_behavior(UndefinedBehavior::hook(|name| {
+            (name == "role").then(|| Value::from("admin"))
+        }));
+        assert_eq!(render(template, &ctx).unwrap(), "Sergio:admin:ADMIN:admin");
+        assert_eq!(
+            render("{{ other }}", &ctx),
+            Err(Error::VariableNotFound("other".to_owned()))
+        );
+    }
+
+    #[test]
+    fn undefined_keep() {
+        let mut ctx = SrTemplate::default();
+        ctx.set_undefined_behavior(UndefinedBehavior::Keep);
+        ctx.add_variable("name", "Sergio");
+        ctx.add_list("items", ["a", "b"]);
+
+        let template = concat!(
+            "{{name}} {{ role }} {{ toUpper(role) | trim }} {{ count + 1 }} {{ role ?? name }}\n",
+            "{{ if admin }}{{ name }}{{ end }}|",
+            "{{ for i in items }}{{ i }}{{ suffix }}{{ end }}|",
+            "{{ for i in missing }}{{ i }}{{ else }}{{{{ none{{ end }}",
+        );
+        let kept = render(template, &ctx).unwrap();
+        assert_eq!(
+            kept,
+            concat!(
+                "Sergio {{ role }} {{ toUpper(role) | trim }} {{ count + 1 }} Sergio\n",
+                "{{ if admin }}{{ name }}{{ end }}|",
+                "a{{ suffix }}b{{ suffix }}|",
+                "{{ for i in missing }}{{ i }}{{ else }}{{{{ none{{ end }}",
+            )
+        );
+
+        // a second pass fills in the remaining placeholders
+        let ctx = SrTemplate::default();
+        ctx.add_variable("name", "Sergio");
+        ctx.add_variable("role", " dev ");
+        ctx.add_variable("count", 1);
+        ctx.add_value("admin", true);
+        ctx.add_variable("suffix", "!");
+        ctx.add_list("missing", Vec::<String>::new());
+        assert_eq!(
+            render(&kept, &ctx).unwrap(),
+            "Sergio  dev  DEV 2 Sergio\nSergio|a!b!|{{ none"
+        );
+    }
+
+    #[test]
+    fn undefined_keep_as_written() {
+        let mut ctx = SrTemplate::default();
+        ctx.set_undefined_behavior(UndefinedBehavior::Keep);
+
+        let template = "a  {{-missing   }} b {{  count+1 -}}\n c\n{{-if  flag   -}}\n x\n{{- end}}{{set  y=missing}}";
+        let kept = render(template, &ctx).unwrap();
+        assert_eq!(
+            kept,
+            "a{{-missing   }} b {{  count+1 -}}c{{-if  flag   -}}\n x\n{{- end}}{{set  y=missing}}"
+        );
+
+        let ctx = SrTemplate::default();
+        ctx.add_variable("missing", "M");
+        ctx.add_variable("count", 1);
+        ctx.add_value("flag", true);
+        assert_eq!(render(&kept, &ctx).unwrap(), "aM b 2cx");
+    }
+
+    #[test]
+    fn partial_render_expressions() {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("host", "example.com");
+        ctx.add_value("port", 8080);
+        ctx.add_value("ratio", 2.0);
+        ctx.add_value("tags", vec!["a"]);
+
+        let residual = ctx
+            .partial_render(concat!(
+                "{{ host }}:{{ port + 1 }} {{ user }} {{ toUpper(host) | trim }} ",
+                "{{ toLower(host, user, port) }} {{ port * ratio + extra }} ",
+                "{{ missing(port) }} {{ f(tags, user) }} {{ not admin }} ",
+                "{{ user ?? host }} {{ default(port, user) }} {{ default(user, ratio) }} ",
+                "{{ false and user }} {{ true and user }}",
+            ))
+            .unwrap();
+        assert_eq!(
+            residual,
+            concat!(
+                "example.com:8081 {{ user }} EXAMPLE.COM ",
+                r#"{{ toLower("example.com", user, 8080) }} {{ 16160.0 + extra }} "#,
+                "{{ missing(8080) }} {{ f(tags, user) }} {{ not admin }} ",
+                r#"{{ user ?? "example.com" }} 8080 {{ default(user, 2.0) }} "#,
+                "false {{ user }}",
+            )
+        );
+    }
+
+    #[test]
+    fn partial_render_access() {
+        let ctx = SrTemplate::default();
+        ctx.add_value("user", BTreeMap::from([("name", "Sergio")]));
+
+        let residual = ctx
+            .partial_render(concat!(
+                "{{ user.name }} {{ user[key] }} {{ other.name[0] }} ",
+                "{{ for u in users }}{{ u.name }} {{ user.name }}{{ end }}",
+            ))
+            .unwrap();
+        assert_eq!(
+            residual,
+            r#"Sergio {{ user[key] }} {{ other.name[0] }} {{ for u in users }}{{ u.name }} Sergio{{ end }}"#
+        );
+        assert_eq!(
+            ctx.partial_render("{{ user.role }} {{ later }}"),
+            Err(Error::KeyNotFound("user.role".to_owned()))
+        );
+    }
+
+    #[test]
+    fn partial_render_named_arguments() {
+        let ctx = SrTemplate::default();
+        ctx.add_value("width", 4);
+        ctx.add_value_function("pad", |args: &[Value]| Ok(Value::from(format!("{args:?}"))));
+        ctx.add_signature("pad", Signature::new().required("text").optional("width"));
+
+        assert_eq!(
+            ctx.partial_render("{{ pad(text, width=width) }} {{ pad(\"a\", width=width) }}"),
+            Ok(r#"{{ pad(text, width=4) }} [String("a"), Integer(4)]"#.to_owned())
+        );
+    }
+
+    #[test]
+    fn include_render() {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("title", "Home");
+        ctx.add_list("items", ["a", "b"]);
+        ctx.add_template("item", "<li>{{ item }}</li>").unwrap();
+        ctx.add_template(
+            "list",
+            "<ul>{{ for item in items }}{{ include \"item\" }}{{ end }}</ul>",
+        )
+        .unwrap();
+        ctx.add_template("page", "<h1>{{ title }}</h1>{{ include \"list\" }}")
+            .unwrap();
+
+        assert_eq!(
+            ctx.render_named("page").unwrap(),
+            "<h1>Home</h1><ul><li>a</li><li>b</li></ul>"
+        );
+        assert_eq!(
+            render(r#"{{ include "item" }}"#, &ctx),
+            Err(Error::VariableNotFound("item".to_owned()))
+        );
+        // the same template can be included many times as long as it does not include itself
+        ctx.add_variable("part", "list");
+        assert_eq!(
+            render("{{ include part }}{{ include part }}", &ctx).unwrap(),
+            "<ul><li>a</li><li>b</li></ul><ul><li>a</li><li>b</li></ul>"
+        );
+        assert_eq!(
+            render(r#"{{ include "footer" }}"#, &ctx),
+            Err(Error::TemplateNotFound("footer".to_owned()))
+        );
+        assert_eq!(
+            ctx.render_named("footer"),
+            Err(Error::TemplateNotFound("footer".to_owned()))
+        );
+    }
+
+    #[test]
+    fn include_cycle() {
+        let ctx = SrTemplate::default();
+        ctx.add_template("a", r#"a{{ include "b" }}"#).unwrap();
+        ctx.add_template("b", r#"b{{ include "c" }}"#).unwrap();
+        ctx.add_template("c", r#"c{{ include "a" }}"#).unwrap();
+        ctx.add_template("self", r#"{{ include "self" }}"#).unwrap();
+
+        let chain = |names: &[&str]| {
+            Err(Error::IncludeCycle(
+                names.iter().map(|name| (*name).to_owned()).collect(),
+            ))
+        };
+        assert_eq!(ctx.render_named("a"), chain(&["a", "b", "c", "a"]));
+        assert_eq!(
+            render(r#"{{ include "b" }}"#, &ctx),
+            chain(&["b", "c", "a", "b"])
+        );
+        assert_eq!(ctx.render_named("self"), chain(&["self", "self"]));
+        assert_eq!(
+            Error::IncludeCycle(vec!["a".to_owned(), "b".to_owned(), "a".to_owned()]).to_string(),
+            "Include cycle: a -> b -> a"
+        );
+    }
+
+    #[test]
+    fn partial_render_include() {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("title", "Home");
+        ctx.add_template("header", "<h1>{{ title }}</h1>{{ user }}")
+            .unwrap();
+
+        assert_eq!(
+            ctx.partial_render(r#"{{ include "header" }} {{ include name }}"#),
+            Ok("<h1>Home</h1>{{ user }} {{ include name }}".to_owned())
+        );
+    }
+
+    #[test]
+    fn extends_render() {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("user", "ana");
+        ctx.add_function("toUpper", builtin::text::to_upper);
+        ctx.add_template(
+            "base",
+            concat!(
+                "<title>{{ block title }}Site{{ end }}</title>",
+                "{{ block body }}<p>{{ block content }}empty{{ end }}</p>{{ end }}",
+                "{{ include \"footer\" }}",
             ),
-            (
-                Cow::Borrowed("trim"),
-                Box::new(builtin::text::trim as Function),
+        )
+        .unwrap();
+        ctx.add_template("footer", "{{ block title }}footer{{ end }}")
+            .unwrap();
+        ctx.add_template(
+            "page",
+            concat!(
+                "{{ extends \"base\" }}ignored",
+                "{{ block title }}{{ super() }} - Page{{ end }}",
+                "{{ block content }}hello {{ user }}{{ end }}",
             ),
-        ]);
-        let template = "Hello {{ toLowerCase(trim(var)) }}";
-        let tnodes = parser(template, "{{", "}}").unwrap();
-        let mut res = String::new();
+        )
+        .unwrap();
 
-        for node in tnodes.into_iter() {
-            let out = nodes(&mut res, node, &vars, &funcs);
-            assert!(out.is_ok());
-        }
+        assert_eq!(
+            ctx.render_named("base").unwrap(),
+            "<title>Site</title><p>empty</p>footer"
+        );
+        assert_eq!(
+            ctx.render_named("page").unwrap(),
+            "<title>Site - Page</title><p>hello ana</p>footer"
+        );
+        // the blocks of each level replace the ones of the templates it extends
+        assert_eq!(
+            render(
+                concat!(
+                    "{{ extends \"page\" }}",
+                    "{{ block title }}[{{ super() | toUpper }}]{{ end }}",
+                    "{{ block body }}<div>{{ super() }}</div>{{ end }}",
+                ),
+                &ctx
+            )
+            .unwrap(),
+            "<title>[SITE - PAGE]</title><div><p>hello ana</p></div>footer"
+        );
+    }
 
-        assert_eq!(&res, "Hello world");
+    #[test]
+    fn extends_errors() {
+        let ctx = SrTemplate::default();
+        ctx.add_template("a", r#"{{ extends "b" }}"#).unwrap();
+        ctx.add_template("b", r#"{{ extends "a" }}"#).unwrap();
+        ctx.add_template("base", "{{ block title }}{{ super() }}{{ end }}")
+            .unwrap();
+
+        assert_eq!(
+            ctx.render_named("a"),
+            Err(Error::IncludeCycle(vec![
+                "a".to_owned(),
+                "b".to_owned(),
+                "a".to_owned()
+            ]))
+        );
+        assert_eq!(
+            render(r#"{{ extends "missing" }}"#, &ctx),
+            Err(Error::TemplateNotFound("missing".to_owned()))
+        );
+        assert_eq!(
+            ctx.render_named("base"),
+            Err(Error::NoParentBlock("title".to_owned()))
+        );
+        assert_eq!(
+            render(r#"{{ extends "base" }}{{ block title }}x{{ end }}"#, &ctx).unwrap(),
+            "x"
+        );
+        assert_eq!(
+            render("{{ super() }}", &ctx),
+            Err(Error::FunctionNotImplemented("super".to_owned()))
+        );
     }
 
     #[test]
-    fn raw_string_render() {
-        let vars = DashMap::from_iter([(Cow::Borrowed("var"), "    WoRlD".to_string())]);
-        let funcs = DashMap::from_iter([
-            (
-                Cow::Borrowed("toLowerCase"),
-                Box::new(builtin::text::to_lower as Function),
+    fn partial_render_extends() {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("title", "Home");
+
+        assert_eq!(
+            ctx.partial_render(
+                r#"{{ extends layout }}{{ block title }}{{ title }} {{ super() }}{{ end }}"#
             ),
-            (
-                Cow::Borrowed("trim"),
-                Box::new(builtin::text::trim as Function),
+            Ok("{{ extends layout }}{{ block title }}Home {{ super() }}{{ end }}".to_owned())
+        );
+    }
+
+    #[test]
+    fn macro_render() {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("site", "example.com");
+        ctx.add_list("items", ["a", "b"]);
+        ctx.add_function("toUpper", builtin::text::to_upper);
+
+        let template = concat!(
+            r#"{{ macro link(url, text=url | toUpper) }}<a href="{{ site }}/{{ url }}">{{ text }}</a>{{ end }}"#,
+            "{{ for item in items }}{{ link(item) }}{{ end }}|",
+            r#"{{ link("x", text="y") }}|{{ "z" | link }}|{{ link(text="w", url="v") }}"#,
+        );
+        assert_eq!(
+            render(template, &ctx).unwrap(),
+            concat!(
+                r#"<a href="example.com/a">A</a><a href="example.com/b">B</a>|"#,
+                r#"<a href="example.com/x">y</a>|<a href="example.com/z">Z</a>|"#,
+                r#"<a href="example.com/v">w</a>"#,
+            )
+        );
+        // the macros can be called before they are defined and call each other
+        assert_eq!(
+            render(
+                "{{ outer(1) }}{{ macro outer(n) }}[{{ inner(n + 1) }}]{{ end }}{{ macro inner(n) }}{{ n }}{{ end }}",
+                &ctx
+            )
+            .unwrap(),
+            "[2]"
+        );
+        assert_eq!(
+            render("{{ macro m(a) }}{{ a }}{{ end }}{{ m() }}", &ctx),
+            Err(Error::Function(FunctionError::InvalidArgument(
+                "m() is missing the argument \"a\"".to_owned()
+            )))
+        );
+    }
+
+    #[test]
+    fn macro_local_scope() {
+        let mut ctx = SrTemplate::default();
+        ctx.set_undefined_behavior(UndefinedBehavior::Empty);
+        ctx.add_list("items", ["a"]);
+
+        // the macro does not see the loop variable of the caller
+        let template = "{{ macro show() }}[{{ item }}{{ loop.index }}]{{ end }}{{ for item in items }}{{ item }}{{ show() }}{{ item }}{{ end }}";
+        assert_eq!(render(template, &ctx).unwrap(), "a[]a");
+        // and its parameters do not leak into the caller
+        assert_eq!(
+            render("{{ macro m(x) }}{{ x }}{{ end }}{{ m(1) }}{{ x }}", &ctx).unwrap(),
+            "1"
+        );
+        assert!(matches!(
+            render("{{ macro m() }}{{ m() }}{{ end }}{{ m() }}", &ctx),
+            Err(Error::InvalidOperation(_))
+        ));
+    }
+
+    #[test]
+    fn macro_import() {
+        let ctx = SrTemplate::default();
+        ctx.add_template(
+            "forms",
+            concat!(
+                r#"{{ macro field(name, kind="text") }}<input type="{{ kind }}" name="{{ name }}">{{ end }}"#,
+                r#"{{ macro password() }}{{ field("pass", kind="password") }}{{ end }}"#,
             ),
-        ]);
-        let template = r#"Hello
-{{ toLowerCase(trim(var, "  !   ")) }}"#;
-        let tnodes = parser(template, "{{", "}}").unwrap();
-        let mut res = String::new();
+        )
+        .unwrap();
+        ctx.add_template(
+            "base",
+            r#"{{ import "forms" }}<form>{{ block fields }}{{ field("user") }}{{ end }}</form>"#,
+        )
+        .unwrap();
+        ctx.add_template("cycle", r#"{{ import "cycle" }}"#)
+            .unwrap();
 
-        for tnode in tnodes.into_iter() {
-            let out = nodes(&mut res, tnode, &vars, &funcs);
-            assert!(out.is_ok());
-        }
+        assert_eq!(
+            render(r#"{{ import "forms" }}{{ password() }}"#, &ctx).unwrap(),
+            r#"<input type="password" name="pass">"#
+        );
+        // the macros of the extended templates are available to the blocks that replace theirs
+        assert_eq!(
+            render(
+                r#"{{ extends "base" }}{{ block fields }}{{ super() }}{{ password() }}{{ end }}"#,
+                &ctx
+            )
+            .unwrap(),
+            r#"<form><input type="text" name="user"><input type="password" name="pass"></form>"#
+        );
+        // the macros defined by a template replace the imported ones
+        assert_eq!(
+            render(
+                r#"{{ macro field(name) }}{{ name }}{{ end }}{{ import "forms" }}{{ field("a") }}{{ password() }}"#,
+                &ctx
+            )
+            .unwrap(),
+            r#"a<input type="password" name="pass">"#
+        );
+        assert_eq!(
+            ctx.render_named("cycle"),
+            Err(Error::IncludeCycle(vec![
+                "cycle".to_owned(),
+                "cycle".to_owned()
+            ]))
+        );
+        assert_eq!(
+            render(r#"{{ import "missing" }}"#, &ctx),
+            Err(Error::TemplateNotFound("missing".to_owned()))
+        );
+    }
+
+    #[test]
+    fn partial_render_macros() {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("site", "example.com");
+
+        assert_eq!(
+            ctx.partial_render(
+                r#"{{ import "forms" }}{{ macro link(site, text=title) }}{{ site }}{{ title }}{{ end }}{{ link(site) }}"#
+            ),
+            Ok(r#"{{ import "forms" }}{{ macro link(site, text=title) }}{{ site }}{{ title }}{{ end }}{{ link("example.com") }}"#.to_owned())
+        );
+    }
+
+    #[test]
+    fn set_render() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let ctx = SrTemplate::default();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        ctx.add_function("toUpper", move |args: &[String]| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            builtin::text::to_upper(args)
+        });
+        ctx.add_variable("user", "ana");
+        ctx.add_list("items", ["a", "b"]);
+
+        // the value is evaluated once
+        assert_eq!(
+            render("{{ set name = toUpper(user) }}{{ name }} {{ name }}", &ctx).unwrap(),
+            "ANA ANA"
+        );
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+
+        // the variables are dropped after the block that sets them
+        let template = concat!(
+            "{{ set user = \"top\" }}",
+            "{{ if true }}{{ set user = \"if\" }}{{ user }}{{ end }}|{{ user }}|",
+            "{{ for item in items }}{{ user ?? \"-\" }}{{ set user = item }}{{ user }}{{ end }}|",
+            "{{ user }}",
+        );
+        assert_eq!(render(template, &ctx).unwrap(), "if|top|topatopb|top");
+        assert_eq!(
+            render("{{ set n = 1 }}{{ set n = n + 1 }}{{ n }}", &ctx).unwrap(),
+            "2"
+        );
+
+        // the variables of the template are not modified
+        assert_eq!(render("{{ user }}", &ctx).unwrap(), "ana");
+        assert!(ctx.variables.get("name").is_none());
+    }
+
+    #[test]
+    fn set_in_included_templates() {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("x", "global");
+        ctx.add_template("part", "{{ x }}{{ set x = \"part\" }}{{ x }}")
+            .unwrap();
+        ctx.add_template("base", "{{ block a }}{{ set x = \"a\" }}{{ end }}{{ x }}")
+            .unwrap();
+
+        assert_eq!(
+            render(r#"{{ set x = "page" }}{{ include "part" }}{{ x }}"#, &ctx).unwrap(),
+            "pagepartpage"
+        );
+        assert_eq!(ctx.render_named("base").unwrap(), "global");
+        assert_eq!(
+            render(
+                r#"{{ macro m() }}{{ set x = "macro" }}{{ x }}{{ end }}{{ m() }}{{ x }}"#,
+                &ctx
+            )
+            .unwrap(),
+            "macroglobal"
+        );
+    }
+
+    #[test]
+    fn partial_render_set() {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("user", "ana");
+        ctx.add_list("items", ["a"]);
+
+        let residual = ctx
+            .partial_render(concat!(
+                "{{ set name = user }}{{ name }}|",
+                "{{ set list = items }}{{ list }}|",
+                "{{ set other = missing }}{{ other }}{{ set other = 1 }}{{ other }}|",
+                "{{ if x }}{{ set name = 2 }}{{ name }}{{ end }}{{ name }}",
+            ))
+            .unwrap();
+        assert_eq!(
+            residual,
+            concat!(
+                "ana|",
+                "{{ set list = items }}[a]|",
+                "{{ set other = missing }}{{ other }}{{ set other = 1 }}{{ other }}|",
+                "{{ if x }}2{{ end }}ana",
+            )
+        );
+    }
+
+    #[test]
+    fn partial_render_blocks() {
+        let ctx = SrTemplate::default();
+        ctx.add_value("admin", true);
+        ctx.add_list("items", ["a", "b"]);
+        ctx.add_variable("i", "global");
+        ctx.add_variable("text", "{{ not a tag }}");
+
+        let residual = ctx
+            .partial_render(concat!(
+                "{{ if admin }}A{{ end }}{{ if not admin }}B{{ else }}C{{ end }}|",
+                "{{ if user }}{{ user }}{{ elif admin }}{{ i }}{{ else }}none{{ end }}|",
+                "{{ if user }}U{{ elif other }}O{{ end }}|",
+                "{{ for i in items }}{{ i }}{{ suffix }}{{ end }}|",
+                "{{ for i in users }}{{ i }}{{ loop.index }}{{ text }}{{ else }}{{ i }}{{ end }}|",
+                "{{ for i in users }}{{ loop[\"first\"] }}{{ end }}|",
+                "{{ text }}",
+            ))
+            .unwrap();
+        assert_eq!(
+            residual,
+            concat!(
+                "AC|",
+                "{{ if user }}{{ user }}{{ else }}global{{ end }}|",
+                "{{ if user }}U{{ elif other }}O{{ end }}|",
+                "a{{ suffix }}b{{ suffix }}|",
+                r"{{ for i in users }}{{ i }}{{ loop.index }}{{{{ not a tag }}{{ else }}global{{ end }}|",
+                r#"{{ for i in users }}{{ loop["first"] }}{{ end }}|"#,
+                "{{{{ not a tag }}",
+            )
+        );
+
+        let request = SrTemplate::default();
+        request.add_variable("user", "sergio");
+        request.add_variable("suffix", "!");
+        request.add_list("users", ["x"]);
+        assert_eq!(
+            request.render(&residual).unwrap(),
+            "AC|sergio|U|a!b!|x1{{ not a tag }}|true|{{ not a tag }}"
+        );
+    }
+
+    #[test]
+    fn partial_render_custom_delimiters_and_errors() {
+        let ctx = SrTemplate::with_delimiter("<%", "%>");
+        ctx.add_value("count", 0);
 
-        assert_eq!(&res, "Hello\nworld !");
+        assert_eq!(
+            ctx.partial_render("<% count %>-<%count + n%> {{ x }}")
+                .unwrap(),
+            "0-<% 0 + n %> {{ x }}"
+        );
+        assert_eq!(
+            ctx.partial_render("<% n + 1 / count %>"),
+            Err(Error::InvalidOperation("1 / 0 divides by zero".to_owned()))
+        );
     }
 }
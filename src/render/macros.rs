@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::error::Error;
+use crate::parser::TemplateNode;
+use crate::template::Signature;
+use crate::value::Value;
+
+use super::{node, nodes, Scope};
+
+/// How many macro calls can be rendered inside each other, so a macro that calls itself
+/// forever fails instead of overflowing the stack.
+const MAX_DEPTH: usize = 64;
+
+/// The macros that can be called by a template, by their name.
+type Module = HashMap<String, Arc<Macro>>;
+
+/// A macro defined by a template with `{{ macro name(parameters) }}`.
+pub struct Macro {
+    /// The module of the template that defines it, whose macros can be called by the body.
+    module: Option<String>,
+    /// The parameters, the ones with a default value are optional.
+    signature: Signature,
+    /// The default values of the parameters, in the order of the signature.
+    defaults: Vec<Option<TemplateNode<'static>>>,
+    body: Vec<TemplateNode<'static>>,
+}
+
+impl Macro {
+    fn new(module: Option<String>, parameters: &[TemplateNode], body: &[TemplateNode]) -> Self {
+        let mut signature = Signature::new();
+        let mut defaults = Vec::with_capacity(parameters.len());
+        for parameter in parameters {
+            match parameter {
+                TemplateNode::Named(name, default) => {
+                    signature = signature.optional(name.as_ref());
+                    defaults.push(Some(default.as_ref().clone().into_owned()));
+                }
+                TemplateNode::Variable(name) => {
+                    signature = signature.required(name.as_ref());
+                    defaults.push(None);
+                }
+                // the parser only gives variables and named values
+                _ => {}
+            }
+        }
+
+        Self {
+            module,
+            signature,
+            defaults,
+            body: body.iter().cloned().map(TemplateNode::into_owned).collect(),
+        }
+    }
+}
+
+/// The macros available while rendering, grouped in the modules of the templates that
+/// define or import them.
+#[derive(Default)]
+pub struct Macros {
+    /// The modules of the templates, by the name of the template, `None` being the template
+    /// that is rendered without a name.
+    modules: HashMap<Option<String>, Arc<Module>>,
+    /// The modules of the templates and macros being rendered, the last one is the current one.
+    current: Vec<Option<String>>,
+}
+
+impl Scope<'_, '_> {
+    /// Uses the module of the template being rendered until [`Scope::exit_module`], creating
+    /// it from the nodes of the template the first time.
+    pub(super) fn enter_module(&mut self, tnodes: &[TemplateNode]) -> Result<(), Error> {
+        let key = self.name().map(str::to_owned);
+        if !self.macros.modules.contains_key(&key) {
+            let module = self.module(key.clone(), tnodes)?;
+            self.macros.modules.insert(key.clone(), Arc::new(module));
+        }
+
+        self.macros.current.push(key);
+        Ok(())
+    }
+
+    pub(super) fn exit_module(&mut self) {
+        self.macros.current.pop();
+    }
+
+    /// Collects the macros defined at the top level of a template and the ones of the
+    /// templates it imports or extends, the macros it defines replace the other ones.
+    fn module(&mut self, key: Option<String>, tnodes: &[TemplateNode]) -> Result<Module, Error> {
+        let mut module = Module::new();
+        for tnode in tnodes {
+            match tnode {
+                TemplateNode::Macro(name, parameters, body) => {
+                    let definition = Macro::new(key.clone(), parameters, body);
+                    module.insert(name.to_string(), Arc::new(definition));
+                }
+                TemplateNode::Import(name) | TemplateNode::Extends(name) => {
+                    let name = node(name, self)?.to_string();
+                    for (name, definition) in self.import(&name)?.iter() {
+                        module
+                            .entry(name.clone())
+                            .or_insert_with(|| Arc::clone(definition));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(module)
+    }
+
+    /// Returns the module of the registered template `name`.
+    fn import(&mut self, name: &str) -> Result<Arc<Module>, Error> {
+        let key = Some(name.to_owned());
+        if let Some(module) = self.macros.modules.get(&key) {
+            return Ok(Arc::clone(module));
+        }
+
+        let template = self.enter_include(name)?;
+        let module = self.module(key.clone(), template.nodes());
+        self.exit_include();
+
+        let module = Arc::new(module?);
+        self.macros.modules.insert(key, Arc::clone(&module));
+        Ok(module)
+    }
+
+    /// Returns the macro `name` of the current module.
+    pub(super) fn find_macro(&self, name: &str) -> Option<Arc<Macro>> {
+        let key = self.macros.current.last()?;
+        self.macros.modules.get(key)?.get(name).cloned()
+    }
+
+    /// Renders a macro with the arguments of a call, its body only sees its arguments and
+    /// the variables added to the template, and the variables it defines are dropped.
+    pub(super) fn call_macro(
+        &mut self,
+        name: &str,
+        definition: &Macro,
+        positional: Vec<Value>,
+        named: Vec<(String, Value)>,
+    ) -> Result<Value, Error> {
+        if self.macros.current.len() > MAX_DEPTH {
+            return Err(Error::InvalidOperation(format!(
+                "{name}() is called inside of more than {MAX_DEPTH} macros"
+            )));
+        }
+        let arguments = definition.signature.arrange(name, positional, named)?;
+
+        let caller = std::mem::replace(&mut self.locals, vec![HashMap::new()]);
+        let block_stack = std::mem::take(&mut self.block_stack);
+        self.macros.current.push(definition.module.clone());
+        let rendered = self.render_macro(definition, arguments);
+        self.macros.current.pop();
+        self.block_stack = block_stack;
+        self.locals = caller;
+
+        rendered
+    }
+
+    fn render_macro(
+        &mut self,
+        definition: &Macro,
+        arguments: Vec<Option<Value>>,
+    ) -> Result<Value, Error> {
+        let parameters = definition.signature.names().zip(&definition.defaults);
+        for ((parameter, default), argument) in parameters.zip(arguments) {
+            // the defaults can use the parameters before them
+            let value = match (argument, default) {
+                (Some(value), _) => value,
+                (None, Some(default)) => node(default, self)?,
+                (None, None) => Value::Null,
+            };
+            self.locals
+                .last_mut()
+                .expect("macro frame")
+                .insert(parameter.to_owned(), value);
+        }
+
+        let mut res = String::new();
+        for tnode in &definition.body {
+            nodes(&mut res, tnode, self)?;
+        }
+        Ok(Value::String(res))
+    }
+}
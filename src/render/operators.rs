@@ -0,0 +1,184 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::error::Error;
+use crate::parser::Operator;
+use crate::value::Value;
+
+/// A number taken from a value, integers are promoted to floats when they are operated with one.
+#[derive(Clone, Copy)]
+enum Number {
+    Integer(i64),
+    Float(f64),
+}
+
+impl Number {
+    const fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Integer(value) => Some(Self::Integer(*value)),
+            Value::Float(value) => Some(Self::Float(*value)),
+            _ => None,
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    const fn as_float(self) -> f64 {
+        match self {
+            Self::Integer(value) => value as f64,
+            Self::Float(value) => value,
+        }
+    }
+
+    fn compare(self, other: Self) -> Option<Ordering> {
+        match (self, other) {
+            (Self::Integer(lhs), Self::Integer(rhs)) => Some(lhs.cmp(&rhs)),
+            (lhs, rhs) => lhs.as_float().partial_cmp(&rhs.as_float()),
+        }
+    }
+}
+
+/// Floats keep their decimals in the errors, so `1.0 / 0` is not reported as `1 / 0`.
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Integer(value) => write!(f, "{value}"),
+            Self::Float(value) => write!(f, "{value:?}"),
+        }
+    }
+}
+
+/// Applies a binary operator to two evaluated operands.
+///
+/// `and`, `or` and `??` are not applied here, they only evaluate their right operand when it
+/// is needed, see [`super::node`].
+pub fn binary(operator: Operator, lhs: &Value, rhs: &Value) -> Result<Value, Error> {
+    match operator {
+        Operator::Add | Operator::Sub | Operator::Mul | Operator::Div | Operator::Rem => {
+            arithmetic(operator, lhs, rhs)
+        }
+        Operator::Eq => Ok(Value::Bool(equals(lhs, rhs))),
+        Operator::Ne => Ok(Value::Bool(!equals(lhs, rhs))),
+        Operator::Lt | Operator::Le | Operator::Gt | Operator::Ge => {
+            let ordering = compare(lhs, rhs).ok_or_else(|| invalid_operands(operator, lhs, rhs))?;
+
+            Ok(Value::Bool(match operator {
+                Operator::Lt => ordering.is_lt(),
+                Operator::Le => ordering.is_le(),
+                Operator::Gt => ordering.is_gt(),
+                _ => ordering.is_ge(),
+            }))
+        }
+        Operator::And | Operator::Or | Operator::Coalesce | Operator::Not | Operator::Neg => {
+            unreachable!("{operator:?} is not applied to two operands")
+        }
+    }
+}
+
+/// Applies a unary operator to an evaluated operand.
+pub fn unary(operator: Operator, operand: &Value) -> Result<Value, Error> {
+    match (operator, Number::from_value(operand)) {
+        (Operator::Not, _) => Ok(Value::Bool(!operand.is_truthy())),
+        (Operator::Neg, Some(Number::Integer(value))) => value
+            .checked_neg()
+            .map(Value::Integer)
+            .ok_or_else(|| Error::InvalidOperation(format!("-{value} overflows"))),
+        (Operator::Neg, Some(Number::Float(value))) => Ok(Value::Float(-value)),
+        _ => Err(Error::InvalidOperation(format!(
+            "cannot apply \"{operator}\" to {}",
+            operand.type_name()
+        ))),
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn arithmetic(operator: Operator, lhs: &Value, rhs: &Value) -> Result<Value, Error> {
+    let (Some(a), Some(b)) = (Number::from_value(lhs), Number::from_value(rhs)) else {
+        return match (operator, lhs, rhs) {
+            (Operator::Add, Value::List(a), Value::List(b)) => {
+                Ok(Value::List(a.iter().chain(b).cloned().collect()))
+            }
+            (Operator::Add, Value::String(_), _) | (Operator::Add, _, Value::String(_)) => {
+                Ok(Value::String(format!("{lhs}{rhs}")))
+            }
+            _ => Err(invalid_operands(operator, lhs, rhs)),
+        };
+    };
+
+    if matches!(operator, Operator::Div | Operator::Rem) && b.as_float() == 0.0 {
+        return Err(Error::InvalidOperation(format!(
+            "{a} {operator} {b} divides by zero"
+        )));
+    }
+
+    match (a, b) {
+        (Number::Integer(a), Number::Integer(b)) => {
+            let result = match operator {
+                Operator::Add => a.checked_add(b),
+                Operator::Sub => a.checked_sub(b),
+                Operator::Mul => a.checked_mul(b),
+                // an inexact division keeps its decimals
+                Operator::Div if a.checked_rem(b).is_some_and(|rest| rest != 0) => {
+                    return Ok(Value::Float(a as f64 / b as f64));
+                }
+                Operator::Div => a.checked_div(b),
+                _ => a.checked_rem(b),
+            };
+
+            result
+                .map(Value::Integer)
+                .ok_or_else(|| Error::InvalidOperation(format!("{a} {operator} {b} overflows")))
+        }
+        (a, b) => {
+            let (x, y) = (a.as_float(), b.as_float());
+            let result = match operator {
+                Operator::Add => x + y,
+                Operator::Sub => x - y,
+                Operator::Mul => x * y,
+                Operator::Div => x / y,
+                _ => x % y,
+            };
+
+            if result.is_finite() {
+                Ok(Value::Float(result))
+            } else {
+                Err(Error::InvalidOperation(format!(
+                    "{a} {operator} {b} overflows"
+                )))
+            }
+        }
+    }
+}
+
+/// Numbers are equal by their value, so `1 == 1.0`, a text is equal to a value with the
+/// same text, so `"true" == true`, and any other values are equal if they are the same.
+fn equals(lhs: &Value, rhs: &Value) -> bool {
+    if let (Some(a), Some(b)) = (Number::from_value(lhs), Number::from_value(rhs)) {
+        return a.compare(b) == Some(Ordering::Equal);
+    }
+
+    match (lhs, rhs) {
+        (Value::Null, _) | (_, Value::Null) => lhs == rhs,
+        (Value::String(text), other) | (other, Value::String(text)) => *text == other.to_string(),
+        _ => lhs == rhs,
+    }
+}
+
+/// Only numbers and texts can be ordered.
+fn compare(lhs: &Value, rhs: &Value) -> Option<Ordering> {
+    if let (Some(a), Some(b)) = (Number::from_value(lhs), Number::from_value(rhs)) {
+        return a.compare(b);
+    }
+
+    match (lhs, rhs) {
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+fn invalid_operands(operator: Operator, lhs: &Value, rhs: &Value) -> Error {
+    Error::InvalidOperation(format!(
+        "cannot apply \"{operator}\" to {} and {}",
+        lhs.type_name(),
+        rhs.type_name()
+    ))
+}
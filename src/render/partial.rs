@@ -0,0 +1,413 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::error::Error;
+use crate::parser::{Operator, Syntax, TemplateNode};
+use crate::value::Value;
+
+use super::{
+    access, access_path, call, default_arguments, extended, iterable_values, node, number_text,
+    operators, Scope, LOOP_VARIABLE,
+};
+
+/// The result of partially evaluating an expression.
+enum Reduced<'t> {
+    /// Everything the expression uses is available, so it has been evaluated.
+    Value(Value),
+    /// The expression with the parts that could be evaluated replaced by their values.
+    Residual(TemplateNode<'t>),
+}
+
+impl<'t> Reduced<'t> {
+    /// Converts the result into an expression, the values that can not be written as a
+    /// literal, like lists and maps, are replaced by the `original` expression.
+    fn into_node(self, original: &TemplateNode<'t>) -> TemplateNode<'t> {
+        match (self, original) {
+            // the value of a named argument keeps its name
+            (Self::Value(value), TemplateNode::Named(name, named)) => {
+                TemplateNode::Named(name.clone(), Box::new(Self::Value(value).into_node(named)))
+            }
+            (Self::Value(value), _) => literal(value).unwrap_or_else(|| original.clone()),
+            (Self::Residual(residual), _) => residual,
+        }
+    }
+}
+
+/// Renders the nodes that only use the available variables and functions, and writes the
+/// other ones back as template source, see
+/// [`SrTemplate::partial_render`](crate::SrTemplate::partial_render).
+pub fn partial_render(
+    tnodes: &[TemplateNode],
+    scope: &mut Scope,
+    syntax: &Syntax,
+    capacity: usize,
+) -> Result<String, Error> {
+    let mut partial = Partial {
+        scope,
+        unknown: Vec::new(),
+    };
+    let residual = partial.nodes(tnodes)?;
+
+    let mut res = String::with_capacity(capacity);
+    for tnode in &residual {
+        tnode.write_source(&mut res, syntax);
+    }
+    Ok(res)
+}
+
+struct Partial<'s, 'r, 'a> {
+    scope: &'s mut Scope<'r, 'a>,
+    /// The variables of the loops that are not rendered and the ones set to an unknown
+    /// value, they shadow the other variables.
+    unknown: Vec<String>,
+}
+
+impl Partial<'_, '_, '_> {
+    fn nodes<'t>(&mut self, tnodes: &[TemplateNode<'t>]) -> Result<Vec<TemplateNode<'t>>, Error> {
+        let mut res = Vec::with_capacity(tnodes.len());
+        for tnode in tnodes {
+            self.node(&mut res, tnode)?;
+        }
+        Ok(res)
+    }
+
+    /// Partially renders the body of a block, the variables it sets are dropped after it.
+    fn body<'t>(&mut self, tnodes: &[TemplateNode<'t>]) -> Result<Vec<TemplateNode<'t>>, Error> {
+        let unknown = self.unknown.len();
+        self.scope.locals.push(HashMap::new());
+        let body = self.nodes(tnodes);
+        self.scope.locals.pop();
+        self.unknown.truncate(unknown);
+        body
+    }
+
+    /// Checks if a variable, or the variable a path of keys starts with, is unknown.
+    fn shadowed(&self, name: &str) -> bool {
+        self.unknown.iter().any(|unknown| {
+            name.strip_prefix(unknown.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+        })
+    }
+
+    fn node<'t>(
+        &mut self,
+        res: &mut Vec<TemplateNode<'t>>,
+        tnode: &TemplateNode<'t>,
+    ) -> Result<(), Error> {
+        match tnode {
+            TemplateNode::RawText(_) => res.push(tnode.clone()),
+            TemplateNode::Number(text) | TemplateNode::Float(text) => {
+                res.push(TemplateNode::RawText(text.clone()));
+            }
+            TemplateNode::If(branches, otherwise) => {
+                let mut residual = Vec::new();
+                for (condition, body) in branches {
+                    match self.reduce(condition)? {
+                        Reduced::Value(value) if !value.is_truthy() => {}
+                        Reduced::Value(_) if residual.is_empty() => {
+                            res.extend(self.body(body)?);
+                            return Ok(());
+                        }
+                        // the previous conditions are unknown, so this is their `else`
+                        Reduced::Value(_) => {
+                            let body = self.body(body)?;
+                            res.push(TemplateNode::If(residual, Some(body)));
+                            return Ok(());
+                        }
+                        Reduced::Residual(condition) => {
+                            residual.push((condition, self.body(body)?));
+                        }
+                    }
+                }
+
+                let otherwise = match otherwise {
+                    Some(body) => Some(self.body(body)?),
+                    None => None,
+                };
+                if residual.is_empty() {
+                    res.extend(otherwise.into_iter().flatten());
+                } else {
+                    res.push(TemplateNode::If(residual, otherwise));
+                }
+            }
+            TemplateNode::For(item, iterable, body, otherwise) => match self.reduce(iterable)? {
+                Reduced::Value(value) => {
+                    let values = iterable_values(value, iterable)?;
+                    if values.is_empty() {
+                        if let Some(otherwise) = otherwise {
+                            res.extend(self.body(otherwise)?);
+                        }
+                        return Ok(());
+                    }
+
+                    let length = values.len();
+                    self.scope.locals.push(HashMap::with_capacity(6));
+                    for (index, value) in values.into_iter().enumerate() {
+                        self.scope.set_loop_variables(item, value, index, length);
+
+                        if let Err(e) = body.iter().try_for_each(|tnode| self.node(res, tnode)) {
+                            self.scope.locals.pop();
+                            return Err(e);
+                        }
+                    }
+                    self.scope.locals.pop();
+                }
+                Reduced::Residual(iterable) => {
+                    let shadowed = self.unknown.len();
+                    self.unknown.push(item.to_string());
+                    self.unknown.push(LOOP_VARIABLE.to_owned());
+                    let body = self.body(body);
+                    self.unknown.truncate(shadowed);
+
+                    let otherwise = match otherwise {
+                        Some(body) => Some(self.body(body)?),
+                        None => None,
+                    };
+                    res.push(TemplateNode::For(
+                        item.clone(),
+                        Box::new(iterable),
+                        body?,
+                        otherwise,
+                    ));
+                }
+            },
+            TemplateNode::Include(name) => match self.reduce(name)? {
+                Reduced::Value(value) => {
+                    let template = self.scope.enter_include(&value.to_string())?;
+                    // the blocks of a template that extends another one can not be inlined
+                    if extended(template.nodes()).is_some() {
+                        self.scope.exit_include();
+                        let name = Reduced::Value(value).into_node(name);
+                        res.push(TemplateNode::Include(Box::new(name)));
+                        return Ok(());
+                    }
+                    let included = template
+                        .nodes()
+                        .iter()
+                        .try_for_each(|tnode| self.node(res, tnode));
+                    self.scope.exit_include();
+                    included?;
+                }
+                Reduced::Residual(name) => res.push(TemplateNode::Include(Box::new(name))),
+            },
+            // the blocks are kept, so the templates that extend the residual can replace them
+            TemplateNode::Extends(name) => {
+                let name = self.reduce(name)?.into_node(name);
+                res.push(TemplateNode::Extends(Box::new(name)));
+            }
+            TemplateNode::Block(name, body) => {
+                let body = self.body(body)?;
+                res.push(TemplateNode::Block(name.clone(), body));
+            }
+            // the macros are not called, so they are kept with the parameters unknown
+            TemplateNode::Macro(name, parameters, body) => {
+                let shadowed = self.unknown.len();
+                self.unknown
+                    .extend(parameters.iter().filter_map(|parameter| match parameter {
+                        TemplateNode::Variable(name) | TemplateNode::Named(name, _) => {
+                            Some(name.to_string())
+                        }
+                        _ => None,
+                    }));
+                let body = self.body(body);
+                self.unknown.truncate(shadowed);
+                res.push(TemplateNode::Macro(name.clone(), parameters.clone(), body?));
+            }
+            TemplateNode::Import(name) => {
+                let name = self.reduce(name)?.into_node(name);
+                res.push(TemplateNode::Import(Box::new(name)));
+            }
+            // the variable is kept when its uses can not be replaced by its value
+            TemplateNode::Set(name, value) => {
+                let shadowed = self.shadowed(name);
+                match self.reduce(value)? {
+                    Reduced::Value(reduced) => {
+                        let written = shadowed || literal(reduced.clone()).is_none();
+                        self.scope.set_local(name, reduced.clone());
+                        if written {
+                            let value = Reduced::Value(reduced).into_node(value);
+                            res.push(TemplateNode::Set(name.clone(), Box::new(value)));
+                        }
+                    }
+                    Reduced::Residual(residual) => {
+                        self.unknown.push(name.to_string());
+                        res.push(TemplateNode::Set(name.clone(), Box::new(residual)));
+                    }
+                }
+            }
+            // the residual is written back from its nodes, see `partial_render`
+            TemplateNode::Tag(_, tnode) => self.node(res, tnode)?,
+            _ => match self.reduce(tnode)? {
+                Reduced::Value(value) => res.push(TemplateNode::RawText(value.to_string().into())),
+                Reduced::Residual(residual) => res.push(residual),
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Evaluates the parts of an expression that only use the available variables and
+    /// functions.
+    fn reduce<'t>(&mut self, tnode: &TemplateNode<'t>) -> Result<Reduced<'t>, Error> {
+        Ok(match tnode {
+            TemplateNode::Variable(name) => {
+                // the keys of an unknown variable are unknown too
+                let value = if self.shadowed(name) {
+                    None
+                } else {
+                    self.scope.resolve(name)?
+                };
+                value.map_or_else(|| Reduced::Residual(tnode.clone()), Reduced::Value)
+            }
+            TemplateNode::Function(function, arguments, span)
+                if self.scope.is_default_function(function) =>
+            {
+                default_arguments(arguments)?;
+
+                let mut residual = Vec::new();
+                for (index, argument) in arguments.iter().enumerate() {
+                    match self.reduce(argument)? {
+                        Reduced::Value(value)
+                            if residual.is_empty()
+                                && (!value.is_null() || index + 1 == arguments.len()) =>
+                        {
+                            return Ok(Reduced::Value(value));
+                        }
+                        Reduced::Value(Value::Null) if index + 1 < arguments.len() => {}
+                        reduced => {
+                            let last = matches!(reduced, Reduced::Value(_));
+                            residual.push(reduced.into_node(argument));
+                            if last {
+                                break;
+                            }
+                        }
+                    }
+                }
+
+                Reduced::Residual(TemplateNode::Function(
+                    function.clone(),
+                    residual,
+                    span.clone(),
+                ))
+            }
+            TemplateNode::Function(function, arguments, span) => {
+                let mut values = Vec::with_capacity(arguments.len());
+                let mut residual = Vec::new();
+                for argument in arguments {
+                    match self.reduce(argument)? {
+                        Reduced::Value(value) if residual.is_empty() => values.push(value),
+                        reduced => residual.push(reduced.into_node(argument)),
+                    }
+                }
+
+                if residual.is_empty()
+                    && self
+                        .scope
+                        .template
+                        .functions
+                        .contains_key(function.as_ref())
+                {
+                    let as_written = self.scope.is_text_function(function);
+                    let mut positional = Vec::with_capacity(values.len());
+                    let mut named = Vec::new();
+                    for (value, argument) in values.into_iter().zip(arguments) {
+                        let value = match number_text(argument) {
+                            Some(text) if as_written => Value::from(text),
+                            _ => value,
+                        };
+                        match argument {
+                            TemplateNode::Named(name, _) => named.push((name.to_string(), value)),
+                            _ => positional.push(value),
+                        }
+                    }
+                    Reduced::Value(call(self.scope, function, positional, named, span)?)
+                } else {
+                    // the arguments evaluated before the first unknown one are written as literals
+                    let evaluated = values
+                        .into_iter()
+                        .zip(arguments)
+                        .map(|(value, argument)| Reduced::Value(value).into_node(argument));
+                    Reduced::Residual(TemplateNode::Function(
+                        function.clone(),
+                        evaluated.chain(residual).collect(),
+                        span.clone(),
+                    ))
+                }
+            }
+            TemplateNode::Named(name, value) => match self.reduce(value)? {
+                Reduced::Value(value) => Reduced::Value(value),
+                Reduced::Residual(residual) => {
+                    Reduced::Residual(TemplateNode::Named(name.clone(), Box::new(residual)))
+                }
+            },
+            TemplateNode::Binary(operator, lhs, rhs) => {
+                let reduced_lhs = self.reduce(lhs)?;
+                match (operator, reduced_lhs) {
+                    (Operator::And, Reduced::Value(value)) if !value.is_truthy() => {
+                        Reduced::Value(value)
+                    }
+                    (Operator::Or, Reduced::Value(value)) if value.is_truthy() => {
+                        Reduced::Value(value)
+                    }
+                    (Operator::Coalesce, Reduced::Value(value)) if !value.is_null() => {
+                        Reduced::Value(value)
+                    }
+                    (Operator::And | Operator::Or | Operator::Coalesce, Reduced::Value(_)) => {
+                        self.reduce(rhs)?
+                    }
+                    (operator, reduced_lhs) => match (reduced_lhs, self.reduce(rhs)?) {
+                        (Reduced::Value(lhs), Reduced::Value(rhs)) => {
+                            Reduced::Value(operators::binary(*operator, &lhs, &rhs)?)
+                        }
+                        (reduced_lhs, reduced_rhs) => Reduced::Residual(TemplateNode::Binary(
+                            *operator,
+                            Box::new(reduced_lhs.into_node(lhs)),
+                            Box::new(reduced_rhs.into_node(rhs)),
+                        )),
+                    },
+                }
+            }
+            TemplateNode::Unary(operator, operand) => match self.reduce(operand)? {
+                Reduced::Value(value) => Reduced::Value(operators::unary(*operator, &value)?),
+                Reduced::Residual(residual) => {
+                    Reduced::Residual(TemplateNode::Unary(*operator, Box::new(residual)))
+                }
+            },
+            TemplateNode::Index(value, index) => match (self.reduce(value)?, self.reduce(index)?) {
+                (Reduced::Value(value), Reduced::Value(index)) => {
+                    Reduced::Value(access(&value, &index, || access_path(tnode))?)
+                }
+                (reduced_value, reduced_index) => Reduced::Residual(TemplateNode::Index(
+                    Box::new(reduced_value.into_node(value)),
+                    Box::new(reduced_index.into_node(index)),
+                )),
+            },
+            TemplateNode::Member(value, name) => match self.reduce(value)? {
+                Reduced::Value(value) => {
+                    Reduced::Value(access(&value, &Value::from(name.as_ref()), || {
+                        access_path(tnode)
+                    })?)
+                }
+                Reduced::Residual(residual) => {
+                    Reduced::Residual(TemplateNode::Member(Box::new(residual), name.clone()))
+                }
+            },
+            _ => Reduced::Value(node(tnode, self.scope)?),
+        })
+    }
+}
+
+/// Converts a value into the literal that is evaluated into it, if there is one.
+fn literal(value: Value) -> Option<TemplateNode<'static>> {
+    match value {
+        Value::Null => Some(TemplateNode::Null),
+        Value::Bool(value) => Some(TemplateNode::Bool(value)),
+        Value::Integer(value) => Some(TemplateNode::Number(value.to_string().into())),
+        // the debug format keeps the decimal point, so it is parsed as a float again
+        Value::Float(value) if value.is_finite() => {
+            Some(TemplateNode::Float(format!("{value:?}").into()))
+        }
+        Value::String(text) => Some(TemplateNode::String(Cow::Owned(text))),
+        Value::Float(_) | Value::List(_) | Value::Map(_) => None,
+    }
+}
@@ -3,23 +3,71 @@ use dashmap::DashMap;
 use paste::paste;
 use std::borrow::Cow;
 use std::sync::Arc;
+use std::time::SystemTime;
 
 use crate::error::Error;
-use crate::parser::parser;
-use crate::render::nodes;
+use crate::parser::{parse, Syntax, TemplateNode};
+use crate::render::{partial_render, render_template, Scope};
+use crate::value::Value;
 use crate::{builtin, Variable};
 
 #[cfg(feature = "math")]
 use crate::gen_math_use;
 
-use self::function::FuncResult;
+use self::function::{FuncResult, FunctionContext, ValueResult};
+use self::loader::LoadedTemplate;
 
+mod compiled;
 pub mod function;
+mod loader;
+mod signature;
+mod undefined;
 pub mod validations;
 
+pub use compiled::CompiledTemplate;
+pub use loader::{ChainLoader, FileSystemLoader, MemoryLoader, TemplateLoader};
+pub use signature::Signature;
+pub use undefined::UndefinedBehavior;
+
 /// This corresponds to the type for custom functions that may exist.
 pub type Function = fn(&[String]) -> FuncResult;
 
+/// This corresponds to the type for custom functions that work with typed values.
+///
+/// Unlike [`Function`], the arguments are received as they were evaluated, so numbers,
+/// booleans, lists and maps do not need to be parsed from their text, and the returned
+/// [`Value`] keeps its type when it is passed to another function.
+pub type ValueFunction = fn(&[Value]) -> ValueResult;
+
+/// A function registered in a [`SrTemplate`].
+///
+/// They are stored behind an [`Arc`], so closures with their captured state are shared by the
+/// clones of the template and can be taken out of the registry before being called.
+#[derive(Clone)]
+pub(crate) enum Callable {
+    Text(Arc<TextFn>),
+    Value(Arc<ValueFn>),
+    Context(Arc<ContextFn>),
+}
+
+type TextFn = dyn Fn(&[String]) -> FuncResult + Send + Sync;
+type ValueFn = dyn Fn(&[Value]) -> ValueResult + Send + Sync;
+type ContextFn = dyn Fn(&FunctionContext<'_, '_>, &[Value]) -> ValueResult + Send + Sync;
+
+impl Callable {
+    /// Calls the function, the text functions receive the rendered text of each argument.
+    pub fn call(&self, ctx: &FunctionContext, args: &[Value]) -> ValueResult {
+        match self {
+            Self::Text(func) => {
+                let args: Vec<String> = args.iter().map(ToString::to_string).collect();
+                func(&args).map(Value::from_text)
+            }
+            Self::Value(func) => func(args),
+            Self::Context(func) => func(ctx, args),
+        }
+    }
+}
+
 /// This structure is the basis of everything, it is responsible for managing variables and functions.
 ///
 /// # Examples
@@ -38,8 +86,19 @@ pub type Function = fn(&[String]) -> FuncResult;
 pub struct SrTemplate<'a> {
     delimiter_start: Cow<'a, str>,
     delimiter_close: Cow<'a, str>,
-    variables: Arc<DashMap<Cow<'a, str>, String>>,
-    functions: Arc<DashMap<Cow<'a, str>, Box<Function>>>,
+    comment_start: Cow<'a, str>,
+    comment_close: Cow<'a, str>,
+    trim_blocks: bool,
+    lstrip_blocks: bool,
+    pub(crate) undefined: UndefinedBehavior,
+    pub(crate) variables: Arc<DashMap<Cow<'a, str>, Value>>,
+    pub(crate) functions: Arc<DashMap<Cow<'a, str>, Callable>>,
+    pub(crate) signatures: Arc<DashMap<Cow<'a, str>, Arc<Signature>>>,
+    pub(crate) templates: Arc<DashMap<Cow<'a, str>, CompiledTemplate>>,
+    loader: Option<Arc<dyn TemplateLoader>>,
+    auto_reload: bool,
+    /// The templates compiled from the sources of the loader, by their name.
+    loaded: Arc<DashMap<String, LoadedTemplate>>,
 }
 
 impl<'a> SrTemplate<'a> {
@@ -50,11 +109,9 @@ impl<'a> SrTemplate<'a> {
     /// * `start`: The start delimiter. This is a string slice or a type that can be converted into a `Cow<str>`.
     /// * `close`: The end delimiter. This is a string slice or a type that can be converted into a `Cow<str>`.
     pub fn with_delimiter<U: Into<Cow<'a, str>>>(start: U, close: U) -> Self {
-        Self {
-            delimiter_start: start.into(),
-            delimiter_close: close.into(),
-            ..Default::default()
-        }
+        let mut tmp = Self::default();
+        tmp.set_delimiter(start, close);
+        tmp
     }
 
     pub fn add<V: Variable<'a>>(&self, value: V) {
@@ -65,16 +122,44 @@ impl<'a> SrTemplate<'a> {
 
     /// Adds variable that can later be rendered in the template
     ///
+    /// The value is stored as its text, or as a number if its text is written exactly as a
+    /// number is, like `3` or `-1.5`, so `{{ count + 1 }}` adds while a text like `007` is
+    /// kept as it is. A text value added with [`SrTemplate::add_value`] is never a number.
+    ///
     /// # Arguments
     ///
     /// * `name`: Variable name, this name is the one you will use in the template
     /// * `value`: This is the value on which the template will be replaced in the template
     pub fn add_variable<U: Into<Cow<'a, str>>, T: ToString>(&self, name: U, value: T) {
-        let value = value.to_string();
         self.variables
-            .entry(name.into())
-            .and_modify(|old| *old = value.clone())
-            .or_insert_with(|| value.clone());
+            .insert(name.into(), Value::from_text(value.to_string()));
+    }
+
+    /// Adds a typed value that can later be rendered in the template
+    ///
+    /// Unlike [`SrTemplate::add_variable`], the value keeps its type, so it can be iterated
+    /// if it is a list and it is received as is by the functions added with
+    /// [`SrTemplate::add_value_function`].
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: Variable name, this name is the one you will use in the template
+    /// * `value`: Any type that can be converted into a [`Value`]
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use srtemplate::SrTemplate;
+    ///
+    /// let ctx = SrTemplate::default();
+    /// ctx.add_value("admin", false);
+    /// ctx.add_value("roles", ["reader", "writer"]);
+    ///
+    /// let template = "{{ if admin }}admin{{ else }}{{ roles }}{{ end }}";
+    /// assert_eq!(ctx.render(template).unwrap(), "[reader, writer]");
+    /// ```
+    pub fn add_value<U: Into<Cow<'a, str>>, V: Into<Value>>(&self, name: U, value: V) {
+        self.variables.insert(name.into(), value.into());
     }
 
     /// Adds variables that can later be rendered in the template
@@ -92,17 +177,142 @@ impl<'a> SrTemplate<'a> {
         });
     }
 
+    /// Adds a list of values that can later be iterated in the template with a `for` block
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: Variable name, this name is the one you will use in the template
+    /// * `values`: The values that will be assigned to the loop variable on each iteration
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use srtemplate::SrTemplate;
+    ///
+    /// let ctx = SrTemplate::default();
+    /// ctx.add_list("items", ["apple", "banana"]);
+    ///
+    /// let template = "{{ for item in items }}{{ loop.index }}. {{ item }}\n{{ end }}";
+    /// assert_eq!(ctx.render(template).unwrap(), "1. apple\n2. banana\n");
+    /// ```
+    pub fn add_list<U: Into<Cow<'a, str>>, I: IntoIterator<Item = T>, T: Into<Value>>(
+        &self,
+        name: U,
+        values: I,
+    ) {
+        self.add_value(name, values.into_iter().collect::<Value>());
+    }
+
     /// Adds function that can later be rendered in the template
     ///
     /// # Arguments
     ///
     /// * `name`: Function name, this name is the one you will use in the template
+    /// * `func`: This is the function that will be evaluated when it is called from the template,
+    ///   it can be a `fn` item or a closure that captures its own state
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use srtemplate::SrTemplate;
+    ///
+    /// let ctx = SrTemplate::default();
+    /// let prefix = String::from("#");
+    /// let counter = AtomicUsize::new(0);
+    /// ctx.add_function("next", move |_: &[String]| {
+    ///     Ok(format!("{prefix}{}", counter.fetch_add(1, Ordering::Relaxed) + 1))
+    /// });
+    ///
+    /// assert_eq!(ctx.render("{{ next() }} {{ next() }}").unwrap(), "#1 #2");
+    /// ```
+    pub fn add_function<T, F>(&self, name: T, func: F)
+    where
+        T: Into<Cow<'a, str>>,
+        F: Fn(&[String]) -> FuncResult + Send + Sync + 'static,
+    {
+        self.functions
+            .insert(name.into(), Callable::Text(Arc::new(func)));
+    }
+
+    /// Adds function that receives and returns typed values
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: Function name, this name is the one you will use in the template
+    /// * `func`: This is the function that will be evaluated when it is called from the template,
+    ///   it can be a `fn` item or a closure that captures its own state
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use srtemplate::prelude::{SrTemplate, Value, ValueResult};
+    ///
+    /// fn len(args: &[Value]) -> ValueResult {
+    ///     Ok(args.iter().map(|arg| arg.as_list().map_or(0, <[Value]>::len)).sum::<usize>().into())
+    /// }
+    ///
+    /// let ctx = SrTemplate::default();
+    /// ctx.add_list("items", [1, 2, 3]);
+    /// ctx.add_value_function("len", len);
+    ///
+    /// assert_eq!(ctx.render("{{ len(items) }}").unwrap(), "3");
+    /// ```
+    pub fn add_value_function<T, F>(&self, name: T, func: F)
+    where
+        T: Into<Cow<'a, str>>,
+        F: Fn(&[Value]) -> ValueResult + Send + Sync + 'static,
+    {
+        self.functions
+            .insert(name.into(), Callable::Value(Arc::new(func)));
+    }
+
+    /// Adds function that can read the state of the template being rendered
+    ///
+    /// The function receives a [`FunctionContext`] to look up variables, call other functions
+    /// or know the name of the template and where the function is called.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: Function name, this name is the one you will use in the template
     /// * `func`: This is the function that will be evaluated when it is called from the template
-    pub fn add_function<T: Into<Cow<'a, str>>>(&self, name: T, func: Function) {
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use srtemplate::prelude::{FunctionContext, SrTemplate, Value};
+    ///
+    /// let ctx = SrTemplate::default();
+    /// ctx.add_context_function("callIfExists", |ctx: &FunctionContext, args: &[Value]| {
+    ///     let name = args[0].to_string();
+    ///     ctx.call(&name, &args[1..]).unwrap_or(Ok(Value::Null))
+    /// });
+    ///
+    /// let template = r#"{{ callIfExists("toUpper", "a") }}{{ callIfExists("missing") }}"#;
+    /// assert_eq!(ctx.render(template).unwrap(), "A");
+    /// ```
+    pub fn add_context_function<T, F>(&self, name: T, func: F)
+    where
+        T: Into<Cow<'a, str>>,
+        F: Fn(&FunctionContext<'_, '_>, &[Value]) -> ValueResult + Send + Sync + 'static,
+    {
         self.functions
-            .entry(name.into())
-            .and_modify(|old| *old = Box::new(func.clone()))
-            .or_insert_with(|| Box::new(func));
+            .insert(name.into(), Callable::Context(Arc::new(func)));
+    }
+
+    /// Sets the names of the arguments of a function, so it can be called with named
+    /// arguments, see [`Signature`].
+    ///
+    /// The signature belongs to the name, so it is kept when the function is replaced and
+    /// it is removed with [`SrTemplate::remove_function`]. The functions without a signature
+    /// fail when they are called with named arguments.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: Function name, this name is the one you will use in the template
+    /// * `signature`: The arguments of the function
+    pub fn add_signature<T: Into<Cow<'a, str>>>(&self, name: T, signature: Signature) {
+        self.signatures.insert(name.into(), Arc::new(signature));
     }
 
     /// Adds functions that can later be rendered in the template
@@ -120,6 +330,176 @@ impl<'a> SrTemplate<'a> {
         });
     }
 
+    /// Adds a template that can be rendered by its name with [`SrTemplate::render_named`] or
+    /// included by other templates with `{{ include "name" }}`
+    ///
+    /// The template is parsed when it is added, with the delimiters and options set at that
+    /// moment, and it is rendered with the variables of the template that includes it, the
+    /// item of a loop included.
+    ///
+    /// Other templates can extend it with `{{ extends "name" }}`, they are rendered as this
+    /// template with its `{{ block name }}...{{ end }}` blocks replaced by their blocks with
+    /// the same name, which can render the replaced block with `{{ super() }}`. The macros
+    /// defined by this template, `{{ macro name(a, b=1) }}...{{ end }}`, can be called by the
+    /// templates that import it with `{{ import "name" }}` or extend it.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: Template name, this name is the one you will use to render or include it
+    /// * `text`: The source of the template
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use srtemplate::SrTemplate;
+    ///
+    /// let ctx = SrTemplate::default();
+    /// ctx.add_variable("title", "Home");
+    /// ctx.add_template("header", "<h1>{{ title }}</h1>").unwrap();
+    /// ctx.add_template("page", "{{ include \"header\" }}<p>{{ body }}</p>").unwrap();
+    ///
+    /// ctx.add_variable("body", "Welcome");
+    /// assert_eq!(ctx.render_named("page").unwrap(), "<h1>Home</h1><p>Welcome</p>");
+    /// assert_eq!(ctx.render("{{ include \"header\" }}").unwrap(), "<h1>Home</h1>");
+    ///
+    /// ctx.add_template("base", "<b>{{ block main }}base{{ end }}</b>").unwrap();
+    /// let child = "{{ extends \"base\" }}{{ block main }}{{ super() }} child{{ end }}";
+    /// assert_eq!(ctx.render(child).unwrap(), "<b>base child</b>");
+    ///
+    /// ctx.add_template("macros", "{{ macro em(text) }}<em>{{ text }}</em>{{ end }}").unwrap();
+    /// assert_eq!(ctx.render("{{ import \"macros\" }}{{ em(title) }}").unwrap(), "<em>Home</em>");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the syntax of the template is invalid.
+    pub fn add_template<U: Into<Cow<'a, str>>, T: AsRef<str>>(
+        &self,
+        name: U,
+        text: T,
+    ) -> Result<(), Error> {
+        let name = name.into();
+        let template = self.compile(text)?.with_name(name.as_ref());
+        self.templates.insert(name, template);
+        Ok(())
+    }
+
+    /// Renders a template added with [`SrTemplate::add_template`] or found by the loader
+    /// set with [`SrTemplate::set_loader`].
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the template to be rendered.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The template does not exist.
+    /// - A template includes itself, directly or through other templates.
+    /// - A variable or function is not found or fails during processing.
+    pub fn render_named<T: AsRef<str>>(&self, name: T) -> Result<String, Error> {
+        self.find_template(name.as_ref())?.render(self)
+    }
+
+    /// Returns the template added with the name `name`, or the one compiled from the source
+    /// given by the loader, which is compiled once and kept until it is modified if
+    /// reloading is enabled.
+    pub(crate) fn find_template(&self, name: &str) -> Result<CompiledTemplate, Error> {
+        if let Some(template) = self.templates.get(name) {
+            return Ok(template.clone());
+        }
+        let Some(loader) = &self.loader else {
+            return Err(Error::TemplateNotFound(name.to_owned()));
+        };
+
+        let modified: Option<SystemTime> = if self.auto_reload {
+            loader.modified(name)?
+        } else {
+            None
+        };
+        if let Some(loaded) = self.loaded.get(name) {
+            if !self.auto_reload || loaded.modified == modified {
+                return Ok(loaded.template.clone());
+            }
+        }
+
+        let Some(text) = loader.load(name)? else {
+            self.loaded.remove(name);
+            return Err(Error::TemplateNotFound(name.to_owned()));
+        };
+        let template = self.compile(text)?.with_name(name);
+        self.loaded.insert(
+            name.to_owned(),
+            LoadedTemplate {
+                template: template.clone(),
+                modified,
+            },
+        );
+        Ok(template)
+    }
+
+    /// Sets where the templates that are not added with [`SrTemplate::add_template`] are
+    /// taken from when they are rendered by their name, included, extended or imported.
+    ///
+    /// Each template is compiled the first time it is used and kept in a cache shared by
+    /// the clones of this instance, the templates compiled from the previous loader are
+    /// dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `loader` - The [`TemplateLoader`], like a [`FileSystemLoader`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use srtemplate::prelude::{MemoryLoader, SrTemplate};
+    ///
+    /// let mut ctx = SrTemplate::default();
+    /// ctx.set_loader(MemoryLoader::new().with_template("header", "<h1>{{ title }}</h1>"));
+    /// ctx.add_variable("title", "Home");
+    ///
+    /// assert_eq!(ctx.render("{{ include \"header\" }}").unwrap(), "<h1>Home</h1>");
+    /// ```
+    pub fn set_loader<L: TemplateLoader + 'static>(&mut self, loader: L) {
+        self.loader = Some(Arc::new(loader));
+        self.loaded = Arc::default();
+    }
+
+    /// Enables or disables reloading, when it is enabled the modification time of a loaded
+    /// template is checked each time it is used, and the template is compiled again if its
+    /// source changed. It is disabled by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether the templates are reloaded when they are modified.
+    pub fn set_auto_reload(&mut self, enabled: bool) {
+        self.auto_reload = enabled;
+    }
+
+    /// Drops the templates compiled from the sources of the loader, so they are loaded again
+    /// the next time they are used.
+    pub fn clear_template_cache(&self) {
+        self.loaded.clear();
+    }
+
+    /// Checks if a template exists by its name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the template to check.
+    pub fn contains_template<T: Into<Cow<'a, str>>>(&self, name: T) -> bool {
+        self.templates.contains_key(&name.into())
+    }
+
+    /// Removes a template by its name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the template to remove.
+    pub fn remove_template<T: Into<Cow<'a, str>>>(&self, name: T) {
+        self.templates.remove(&name.into());
+    }
+
     /// Checks if a variable exists in the template string by its name.
     ///
     /// # Arguments
@@ -162,7 +542,9 @@ impl<'a> SrTemplate<'a> {
     ///
     /// * `name` - The name of the function to remove.
     pub fn remove_function<T: Into<Cow<'a, str>>>(&self, name: T) {
-        self.functions.remove(&name.into());
+        let name = name.into();
+        self.functions.remove(&name);
+        self.signatures.remove(&name);
     }
 
     /// Clears all variables from the template string.
@@ -173,6 +555,7 @@ impl<'a> SrTemplate<'a> {
     /// Clears all functions from the template string.
     pub fn clear_functions(&self) {
         self.functions.clear();
+        self.signatures.clear();
     }
 
     /// Sets the delimiters for the template string.
@@ -185,13 +568,114 @@ impl<'a> SrTemplate<'a> {
     ///
     /// * `start`: The start delimiter. This is a string slice or a type that can be converted into a `Cow<str>`.
     /// * `close`: The end delimiter. This is a string slice or a type that can be converted into a `Cow<str>`.
+    ///
+    /// The comment delimiters are changed to match, so with `<%` and `%>` the comments are
+    /// written as `<%# ... #%>`.
     pub fn set_delimiter<U: Into<Cow<'a, str>>>(&mut self, start: U, close: U) {
         self.delimiter_start = start.into();
         self.delimiter_close = close.into();
+        self.comment_start = format!("{}#", self.delimiter_start).into();
+        self.comment_close = format!("#{}", self.delimiter_close).into();
+    }
+
+    /// Sets the delimiters of the comments, which are `{{#` and `#}}` by default.
+    ///
+    /// Everything between them is removed from the output, including newlines. Like the
+    /// other tags, a `-` next to a delimiter removes the whitespace on that side of the comment.
+    /// Calling [`SrTemplate::set_delimiter`] afterwards resets them.
+    ///
+    /// # Arguments
+    ///
+    /// * `start`: The delimiter that opens a comment.
+    /// * `close`: The delimiter that closes a comment.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use srtemplate::SrTemplate;
+    ///
+    /// let mut ctx = SrTemplate::default();
+    /// ctx.set_comment_delimiter("<!--", "-->");
+    /// ctx.add_variable("name", "World");
+    ///
+    /// let template = "Hello <!-- the name\n of the user -->{{ name }}";
+    /// assert_eq!(ctx.render(template).unwrap(), "Hello World");
+    /// ```
+    pub fn set_comment_delimiter<U: Into<Cow<'a, str>>>(&mut self, start: U, close: U) {
+        self.comment_start = start.into();
+        self.comment_close = close.into();
+    }
+
+    /// Removes the first newline after a block tag, like `{{ if admin }}` or `{{ end }}`.
+    ///
+    /// This way a block tag alone on its line does not leave an empty line in the output.
+    /// Disabled by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled`: Whether the newline after block tags is removed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use srtemplate::SrTemplate;
+    ///
+    /// let mut ctx = SrTemplate::default();
+    /// ctx.set_trim_blocks(true);
+    /// ctx.set_lstrip_blocks(true);
+    /// ctx.add_list("hosts", ["a", "b"]);
+    ///
+    /// let template = "hosts:\n    {{ for host in hosts }}\n  - {{ host }}\n    {{ end }}\nend";
+    /// assert_eq!(ctx.render(template).unwrap(), "hosts:\n  - a\n  - b\nend");
+    /// ```
+    pub fn set_trim_blocks(&mut self, enabled: bool) {
+        self.trim_blocks = enabled;
+    }
+
+    /// Removes the spaces and tabs from the start of a line to a block tag, like
+    /// `{{ if admin }}` or `{{ end }}`, so block tags can be indented. Disabled by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled`: Whether the indentation before block tags is removed.
+    pub fn set_lstrip_blocks(&mut self, enabled: bool) {
+        self.lstrip_blocks = enabled;
+    }
+
+    /// Sets what happens when a template uses a variable that does not exist, by default the
+    /// rendering fails with [`Error::VariableNotFound`], see [`UndefinedBehavior`].
+    ///
+    /// # Arguments
+    ///
+    /// * `behavior`: The behavior for the variables that are not found.
+    pub fn set_undefined_behavior(&mut self, behavior: UndefinedBehavior) {
+        self.undefined = behavior;
+    }
+
+    pub(crate) fn syntax(&self) -> Syntax<'_> {
+        Syntax {
+            trim_blocks: self.trim_blocks,
+            lstrip_blocks: self.lstrip_blocks,
+            comment: Some((&self.comment_start, &self.comment_close)),
+            ..Syntax::new(&self.delimiter_start, &self.delimiter_close)
+        }
     }
 
     /// Renders a template by replacing variables and processing functions.
     ///
+    /// The items of lists and texts are taken with `items[0]`, counting from the end with a
+    /// negative index, and the keys of maps with `user["name"]` or `user.name`. A variable
+    /// whose name contains dots, like `user.name`, is used before the key of `user`, and
+    /// the [`UndefinedBehavior`] only applies to variables, a missing key or index fails
+    /// with [`Error::KeyNotFound`] or [`Error::IndexOutOfRange`] unless it has a default.
+    ///
+    /// `{{ set name = expr }}` defines a variable for the rest of the block where it is, it
+    /// shadows the variables of this instance without modifying them, so the renders that
+    /// share this instance never see it.
+    ///
+    /// An opening delimiter written twice is text, so `{{{{ name }}` is rendered as
+    /// `{{ name }}`, and `{{ raw }}...{{ endraw }}` renders everything between them as is.
+    ///
     /// # Arguments
     ///
     /// * `text` - A template string to be rendered.
@@ -215,38 +699,160 @@ impl<'a> SrTemplate<'a> {
     /// }
     /// ```
     ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use srtemplate::prelude::SrTemplate;
+    ///
+    /// let ctx = SrTemplate::default();
+    /// ctx.add_value("user", BTreeMap::from([("name", "Sergio")]));
+    /// ctx.add_value("items", ["first", "last"]);
+    ///
+    /// let template = "{{ user.name }}: {{ items[0] }}, {{ items[-1] }} {{ user.role ?? \"guest\" }}";
+    /// assert_eq!(ctx.render(template).unwrap(), "Sergio: first, last guest");
+    ///
+    /// let template = "{{ set name = user.name + \"!\" }}{{ name }} {{ name }}";
+    /// assert_eq!(ctx.render(template).unwrap(), "Sergio! Sergio!");
+    /// ```
+    ///
     /// # Errors
     ///
     /// Returns an error if:
     /// - The syntax of the template is invalid.
     /// - A variable or function is not found or fails during processing.
+    /// - A key or an index is not found, or it is taken from a value without them.
     pub fn render<T: AsRef<str>>(&self, text: T) -> Result<String, Error> {
         let input = text.as_ref();
-        let open_delim = self.delimiter_start.as_ref();
-        let close_delim = self.delimiter_close.as_ref();
-        let mut res = String::with_capacity(input.len());
-        let tnodes = parser(input, open_delim, close_delim)?;
-
-        for var in tnodes {
-            nodes(
-                &mut res,
-                var,
-                self.variables.as_ref(),
-                self.functions.as_ref(),
-            )?;
-        }
+        let tnodes = parse(input, &self.syntax())?;
+
+        self.render_nodes(&tnodes, input.len(), None)
+    }
+
+    /// Renders the parts of a template that only use the variables and functions of this
+    /// instance, and returns the rest as template source, so it can be rendered later by
+    /// another [`SrTemplate`] that has the missing ones.
+    ///
+    /// The tags and blocks that use a variable or function that does not exist are written
+    /// back with the delimiters of this instance. The parts of their expressions that can be
+    /// evaluated are replaced by their values, so a function call with an unknown argument
+    /// keeps the arguments that are known as literals. Lists and maps can not be written as
+    /// literals, so the expressions that give them are kept as they are.
+    ///
+    /// The [`UndefinedBehavior`] of this instance is not used, every variable that does not
+    /// exist is kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - A template string to be partially rendered.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use srtemplate::SrTemplate;
+    ///
+    /// let deploy = SrTemplate::default();
+    /// deploy.add_variable("host", "example.com");
+    ///
+    /// let template = "{{ toUpper(host) }}: {{ if user }}{{ toLower(user, host) }}{{ end }}";
+    /// let residual = deploy.partial_render(template).unwrap();
+    /// assert_eq!(
+    ///     residual,
+    ///     r#"EXAMPLE.COM: {{ if user }}{{ toLower(user, "example.com") }}{{ end }}"#
+    /// );
+    ///
+    /// let request = SrTemplate::default();
+    /// request.add_variable("user", "Sergio");
+    /// assert_eq!(request.render(residual).unwrap(), "EXAMPLE.COM: sergio example.com");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the syntax of the template is invalid, or if a function fails or
+    /// an operation is invalid while evaluating the known parts.
+    pub fn partial_render<T: AsRef<str>>(&self, text: T) -> Result<String, Error> {
+        let input = text.as_ref();
+        let syntax = self.syntax();
+        let tnodes = parse(input, &syntax)?;
+
+        partial_render(&tnodes, &mut Scope::new(self), &syntax, input.len())
+    }
+
+    /// Parses a template once so it can be rendered many times without parsing it again.
+    ///
+    /// The resulting [`CompiledTemplate`] owns its syntax tree, so it does not borrow `text`
+    /// and can be cached or shared between threads. It is parsed with the delimiters of this
+    /// instance, but it can be rendered with any [`SrTemplate`].
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - A template string to be compiled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use srtemplate::SrTemplate;
+    ///
+    /// let ctx = SrTemplate::default();
+    /// let template = ctx.compile("Hello, {{ name }}!").unwrap();
+    ///
+    /// ctx.add_variable("name", &"World");
+    /// assert_eq!(template.render(&ctx).unwrap(), "Hello, World!");
+    ///
+    /// ctx.add_variable("name", &"Rust");
+    /// assert_eq!(template.render(&ctx).unwrap(), "Hello, Rust!");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the syntax of the template is invalid.
+    pub fn compile<T: AsRef<str>>(&self, text: T) -> Result<CompiledTemplate, Error> {
+        let input = text.as_ref();
+        let tnodes = parse(input, &self.syntax())?;
+
+        Ok(CompiledTemplate::new(tnodes, input.len()))
+    }
+
+    pub(crate) fn render_nodes(
+        &self,
+        tnodes: &[TemplateNode],
+        capacity: usize,
+        name: Option<&str>,
+    ) -> Result<String, Error> {
+        let mut res = String::with_capacity(capacity);
+        let mut scope = Scope::new(self).with_name(name);
+
+        render_template(&mut res, tnodes, &mut scope)?;
         Ok(res)
     }
 }
 
 impl Default for SrTemplate<'_> {
     /// Generates an instance with all the builtin functions that are enabled from features
+    ///
+    /// With the `math` feature some functions are also registered without the type suffix:
+    /// `add`, `sub`, `mul`, `div`, `mod`, `pow`, `abs`, `min` and `max` for `i32` and `round`,
+    /// `floor` and `ceil` for `f64`. The `i32` ones only take integers, so `add(1, 2.5)` fails
+    /// with [`FunctionError::InvalidType`](crate::prelude::FunctionError::InvalidType), use
+    /// `add_f64` for decimals.
+    ///
+    /// The unsuffixed `mod`, `pow`, `abs`, `min`, `max`, `round`, `floor` and `ceil` are new
+    /// names. Registering a function with one of those names after `default()` replaces the
+    /// builtin.
     fn default() -> Self {
         let tmp = Self {
             delimiter_start: "{{".into(),
             delimiter_close: "}}".into(),
+            comment_start: "{{#".into(),
+            comment_close: "#}}".into(),
+            trim_blocks: false,
+            lstrip_blocks: false,
+            undefined: UndefinedBehavior::default(),
             variables: Arc::default(),
             functions: Arc::default(),
+            signatures: Arc::default(),
+            templates: Arc::default(),
+            loader: None,
+            auto_reload: false,
+            loaded: Arc::default(),
         };
 
         #[cfg(feature = "os")]
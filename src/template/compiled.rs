@@ -0,0 +1,164 @@
+use std::sync::Arc;
+
+use crate::error::Error;
+use crate::parser::TemplateNode;
+use crate::template::SrTemplate;
+
+/// A template that has already been parsed, created with [`SrTemplate::compile`].
+///
+/// It owns its syntax tree, so it has no lifetime tied to the source text, it is cheap to
+/// clone and it can be shared between threads or stored in a cache. Rendering it skips the
+/// parsing step entirely and only evaluates variables and functions.
+///
+/// # Examples
+/// ```
+/// use srtemplate::SrTemplate;
+///
+/// let ctx = SrTemplate::default();
+/// let template = ctx.compile("Hello {{ toUpper(name) }}").unwrap();
+///
+/// for name in ["world", "rust"] {
+///     ctx.add_variable("name", name);
+///     println!("{}", template.render(&ctx).unwrap());
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct CompiledTemplate {
+    nodes: Arc<[TemplateNode<'static>]>,
+    capacity: usize,
+    name: Option<Arc<str>>,
+}
+
+impl CompiledTemplate {
+    pub(crate) fn new(nodes: Vec<TemplateNode<'_>>, capacity: usize) -> Self {
+        Self {
+            nodes: nodes.into_iter().map(TemplateNode::into_owned).collect(),
+            capacity,
+            name: None,
+        }
+    }
+
+    /// Names the template, the name is available to the functions added with
+    /// [`SrTemplate::add_context_function`] through [`crate::function::FunctionContext::name`].
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the template, usually the path it was loaded from.
+    #[must_use]
+    pub fn with_name<N: Into<Arc<str>>>(mut self, name: N) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Returns the name of the template, if it has one.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub(crate) fn nodes(&self) -> &[TemplateNode<'static>] {
+        &self.nodes
+    }
+
+    /// Renders the template using the variables and functions of `ctx`.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The instance that provides the variables and functions.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` where:
+    /// - `Ok(String)` contains the rendered template as a string.
+    /// - `Err(Error)` contains the details of an error if rendering fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a variable or function is not found or fails during processing.
+    pub fn render(&self, ctx: &SrTemplate<'_>) -> Result<String, Error> {
+        ctx.render_nodes(&self.nodes, self.capacity, self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_many_times() {
+        let ctx = SrTemplate::default();
+        let template = ctx.compile("Hello {{ toLower(var) }}!").unwrap();
+
+        ctx.add_variable("var", "WORLD");
+        assert_eq!(template.render(&ctx).unwrap(), "Hello world!");
+
+        ctx.add_variable("var", "RUST");
+        assert_eq!(template.render(&ctx).unwrap(), "Hello rust!");
+    }
+
+    #[test]
+    fn outlives_source() {
+        let ctx = SrTemplate::default();
+        let template = {
+            let source = String::from("{{ a }}-{{ b }}");
+            ctx.compile(&source).unwrap()
+        };
+
+        ctx.add_variable("a", 1);
+        ctx.add_variable("b", 2);
+        assert_eq!(template.render(&ctx).unwrap(), "1-2");
+    }
+
+    #[test]
+    fn render_with_other_context() {
+        let template = SrTemplate::with_delimiter("${", "}")
+            .compile("Hi ${ name }")
+            .unwrap();
+
+        let ctx = SrTemplate::default();
+        ctx.add_variable("name", "Sergio");
+        assert_eq!(template.render(&ctx).unwrap(), "Hi Sergio");
+    }
+
+    #[test]
+    fn shared_between_threads() {
+        let ctx = SrTemplate::default();
+        ctx.add_variable("var", "World");
+        let template = ctx.compile("Hello {{ var }}").unwrap();
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| assert_eq!(template.render(&ctx).unwrap(), "Hello World"));
+            }
+        });
+    }
+
+    #[test]
+    fn compile_syntax_error() {
+        let ctx = SrTemplate::default();
+
+        assert!(matches!(
+            ctx.compile("Hello {{ var"),
+            Err(Error::BadSyntax(_))
+        ));
+    }
+
+    #[test]
+    fn named_template() {
+        use crate::function::FunctionContext;
+        use crate::Value;
+
+        let ctx = SrTemplate::default();
+        ctx.add_context_function("where", |ctx: &FunctionContext, _: &[Value]| {
+            let span = ctx.span();
+            Ok(format!("{}@{}..{}", ctx.name().unwrap_or("?"), span.start, span.end).into())
+        });
+
+        let template = ctx.compile("at {{ where() }}").unwrap();
+        assert_eq!(template.name(), None);
+        assert_eq!(template.render(&ctx).unwrap(), "at ?@6..13");
+
+        let template = template.with_name("index.html");
+        assert_eq!(template.name(), Some("index.html"));
+        assert_eq!(template.render(&ctx).unwrap(), "at index.html@6..13");
+    }
+}
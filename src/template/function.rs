@@ -1,7 +1,85 @@
+use std::ops::Range;
+
 use thiserror::Error;
 
+use crate::render::Scope;
+use crate::value::Value;
+
 pub type FuncResult = Result<String, Error>;
 
+/// The result of the functions that work with typed values, see [`crate::ValueFunction`].
+pub type ValueResult = Result<Value, Error>;
+
+/// Read access to the state of the template being rendered, received by the functions added
+/// with [`SrTemplate::add_context_function`](crate::SrTemplate::add_context_function).
+///
+/// # Examples
+/// ```
+/// use srtemplate::prelude::{FunctionContext, SrTemplate, Value};
+///
+/// let ctx = SrTemplate::default();
+/// ctx.add_variable("user.name", "Sergio");
+/// ctx.add_context_function("lookup", |ctx: &FunctionContext, args: &[Value]| {
+///     Ok(ctx.variable(&args[0].to_string()).unwrap_or_default())
+/// });
+///
+/// assert_eq!(ctx.render(r#"{{ lookup("user.name") }}"#).unwrap(), "Sergio");
+/// ```
+pub struct FunctionContext<'r, 'a> {
+    scope: &'r Scope<'r, 'a>,
+    span: Range<usize>,
+}
+
+impl<'r, 'a> FunctionContext<'r, 'a> {
+    pub(crate) fn new(scope: &'r Scope<'r, 'a>, span: Range<usize>) -> Self {
+        Self { scope, span }
+    }
+
+    /// Returns the value of a variable, including the ones defined by the template itself
+    /// like the item of a loop, or `None` if it does not exist.
+    ///
+    /// The name can be a path of keys, like `user.address.city`, see
+    /// [`SrTemplate::render`](crate::SrTemplate::render).
+    pub fn variable(&self, name: &str) -> Option<Value> {
+        self.scope.resolve(name).ok().flatten()
+    }
+
+    /// Checks if a variable exists, see [`FunctionContext::variable`].
+    pub fn contains_variable(&self, name: &str) -> bool {
+        self.variable(name).is_some()
+    }
+
+    /// Checks if a function is registered in the template.
+    pub fn contains_function(&self, name: &str) -> bool {
+        self.scope.template().functions.contains_key(name)
+    }
+
+    /// Calls a registered function with already evaluated arguments, the arguments that are
+    /// not given take their default value from the [`Signature`](crate::Signature) of the
+    /// function.
+    ///
+    /// Returns `None` if the function does not exist, otherwise the result of the function.
+    pub fn call(&self, name: &str, args: &[Value]) -> Option<ValueResult> {
+        // cloned out of the registry, so the function can add or call other functions
+        let function = self.scope.template().functions.get(name)?.clone();
+        let result = self
+            .scope
+            .bind_arguments(name, args.to_vec(), Vec::new())
+            .and_then(|args| function.call(self, &args));
+        Some(result)
+    }
+
+    /// Returns the name of the template being rendered, if it has one.
+    pub fn name(&self) -> Option<&str> {
+        self.scope.name()
+    }
+
+    /// Returns the byte range of the function call in the source of the template.
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+}
+
 /// An enumeration representing various errors that can occur while processing functions.
 #[derive(Clone, Debug, Error, PartialEq)]
 pub enum Error {
@@ -15,7 +93,7 @@ pub enum Error {
     #[error("Convert type from arguments failed: {0}")]
     ConvertArgsFailed(#[from] crate::helper::serialize::FromArgsError),
 
-    #[error("This function require {0} arguments, but found {1}")]
+    #[error("This function require {1} arguments, but found {0}")]
     ArgumentsIncomplete(usize, usize),
 
     #[error("Error calling the function: {0}")]
@@ -0,0 +1,513 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use dashmap::DashMap;
+
+use super::CompiledTemplate;
+use crate::error::Error;
+
+/// A source of templates for [`SrTemplate::set_loader`](crate::SrTemplate::set_loader).
+///
+/// The templates that are rendered by their name, included, extended or imported, and that
+/// were not added with [`SrTemplate::add_template`](crate::SrTemplate::add_template), are
+/// taken from the loader the first time they are used and compiled once.
+///
+/// # Examples
+/// ```
+/// use srtemplate::prelude::{Error, SrTemplate, TemplateLoader};
+///
+/// struct Upper;
+///
+/// impl TemplateLoader for Upper {
+///     fn load(&self, name: &str) -> Result<Option<String>, Error> {
+///         Ok(Some(name.to_uppercase()))
+///     }
+/// }
+///
+/// let mut ctx = SrTemplate::default();
+/// ctx.set_loader(Upper);
+/// assert_eq!(ctx.render("{{ include \"hello\" }}").unwrap(), "HELLO");
+/// ```
+pub trait TemplateLoader: Send + Sync {
+    /// Returns the source of the template `name`, or `None` if this loader does not have it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name is not valid for this loader or the template can not be read.
+    fn load(&self, name: &str) -> Result<Option<String>, Error>;
+
+    /// Returns when the template `name` was modified for the last time, so the compiled
+    /// template is replaced when it changes and reloading is enabled with
+    /// [`SrTemplate::set_auto_reload`](crate::SrTemplate::set_auto_reload).
+    ///
+    /// The loaders whose templates never change return `None`, which is the default.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name is not valid for this loader or the template can not be read.
+    fn modified(&self, _name: &str) -> Result<Option<SystemTime>, Error> {
+        Ok(None)
+    }
+}
+
+impl<L: TemplateLoader + ?Sized> TemplateLoader for Arc<L> {
+    fn load(&self, name: &str) -> Result<Option<String>, Error> {
+        (**self).load(name)
+    }
+
+    fn modified(&self, name: &str) -> Result<Option<SystemTime>, Error> {
+        (**self).modified(name)
+    }
+}
+
+impl<L: TemplateLoader + ?Sized> TemplateLoader for Box<L> {
+    fn load(&self, name: &str) -> Result<Option<String>, Error> {
+        (**self).load(name)
+    }
+
+    fn modified(&self, name: &str) -> Result<Option<SystemTime>, Error> {
+        (**self).modified(name)
+    }
+}
+
+/// Loads the templates from the files of one or more directories, the name of a template
+/// is its path relative to the directory, like `emails/welcome.html`.
+///
+/// The directories are searched in the order they are added. The names can not leave the
+/// directories, so names with `..` or absolute paths, and files whose real path is outside
+/// of their directory through a symbolic link, fail with [`Error::InvalidTemplateName`].
+///
+/// # Examples
+/// ```no_run
+/// use srtemplate::prelude::{FileSystemLoader, SrTemplate};
+///
+/// let mut ctx = SrTemplate::default();
+/// ctx.set_loader(FileSystemLoader::new("templates").with_root("themes/default"));
+/// ctx.add_variable("name", "Sergio");
+///
+/// println!("{}", ctx.render_named("emails/welcome.html").unwrap());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct FileSystemLoader {
+    roots: Vec<PathBuf>,
+}
+
+impl FileSystemLoader {
+    /// Creates a loader that searches the templates in `root`.
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self {
+            roots: vec![root.into()],
+        }
+    }
+
+    /// Adds a directory that is searched after the previous ones.
+    #[must_use]
+    pub fn with_root<P: Into<PathBuf>>(mut self, root: P) -> Self {
+        self.roots.push(root.into());
+        self
+    }
+
+    /// Returns the path of the file of the template `name` in the first directory that has it.
+    fn path(&self, name: &str) -> Result<Option<PathBuf>, Error> {
+        let relative = Path::new(name);
+        let valid = !name.is_empty()
+            && relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+        if !valid {
+            return Err(Error::InvalidTemplateName(name.to_owned()));
+        }
+
+        for root in &self.roots {
+            let path = root.join(relative);
+            if !path.is_file() {
+                continue;
+            }
+
+            // a symbolic link can not lead out of the directory
+            let inside = match (path.canonicalize(), root.canonicalize()) {
+                (Ok(path), Ok(root)) => path.starts_with(root),
+                _ => false,
+            };
+            if !inside {
+                return Err(Error::InvalidTemplateName(name.to_owned()));
+            }
+            return Ok(Some(path));
+        }
+        Ok(None)
+    }
+}
+
+impl TemplateLoader for FileSystemLoader {
+    fn load(&self, name: &str) -> Result<Option<String>, Error> {
+        let Some(path) = self.path(name)? else {
+            return Ok(None);
+        };
+        fs::read_to_string(path)
+            .map(Some)
+            .map_err(|e| Error::LoadFailed(name.to_owned(), e.to_string()))
+    }
+
+    fn modified(&self, name: &str) -> Result<Option<SystemTime>, Error> {
+        let Some(path) = self.path(name)? else {
+            return Ok(None);
+        };
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .map(Some)
+            .map_err(|e| Error::LoadFailed(name.to_owned(), e.to_string()))
+    }
+}
+
+/// Keeps the sources of the templates in memory, they can be replaced while it is used
+/// through an [`Arc`], and the time they were inserted is their modification time.
+///
+/// # Examples
+/// ```
+/// use std::sync::Arc;
+/// use srtemplate::prelude::{MemoryLoader, SrTemplate};
+///
+/// let loader = Arc::new(MemoryLoader::new());
+/// loader.insert("greeting", "Hello {{ name }}");
+///
+/// let mut ctx = SrTemplate::default();
+/// ctx.set_loader(Arc::clone(&loader));
+/// ctx.set_auto_reload(true);
+/// ctx.add_variable("name", "world");
+/// assert_eq!(ctx.render_named("greeting").unwrap(), "Hello world");
+///
+/// loader.insert("greeting", "Bye {{ name }}");
+/// assert_eq!(ctx.render_named("greeting").unwrap(), "Bye world");
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryLoader {
+    templates: DashMap<String, (String, SystemTime)>,
+}
+
+impl MemoryLoader {
+    /// Creates a loader without templates.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a template, or replaces the source of the template with the same name.
+    pub fn insert<N: Into<String>, T: Into<String>>(&self, name: N, text: T) {
+        self.templates
+            .insert(name.into(), (text.into(), SystemTime::now()));
+    }
+
+    /// Adds a template, like [`MemoryLoader::insert`].
+    #[must_use]
+    pub fn with_template<N: Into<String>, T: Into<String>>(self, name: N, text: T) -> Self {
+        self.insert(name, text);
+        self
+    }
+
+    /// Removes a template by its name.
+    pub fn remove(&self, name: &str) {
+        self.templates.remove(name);
+    }
+}
+
+impl TemplateLoader for MemoryLoader {
+    fn load(&self, name: &str) -> Result<Option<String>, Error> {
+        Ok(self.templates.get(name).map(|template| template.0.clone()))
+    }
+
+    fn modified(&self, name: &str) -> Result<Option<SystemTime>, Error> {
+        Ok(self.templates.get(name).map(|template| template.1))
+    }
+}
+
+/// Takes each template from the first of its loaders that has it, like the templates of an
+/// application that replace the default ones of a library.
+///
+/// # Examples
+/// ```
+/// use srtemplate::prelude::{ChainLoader, MemoryLoader, SrTemplate};
+///
+/// let defaults = MemoryLoader::new()
+///     .with_template("header", "default header")
+///     .with_template("footer", "default footer");
+/// let custom = MemoryLoader::new().with_template("header", "custom header");
+///
+/// let mut ctx = SrTemplate::default();
+/// ctx.set_loader(ChainLoader::new().with_loader(custom).with_loader(defaults));
+///
+/// let page = "{{ include \"header\" }}, {{ include \"footer\" }}";
+/// assert_eq!(ctx.render(page).unwrap(), "custom header, default footer");
+/// ```
+#[derive(Default)]
+pub struct ChainLoader {
+    loaders: Vec<Box<dyn TemplateLoader>>,
+}
+
+impl ChainLoader {
+    /// Creates a loader without loaders, which has no templates.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a loader that is used for the templates that the previous ones do not have.
+    #[must_use]
+    pub fn with_loader<L: TemplateLoader + 'static>(mut self, loader: L) -> Self {
+        self.loaders.push(Box::new(loader));
+        self
+    }
+}
+
+impl TemplateLoader for ChainLoader {
+    fn load(&self, name: &str) -> Result<Option<String>, Error> {
+        for loader in &self.loaders {
+            if let Some(text) = loader.load(name)? {
+                return Ok(Some(text));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the modification time given by the loader that [`ChainLoader::load`] takes the
+    /// template from, which is found by loading it.
+    fn modified(&self, name: &str) -> Result<Option<SystemTime>, Error> {
+        for loader in &self.loaders {
+            if loader.load(name)?.is_some() {
+                return loader.modified(name);
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// A template compiled from the source given by a loader.
+#[derive(Clone)]
+pub(crate) struct LoadedTemplate {
+    pub template: CompiledTemplate,
+    /// The modification time of the source it was compiled from.
+    pub modified: Option<SystemTime>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use super::*;
+    use crate::SrTemplate;
+
+    /// A directory for the files of a test, removed when it is dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("srtemplate-{name}-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        fn write(&self, name: &str, text: &str) -> PathBuf {
+            let path = self.0.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, text).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn file_system_loader() {
+        let dir = TempDir::new("roots");
+        dir.write("app/page.html", "app page");
+        dir.write("app/emails/welcome.txt", "welcome");
+        dir.write("theme/page.html", "theme page");
+        dir.write("theme/footer.html", "theme footer");
+
+        let loader = FileSystemLoader::new(dir.0.join("app")).with_root(dir.0.join("theme"));
+        assert_eq!(loader.load("page.html"), Ok(Some("app page".to_owned())));
+        assert_eq!(
+            loader.load("footer.html"),
+            Ok(Some("theme footer".to_owned()))
+        );
+        assert_eq!(
+            loader.load("./emails/welcome.txt"),
+            Ok(Some("welcome".to_owned()))
+        );
+        assert_eq!(loader.load("missing.html"), Ok(None));
+        assert_eq!(loader.load("emails"), Ok(None));
+        assert!(loader.modified("page.html").unwrap().is_some());
+        assert_eq!(loader.modified("missing.html"), Ok(None));
+    }
+
+    #[test]
+    fn file_system_loader_traversal() {
+        let dir = TempDir::new("traversal");
+        let secret = dir.write("secret.txt", "secret");
+        dir.write("root/page.html", "page");
+
+        let loader = FileSystemLoader::new(dir.0.join("root"));
+        for name in [
+            "../secret.txt",
+            "a/../../secret.txt",
+            "",
+            secret.to_str().unwrap(),
+        ] {
+            assert_eq!(
+                loader.load(name),
+                Err(Error::InvalidTemplateName(name.to_owned()))
+            );
+        }
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&secret, dir.0.join("root/link.txt")).unwrap();
+            assert_eq!(
+                loader.load("link.txt"),
+                Err(Error::InvalidTemplateName("link.txt".to_owned()))
+            );
+        }
+    }
+
+    #[test]
+    fn memory_and_chain_loaders() {
+        let memory = MemoryLoader::new().with_template("a", "memory a");
+        let empty = ChainLoader::new();
+        assert_eq!(empty.load("a"), Ok(None));
+
+        let dir = TempDir::new("chain");
+        dir.write("a", "file a");
+        dir.write("b", "file b");
+        let chain = ChainLoader::new()
+            .with_loader(memory)
+            .with_loader(FileSystemLoader::new(&dir.0));
+
+        assert_eq!(chain.load("a"), Ok(Some("memory a".to_owned())));
+        assert_eq!(chain.load("b"), Ok(Some("file b".to_owned())));
+        assert_eq!(chain.load("c"), Ok(None));
+        assert!(chain.load("../a").is_err());
+    }
+
+    #[test]
+    fn chain_loader_modified() {
+        /// Has no templates but gives a modification time for any name.
+        struct Stamps;
+
+        impl TemplateLoader for Stamps {
+            fn load(&self, _name: &str) -> Result<Option<String>, Error> {
+                Ok(None)
+            }
+
+            fn modified(&self, _name: &str) -> Result<Option<SystemTime>, Error> {
+                Ok(Some(SystemTime::UNIX_EPOCH))
+            }
+        }
+
+        let memory = MemoryLoader::new().with_template("a", "memory a");
+        let inserted = memory.modified("a").unwrap();
+        let chain = ChainLoader::new().with_loader(Stamps).with_loader(memory);
+
+        // the time is the one of the loader that has the template
+        assert_eq!(chain.modified("a"), Ok(inserted));
+        assert_eq!(chain.modified("b"), Ok(None));
+    }
+
+    #[test]
+    fn loaded_templates_render() {
+        let dir = TempDir::new("render");
+        dir.write("base.html", "<main>{{ block body }}{{ end }}</main>");
+        dir.write("forms.html", "{{ macro field(name) }}[{{ name }}]{{ end }}");
+        dir.write(
+            "pages/home.html",
+            "{{ extends \"base.html\" }}{{ import \"forms.html\" }}{{ block body }}{{ field(title) }}{{ end }}",
+        );
+
+        let mut ctx = SrTemplate::default();
+        ctx.set_loader(FileSystemLoader::new(&dir.0));
+        ctx.add_variable("title", "Home");
+        ctx.add_template("forms.html", "{{ macro field(name) }}<{{ name }}>{{ end }}")
+            .unwrap();
+
+        // the templates that are added are used before the ones of the loader
+        assert_eq!(
+            ctx.render_named("pages/home.html").unwrap(),
+            "<main><Home></main>"
+        );
+        assert_eq!(
+            ctx.render_named("missing.html"),
+            Err(Error::TemplateNotFound("missing.html".to_owned()))
+        );
+        assert_eq!(
+            ctx.render("{{ include \"../secret\" }}"),
+            Err(Error::InvalidTemplateName("../secret".to_owned()))
+        );
+    }
+
+    #[test]
+    fn loaded_templates_are_cached() {
+        struct Counting(AtomicUsize);
+
+        impl TemplateLoader for Counting {
+            fn load(&self, name: &str) -> Result<Option<String>, Error> {
+                self.0.fetch_add(1, Ordering::Relaxed);
+                Ok((name == "item").then(|| "{{ item }}".to_owned()))
+            }
+        }
+
+        let loader = Arc::new(Counting(AtomicUsize::new(0)));
+        let mut ctx = SrTemplate::default();
+        ctx.set_loader(Arc::clone(&loader));
+        ctx.add_list("items", ["a", "b", "c"]);
+
+        let template = "{{ for item in items }}{{ include \"item\" }}{{ end }}";
+        assert_eq!(ctx.render(template).unwrap(), "abc");
+        assert_eq!(ctx.render(template).unwrap(), "abc");
+        assert_eq!(loader.0.load(Ordering::Relaxed), 1);
+
+        ctx.clear_template_cache();
+        assert_eq!(ctx.render(template).unwrap(), "abc");
+        assert_eq!(loader.0.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn auto_reload() {
+        let dir = TempDir::new("reload");
+        let path = dir.write("page.html", "first");
+
+        let mut ctx = SrTemplate::default();
+        ctx.set_loader(FileSystemLoader::new(&dir.0));
+        assert_eq!(ctx.render_named("page.html").unwrap(), "first");
+
+        let rewrite = |text: &str, seconds: u64| {
+            fs::write(&path, text).unwrap();
+            let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(seconds);
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        };
+
+        // the compiled template is kept until reloading is enabled
+        rewrite("second", 1_000);
+        assert_eq!(ctx.render_named("page.html").unwrap(), "first");
+
+        ctx.set_auto_reload(true);
+        assert_eq!(ctx.render_named("page.html").unwrap(), "second");
+        rewrite("third", 2_000);
+        assert_eq!(ctx.render_named("page.html").unwrap(), "third");
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            ctx.render_named("page.html"),
+            Err(Error::TemplateNotFound("page.html".to_owned()))
+        );
+    }
+}
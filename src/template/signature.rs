@@ -0,0 +1,263 @@
+use super::function::Error;
+use crate::value::Value;
+
+/// The names of the arguments of a function, so it can be called with named arguments like
+/// `pad(name, width=10)`, set with [`SrTemplate::add_signature`](crate::SrTemplate::add_signature).
+///
+/// The named arguments are moved to the position of their name, the arguments that are
+/// not given take their default value, and the optional ones without a default are `null`,
+/// or they are not passed at all if no argument after them is given. So the function
+/// still receives its arguments by position.
+///
+/// # Examples
+/// ```
+/// use srtemplate::prelude::{Signature, SrTemplate, Value, ValueResult};
+///
+/// fn pad(args: &[Value]) -> ValueResult {
+///     let width = args[1].as_integer().unwrap_or_default() as usize;
+///     let fill = args[2].to_string();
+///     let text = args[0].to_string();
+///     Ok(Value::String(format!("{}{text}", fill.repeat(width.saturating_sub(text.len())))))
+/// }
+///
+/// let ctx = SrTemplate::default();
+/// ctx.add_variable("name", "ab");
+/// ctx.add_value_function("pad", pad);
+/// ctx.add_signature(
+///     "pad",
+///     Signature::new().required("text").with_default("width", 4).with_default("fill", "."),
+/// );
+///
+/// assert_eq!(ctx.render("{{ pad(name) }}").unwrap(), "..ab");
+/// assert_eq!(ctx.render(r#"{{ pad(name, fill="*") }}"#).unwrap(), "**ab");
+/// assert_eq!(ctx.render(r#"{{ name | pad(fill="-", width=3) }}"#).unwrap(), "-ab");
+/// assert!(ctx.render("{{ pad(width=3) }}").is_err());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Signature {
+    parameters: Vec<Parameter>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Parameter {
+    name: String,
+    kind: ParameterKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum ParameterKind {
+    Required,
+    Optional,
+    Default(Value),
+}
+
+impl Signature {
+    /// Creates a signature without arguments.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an argument that must be given.
+    #[must_use]
+    pub fn required<T: Into<String>>(self, name: T) -> Self {
+        self.parameter(name, ParameterKind::Required)
+    }
+
+    /// Adds an argument that can be omitted.
+    #[must_use]
+    pub fn optional<T: Into<String>>(self, name: T) -> Self {
+        self.parameter(name, ParameterKind::Optional)
+    }
+
+    /// Adds an argument that takes `value` when it is omitted.
+    #[must_use]
+    pub fn with_default<T: Into<String>, V: Into<Value>>(self, name: T, value: V) -> Self {
+        self.parameter(name, ParameterKind::Default(value.into()))
+    }
+
+    fn parameter<T: Into<String>>(mut self, name: T, kind: ParameterKind) -> Self {
+        self.parameters.push(Parameter {
+            name: name.into(),
+            kind,
+        });
+        self
+    }
+
+    /// Returns the names of the arguments, in the order the function receives them.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.parameters
+            .iter()
+            .map(|parameter| parameter.name.as_str())
+    }
+
+    /// Moves the named arguments of a call to `function` to their positions and fills the
+    /// arguments that are not given.
+    pub(crate) fn bind(
+        &self,
+        function: &str,
+        positional: Vec<Value>,
+        named: Vec<(String, Value)>,
+    ) -> Result<Vec<Value>, Error> {
+        let bound = self.arrange(function, positional, named)?;
+
+        // the optional arguments at the end are not passed, so the function knows they are omitted
+        let given = self
+            .parameters
+            .iter()
+            .zip(&bound)
+            .rposition(|(parameter, value)| {
+                value.is_some() || parameter.kind != ParameterKind::Optional
+            })
+            .map_or(0, |last| last + 1);
+
+        Ok(self
+            .parameters
+            .iter()
+            .zip(bound)
+            .take(given)
+            .map(|(parameter, value)| {
+                value.unwrap_or_else(|| match &parameter.kind {
+                    ParameterKind::Default(value) => value.clone(),
+                    _ => Value::Null,
+                })
+            })
+            .collect())
+    }
+
+    /// Moves the named arguments of a call to `function` to their positions, leaving `None`
+    /// in the positions of the optional arguments that are not given.
+    pub(crate) fn arrange(
+        &self,
+        function: &str,
+        positional: Vec<Value>,
+        named: Vec<(String, Value)>,
+    ) -> Result<Vec<Option<Value>>, Error> {
+        if positional.len() > self.parameters.len() {
+            return Err(Error::InvalidArgument(format!(
+                "{function}() takes {} arguments, but {} were given",
+                self.parameters.len(),
+                positional.len()
+            )));
+        }
+
+        let mut bound: Vec<Option<Value>> = positional.into_iter().map(Some).collect();
+        bound.resize(self.parameters.len(), None);
+        for (name, value) in named {
+            let index = self
+                .parameters
+                .iter()
+                .position(|parameter| parameter.name == name)
+                .ok_or_else(|| {
+                    Error::InvalidArgument(format!("{function}() has no argument \"{name}\""))
+                })?;
+            if bound[index].is_some() {
+                return Err(Error::InvalidArgument(format!(
+                    "{function}() got the argument \"{name}\" twice"
+                )));
+            }
+            bound[index] = Some(value);
+        }
+
+        let missing = self
+            .parameters
+            .iter()
+            .zip(&bound)
+            .find(|(parameter, value)| {
+                value.is_none() && parameter.kind == ParameterKind::Required
+            });
+        if let Some((parameter, _)) = missing {
+            return Err(Error::InvalidArgument(format!(
+                "{function}() is missing the argument \"{}\"",
+                parameter.name
+            )));
+        }
+        Ok(bound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signature() -> Signature {
+        Signature::new()
+            .required("text")
+            .optional("width")
+            .with_default("fill", " ")
+            .optional("align")
+    }
+
+    #[test]
+    fn bind_named_arguments() {
+        let named = |pairs: &[(&str, Value)]| {
+            pairs
+                .iter()
+                .map(|(name, value)| ((*name).to_owned(), value.clone()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            signature().bind("pad", vec![Value::from("a")], Vec::new()),
+            Ok(vec![Value::from("a"), Value::Null, Value::from(" ")])
+        );
+        assert_eq!(
+            signature().bind(
+                "pad",
+                vec![Value::from("a")],
+                named(&[("align", Value::from("left")), ("width", Value::from(3))])
+            ),
+            Ok(vec![
+                Value::from("a"),
+                Value::from(3),
+                Value::from(" "),
+                Value::from("left")
+            ])
+        );
+        assert_eq!(
+            signature().bind("pad", Vec::new(), named(&[("text", Value::from("a"))])),
+            Ok(vec![Value::from("a"), Value::Null, Value::from(" ")])
+        );
+    }
+
+    #[test]
+    fn bind_errors() {
+        assert_eq!(
+            signature().bind(
+                "pad",
+                Vec::new(),
+                vec![("width".to_owned(), Value::from(3))]
+            ),
+            Err(Error::InvalidArgument(
+                "pad() is missing the argument \"text\"".to_owned()
+            ))
+        );
+        assert_eq!(
+            signature().bind(
+                "pad",
+                vec![Value::from("a")],
+                vec![("size".to_owned(), Value::from(3))]
+            ),
+            Err(Error::InvalidArgument(
+                "pad() has no argument \"size\"".to_owned()
+            ))
+        );
+        assert_eq!(
+            signature().bind(
+                "pad",
+                vec![Value::from("a")],
+                vec![("text".to_owned(), Value::from("b"))]
+            ),
+            Err(Error::InvalidArgument(
+                "pad() got the argument \"text\" twice".to_owned()
+            ))
+        );
+        assert_eq!(
+            Signature::new()
+                .required("a")
+                .bind("f", vec![Value::Null, Value::Null], Vec::new()),
+            Err(Error::InvalidArgument(
+                "f() takes 1 arguments, but 2 were given".to_owned()
+            ))
+        );
+    }
+}
@@ -0,0 +1,70 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::value::Value;
+
+/// What happens when a template uses a variable that does not exist, set with
+/// [`SrTemplate::set_undefined_behavior`](crate::SrTemplate::set_undefined_behavior).
+///
+/// The variables that are not found are still replaced by the right operand of `??` or the
+/// last argument of `default` with every behavior. A missing key of a map or index of a
+/// list is not an undefined variable, it fails with
+/// [`Error::KeyNotFound`](crate::Error::KeyNotFound) or
+/// [`Error::IndexOutOfRange`](crate::Error::IndexOutOfRange) with every behavior.
+///
+/// # Examples
+/// ```
+/// use srtemplate::prelude::{SrTemplate, UndefinedBehavior, Value};
+///
+/// let mut ctx = SrTemplate::default();
+/// ctx.add_variable("name", "Sergio");
+///
+/// ctx.set_undefined_behavior(UndefinedBehavior::Empty);
+/// assert_eq!(ctx.render("{{ name }}: {{ role }}").unwrap(), "Sergio: ");
+///
+/// ctx.set_undefined_behavior(UndefinedBehavior::Keep);
+/// assert_eq!(ctx.render("{{ name }}: {{role}}").unwrap(), "Sergio: {{role}}");
+///
+/// ctx.set_undefined_behavior(UndefinedBehavior::hook(|name| Some(Value::from(name.len()))));
+/// assert_eq!(ctx.render("{{ name }}: {{ role }}").unwrap(), "Sergio: 4");
+/// ```
+#[derive(Clone, Default)]
+pub enum UndefinedBehavior {
+    /// Fails with [`Error::VariableNotFound`](crate::Error::VariableNotFound), the default.
+    #[default]
+    Strict,
+    /// The variables that are not found are `null`, which is rendered as an empty text.
+    Empty,
+    /// The tags that use a variable that is not found are written to the output as template
+    /// source, blocks included, so the output can be rendered again when the variable exists.
+    ///
+    /// The tags are written as they are in the template, trim markers included, only the
+    /// whitespace those markers removed around the tag is not written back.
+    Keep,
+    /// Calls the function with the name of the variable that is not found, the value it
+    /// returns is used instead, or it fails like [`UndefinedBehavior::Strict`] if it returns `None`.
+    Hook(Arc<UndefinedFn>),
+}
+
+type UndefinedFn = dyn Fn(&str) -> Option<Value> + Send + Sync;
+
+impl UndefinedBehavior {
+    /// Creates an [`UndefinedBehavior::Hook`] with the function.
+    pub fn hook<F>(hook: F) -> Self
+    where
+        F: Fn(&str) -> Option<Value> + Send + Sync + 'static,
+    {
+        Self::Hook(Arc::new(hook))
+    }
+}
+
+impl fmt::Debug for UndefinedBehavior {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Strict => f.write_str("Strict"),
+            Self::Empty => f.write_str("Empty"),
+            Self::Keep => f.write_str("Keep"),
+            Self::Hook(_) => f.write_str("Hook(..)"),
+        }
+    }
+}
@@ -0,0 +1,362 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// A typed value that can be stored as a variable, passed to functions and rendered.
+///
+/// Values are rendered with their [`Display`](fmt::Display) implementation, which writes
+/// numbers, booleans and texts exactly as their `to_string` would, so templates that
+/// used to receive the text of a variable produce the same output.
+///
+/// # Examples
+/// ```
+/// use srtemplate::prelude::{SrTemplate, Value};
+///
+/// let ctx = SrTemplate::default();
+/// ctx.add_value("count", 3);
+/// ctx.add_value("price", 9.5);
+/// ctx.add_value("tags", vec!["a", "b"]);
+///
+/// assert_eq!(Value::from(3).to_string(), "3");
+/// assert_eq!(ctx.render("{{ count }} x {{ price }}").unwrap(), "3 x 9.5");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Value {
+    /// The absence of a value, rendered as an empty text.
+    #[default]
+    Null,
+    /// A boolean, rendered as `true` or `false`.
+    Bool(bool),
+    /// A signed integer.
+    Integer(i64),
+    /// A floating point number.
+    Float(f64),
+    /// A text.
+    String(String),
+    /// A list of values, rendered as `[a, b]`.
+    List(Vec<Value>),
+    /// A map of values sorted by key, rendered as `{key: value}`.
+    Map(BTreeMap<String, Value>),
+}
+
+impl Value {
+    /// Checks if the value allows the body of a conditional block to be rendered.
+    ///
+    /// `null`, `false`, zero and empty lists or maps are falsy. Texts are falsy when they
+    /// are empty, `"false"` or `"0"`, which are the texts of the values that used to be
+    /// stored for empty texts, `false` and zero.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Self::Null => false,
+            Self::Bool(value) => *value,
+            Self::Integer(value) => *value != 0,
+            Self::Float(value) => *value != 0.0,
+            Self::String(value) => !matches!(value.as_str(), "" | "false" | "0"),
+            Self::List(values) => !values.is_empty(),
+            Self::Map(values) => !values.is_empty(),
+        }
+    }
+
+    /// Returns the name of the type of the value, used by the error messages.
+    pub const fn type_name(&self) -> &'static str {
+        match self {
+            Self::Null => "null",
+            Self::Bool(_) => "bool",
+            Self::Integer(_) => "integer",
+            Self::Float(_) => "float",
+            Self::String(_) => "text",
+            Self::List(_) => "list",
+            Self::Map(_) => "map",
+        }
+    }
+
+    /// Returns `true` if the value is [`Value::Null`].
+    pub const fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    /// Returns the boolean if the value is a [`Value::Bool`].
+    pub const fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the integer if the value is a [`Value::Integer`].
+    pub const fn as_integer(&self) -> Option<i64> {
+        match self {
+            Self::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the number if the value is a [`Value::Float`] or a [`Value::Integer`].
+    #[allow(clippy::cast_precision_loss)]
+    pub const fn as_float(&self) -> Option<f64> {
+        match self {
+            Self::Float(value) => Some(*value),
+            Self::Integer(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+
+    /// Returns the text if the value is a [`Value::String`].
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Converts the text of a variable added with
+    /// [`SrTemplate::add_variable`](crate::SrTemplate::add_variable) or the result of a text
+    /// function into a value.
+    ///
+    /// A text written exactly as a number is written, like `3` or `-1.5`, is that number, so
+    /// the operators use it as one wherever it goes. Any other text, like `007`, `1e3` or
+    /// ` 3`, stays a text and is rendered as it was given.
+    pub(crate) fn from_text(text: String) -> Self {
+        if let Ok(value) = text.parse::<i64>() {
+            if value.to_string() == text {
+                return Self::Integer(value);
+            }
+        }
+        if let Ok(value) = text.parse::<f64>() {
+            if value.is_finite() && value.to_string() == text {
+                return Self::Float(value);
+            }
+        }
+
+        Self::String(text)
+    }
+
+    /// Returns the values if the value is a [`Value::List`].
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match self {
+            Self::List(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Returns the entries if the value is a [`Value::Map`].
+    pub const fn as_map(&self) -> Option<&BTreeMap<String, Value>> {
+        match self {
+            Self::Map(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Null => Ok(()),
+            Self::Bool(value) => value.fmt(f),
+            Self::Integer(value) => value.fmt(f),
+            Self::Float(value) => value.fmt(f),
+            Self::String(value) => f.write_str(value),
+            Self::List(values) => {
+                f.write_str("[")?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    value.fmt(f)?;
+                }
+                f.write_str("]")
+            }
+            Self::Map(values) => {
+                f.write_str("{")?;
+                for (index, (key, value)) in values.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{key}: {value}")?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+macro_rules! from_integer {
+    ($( $t: ty ),*) => {
+        $(
+            impl From<$t> for Value {
+                fn from(value: $t) -> Self {
+                    Self::Integer(value.into())
+                }
+            }
+        )*
+    };
+    (@try $( $t: ty ),*) => {
+        $(
+            /// Integers that do not fit in an `i64` are kept as their text.
+            impl From<$t> for Value {
+                fn from(value: $t) -> Self {
+                    i64::try_from(value).map_or_else(|_| Self::String(value.to_string()), Self::Integer)
+                }
+            }
+        )*
+    };
+}
+
+from_integer!(i8, i16, i32, i64, u8, u16, u32);
+from_integer!(@try isize, usize, u64, i128, u128);
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<f32> for Value {
+    /// Keeps the shortest text of the `f32`, so `5.025f32` is rendered as `5.025`.
+    fn from(value: f32) -> Self {
+        Self::Float(value.to_string().parse().unwrap_or_else(|_| value.into()))
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<char> for Value {
+    fn from(value: char) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<&String> for Value {
+    fn from(value: &String) -> Self {
+        Self::String(value.clone())
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_owned())
+    }
+}
+
+impl From<Cow<'_, str>> for Value {
+    fn from(value: Cow<'_, str>) -> Self {
+        Self::String(value.into_owned())
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Null, Into::into)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(values: Vec<T>) -> Self {
+        values.into_iter().collect()
+    }
+}
+
+impl<T: Into<Value>, const N: usize> From<[T; N]> for Value {
+    fn from(values: [T; N]) -> Self {
+        values.into_iter().collect()
+    }
+}
+
+impl<T: Into<Value> + Clone> From<&[T]> for Value {
+    fn from(values: &[T]) -> Self {
+        values.iter().cloned().collect()
+    }
+}
+
+impl<K: Into<String>, V: Into<Value>> From<BTreeMap<K, V>> for Value {
+    fn from(values: BTreeMap<K, V>) -> Self {
+        values.into_iter().collect()
+    }
+}
+
+impl<K: Into<String>, V: Into<Value>, S> From<HashMap<K, V, S>> for Value {
+    fn from(values: HashMap<K, V, S>) -> Self {
+        values.into_iter().collect()
+    }
+}
+
+impl<T: Into<Value>> FromIterator<T> for Value {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::List(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<K: Into<String>, V: Into<Value>> FromIterator<(K, V)> for Value {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self::Map(
+            iter.into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        assert_eq!(Value::Null.to_string(), "");
+        assert_eq!(Value::from(true).to_string(), "true");
+        assert_eq!(Value::from(-42).to_string(), "-42");
+        assert_eq!(Value::from(0.25).to_string(), "0.25");
+        assert_eq!(Value::from(5.025f32).to_string(), "5.025");
+        assert_eq!(Value::from(85u8).to_string(), 85u8.to_string());
+        assert_eq!(Value::from(u128::MAX).to_string(), u128::MAX.to_string());
+        assert_eq!(Value::from("text").to_string(), "text");
+        assert_eq!(Value::from(vec![1, 2]).to_string(), "[1, 2]");
+        assert_eq!(
+            Value::from(BTreeMap::from([("b", 2), ("a", 1)])).to_string(),
+            "{a: 1, b: 2}"
+        );
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(Value::from(Some(1)), Value::Integer(1));
+        assert_eq!(Value::from(None::<i32>), Value::Null);
+        assert_eq!(Value::from(u64::MAX), Value::String(u64::MAX.to_string()));
+        assert_eq!(
+            Value::from(["a", "b"]),
+            Value::List(vec![Value::from("a"), Value::from("b")])
+        );
+        assert_eq!(
+            Value::from(HashMap::from([("key", true)])),
+            Value::Map(BTreeMap::from([("key".to_string(), Value::Bool(true))]))
+        );
+    }
+
+    #[test]
+    fn truthiness() {
+        assert!(Value::from("true").is_truthy());
+        assert!(Value::from("1").is_truthy());
+        assert!(Value::from("0.0").is_truthy());
+        assert!(Value::from(" ").is_truthy());
+        assert!(Value::from(-1).is_truthy());
+        assert!(Value::from(vec![0]).is_truthy());
+        assert!(!Value::from("").is_truthy());
+        assert!(!Value::from("false").is_truthy());
+        assert!(!Value::from("0").is_truthy());
+        assert!(!Value::Null.is_truthy());
+        assert!(!Value::from(false).is_truthy());
+        assert!(!Value::from(0).is_truthy());
+        assert!(!Value::from(0.0).is_truthy());
+        assert!(!Value::from(Vec::<Value>::new()).is_truthy());
+    }
+}